@@ -0,0 +1,257 @@
+use crate::archean::json::{Blueprint, Segment};
+
+/// Size of one grid unit (a block or occupancy cell) in meters.
+pub const GRID: f64 = 0.25;
+
+pub type Point = [f64; 3];
+
+/// Colors used for blocks that have none, by material.
+const MATERIAL_COLORS: [[u8; 4]; 8] = [
+    [128, 128, 128, 255],
+    [170, 170, 175, 255],
+    [92, 92, 96, 255],
+    [196, 160, 110, 255],
+    [150, 80, 60, 255],
+    [70, 110, 150, 255],
+    [200, 200, 200, 128],
+    [60, 60, 60, 255],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Rgba(pub [u8; 4]);
+
+impl Rgba {
+    /// A color as blueprints store them, packed as `0xAARRGGBB`.
+    pub fn from_packed(packed: i64) -> Rgba {
+        let [a, r, g, b] = (packed as u32).to_be_bytes();
+        Rgba([r, g, b, a])
+    }
+
+    pub fn from_material(material: i64) -> Rgba {
+        Rgba(MATERIAL_COLORS[material.rem_euclid(MATERIAL_COLORS.len() as i64) as usize])
+    }
+
+    pub fn hex(&self) -> String {
+        format!("{:02x}{:02x}{:02x}", self.0[0], self.0[1], self.0[2])
+    }
+}
+
+/// What a [`GridBox`] belongs to, by index in the blueprint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Part {
+    Block(usize),
+    Component(usize),
+}
+
+/// A box on the build grid: a block, or a cell range a component occupies.
+#[derive(Debug, Clone)]
+pub struct GridBox {
+    pub part: Part,
+    /// lowest corner, in grid units
+    pub min: [i64; 3],
+    pub size: [i64; 3],
+    pub color: Rgba,
+    /// blocks only
+    pub material: Option<i64>,
+}
+
+impl GridBox {
+    /// Highest corner, in grid units (exclusive).
+    pub fn max(&self) -> [i64; 3] {
+        [0, 1, 2].map(|i| self.min[i] + self.size[i])
+    }
+
+    /// Corners in meters.
+    pub fn bounds(&self) -> (Point, Point) {
+        (self.min.map(|v| v as f64 * GRID), self.max().map(|v| v as f64 * GRID))
+    }
+}
+
+/// A straight piece of pipe.
+#[derive(Debug, Clone)]
+pub struct PipeRun {
+    /// index of the pipe in the blueprint
+    pub pipe: usize,
+    pub start: Point,
+    pub end: Point,
+    pub radius: f64,
+    pub color: Rgba,
+}
+
+/// Unit vector of a segment's `dir`: +x, -x, +y, -y, +z, -z.
+pub fn direction(dir: i64) -> Option<Point> {
+    match dir {
+        0 => Some([1.0, 0.0, 0.0]),
+        1 => Some([-1.0, 0.0, 0.0]),
+        2 => Some([0.0, 1.0, 0.0]),
+        3 => Some([0.0, -1.0, 0.0]),
+        4 => Some([0.0, 0.0, 1.0]),
+        5 => Some([0.0, 0.0, -1.0]),
+        _ => None,
+    }
+}
+
+pub fn segment_start(segment: &Segment) -> Point {
+    [segment.start.x, segment.start.y, segment.start.z]
+}
+
+/// Where a segment ends, or `None` if its direction is unknown.
+pub fn segment_end(segment: &Segment) -> Option<Point> {
+    let start = segment_start(segment);
+    direction(segment.dir).map(|d| [0, 1, 2].map(|i| start[i] + d[i] * segment.length))
+}
+
+/// The blocks of a blueprint, colored by their first face color, or by material if they have none.
+///
+/// Blocks on other frames (`frame_*`) are placed as if they were on the main one.
+pub fn blocks(blueprint: &Blueprint) -> Vec<GridBox> {
+    blueprint.data.blocks.iter().enumerate().map(|(index, b)| GridBox {
+        part: Part::Block(index),
+        min: [b.pos_x, b.pos_y, b.pos_z],
+        size: [b.size_x, b.size_y, b.size_z],
+        color: b.colors.first().map(|c| Rgba::from_packed(*c)).unwrap_or_else(|| Rgba::from_material(b.material)),
+        material: Some(b.material),
+    }).collect()
+}
+
+/// The cells every component occupies, colored by the component's first color.
+pub fn occupancies(blueprint: &Blueprint) -> Vec<GridBox> {
+    blueprint.data.components.iter().enumerate().flat_map(|(index, c)| {
+        let color = c.colors.first().map(|c| Rgba::from_packed(*c)).unwrap_or(Rgba([160, 160, 160, 255]));
+        c.occupancies.iter().map(move |o| GridBox {
+            part: Part::Component(index),
+            min: [o.pos_x, o.pos_y, o.pos_z],
+            size: [o.size_x, o.size_y, o.size_z],
+            color,
+            material: None,
+        })
+    }).collect()
+}
+
+/// Every pipe segment with a known direction, in meters.
+pub fn pipe_runs(blueprint: &Blueprint) -> Vec<PipeRun> {
+    blueprint.data.pipes.iter().enumerate().flat_map(|(index, p)| {
+        p.segments.iter().filter_map(move |s| Some(PipeRun {
+            pipe: index,
+            start: segment_start(s),
+            end: segment_end(s)?,
+            radius: p.radius,
+            color: Rgba([s.r.clamp(0, 255) as u8, s.g.clamp(0, 255) as u8, s.b.clamp(0, 255) as u8, s.a.clamp(0, 255) as u8]),
+        }))
+    }).collect()
+}
+
+/// A triangle mesh with one color.
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub name: String,
+    pub color: Rgba,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+fn to_f32(p: Point) -> [f32; 3] {
+    p.map(|v| v as f32)
+}
+
+fn normalize(v: Point) -> Point {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if length == 0.0 { v } else { v.map(|c| c / length) }
+}
+
+fn cross(a: Point, b: Point) -> Point {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+impl Mesh {
+    pub fn new(name: &str, color: Rgba) -> Mesh {
+        Mesh { name: name.to_string(), color, positions: vec![], normals: vec![], triangles: vec![] }
+    }
+
+    /// Add a quad with corners in counter-clockwise order seen from outside.
+    fn quad(&mut self, corners: [Point; 4], normal: Point) {
+        let first = self.positions.len() as u32;
+        for corner in corners {
+            self.positions.push(to_f32(corner));
+            self.normals.push(to_f32(normal));
+        }
+        self.triangles.push([first, first + 1, first + 2]);
+        self.triangles.push([first, first + 2, first + 3]);
+    }
+
+    /// Add an axis-aligned box, with flat faces.
+    pub fn cuboid(&mut self, min: Point, max: Point) {
+        let [x0, y0, z0] = min;
+        let [x1, y1, z1] = max;
+        self.quad([[x1, y0, z0], [x1, y1, z0], [x1, y1, z1], [x1, y0, z1]], [1.0, 0.0, 0.0]);
+        self.quad([[x0, y0, z1], [x0, y1, z1], [x0, y1, z0], [x0, y0, z0]], [-1.0, 0.0, 0.0]);
+        self.quad([[x0, y1, z0], [x0, y1, z1], [x1, y1, z1], [x1, y1, z0]], [0.0, 1.0, 0.0]);
+        self.quad([[x0, y0, z0], [x1, y0, z0], [x1, y0, z1], [x0, y0, z1]], [0.0, -1.0, 0.0]);
+        self.quad([[x0, y0, z1], [x1, y0, z1], [x1, y1, z1], [x0, y1, z1]], [0.0, 0.0, 1.0]);
+        self.quad([[x1, y0, z0], [x0, y0, z0], [x0, y1, z0], [x1, y1, z0]], [0.0, 0.0, -1.0]);
+    }
+
+    /// Add a closed cylinder from `start` to `end`.
+    pub fn cylinder(&mut self, start: Point, end: Point, radius: f64, sides: usize) {
+        let axis = normalize([0, 1, 2].map(|i| end[i] - start[i]));
+        // any vector not parallel to the axis gives the plane of the circle
+        let helper = if axis[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+        let u = normalize(cross(axis, helper));
+        let v = cross(axis, u);
+        let around = |i: usize| {
+            let angle = i as f64 / sides as f64 * std::f64::consts::TAU;
+            [0, 1, 2].map(|k| u[k] * angle.cos() + v[k] * angle.sin())
+        };
+        let at = |center: Point, direction: Point| [0, 1, 2].map(|k| center[k] + direction[k] * radius);
+
+        for i in 0..sides {
+            let (a, b) = (around(i), around(i + 1));
+            let first = self.positions.len() as u32;
+            for (point, normal) in [(at(start, a), a), (at(start, b), b), (at(end, b), b), (at(end, a), a)] {
+                self.positions.push(to_f32(point));
+                self.normals.push(to_f32(normal));
+            }
+            self.triangles.push([first, first + 1, first + 2]);
+            self.triangles.push([first, first + 2, first + 3]);
+        }
+        for (center, normal) in [(start, axis.map(|c| -c)), (end, axis)] {
+            let first = self.positions.len() as u32;
+            self.positions.push(to_f32(center));
+            self.normals.push(to_f32(normal));
+            for i in 0..sides {
+                self.positions.push(to_f32(at(center, around(i))));
+                self.normals.push(to_f32(normal));
+            }
+            for i in 0..sides as u32 {
+                let (a, b) = (first + 1 + i, first + 1 + (i + 1) % sides as u32);
+                // both caps face away from the pipe
+                self.triangles.push(if normal == axis { [first, a, b] } else { [first, b, a] });
+            }
+        }
+    }
+
+    pub fn scale(&mut self, factor: f32) {
+        self.positions.iter_mut().for_each(|p| *p = p.map(|c| c * factor));
+    }
+
+    /// Turn the mesh so +z is up instead of +y, as 3D printing tools expect.
+    pub fn z_up(&mut self) {
+        let turn = |[x, y, z]: [f32; 3]| [x, -z, y];
+        self.positions.iter_mut().for_each(|p| *p = turn(*p));
+        self.normals.iter_mut().for_each(|n| *n = turn(*n));
+    }
+
+    /// Lowest and highest corner of the vertices.
+    pub fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for p in &self.positions {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        (min, max)
+    }
+}
@@ -1,9 +1,19 @@
 use serde_derive::{Deserialize, Serialize};
 use serde_json::Value;
 
+/// File name an inline `program` is yanked to, next to the component's HDD files.
+pub const PROGRAM_FILE: &str = "program.xc";
+
 impl Blueprint {
-    pub fn components_with_hdd(&self) -> Vec<Component> {
-        self.data.components.iter().filter(|c| c.has_data_storage()).cloned().collect()
+    /// Every code file in the blueprint, tagged with the index of the component it lives on.
+    ///
+    /// Inline component programs are included as a file named [`PROGRAM_FILE`].
+    pub fn xc_files(&self) -> Vec<XcFileMeta> {
+        self.data.components.iter()
+            .enumerate()
+            .filter(|(_, c)| c.has_code())
+            .flat_map(|(index, c)| c.xc_files(index))
+            .collect()
     }
 }
 
@@ -12,25 +22,34 @@ impl Component {
         if self.alias.is_none() {
             self.module.to_string()
         } else {
-            let has_alias = !self.alias.clone().unwrap().as_str().is_empty();
+            let has_alias = !self.alias.clone().unwrap().is_empty();
             if has_alias {
-                format!("{}", self.alias.clone().unwrap())
+                self.alias.clone().unwrap().to_string()
             } else {
                 self.module.to_string()
             }
         }
     }
 
-    pub fn xc_files(&self) -> Vec<XcFileMeta> {
-        if let Some(hdd) = &self.data.hdd {
-            hdd.xc_files().iter().map(|f| XcFileMeta::new(self.name().to_string(), f.clone())).collect()
+    pub fn xc_files(&self, index: usize) -> Vec<XcFileMeta> {
+        let mut files: Vec<XcFileMeta> = if let Some(hdd) = &self.data.hdd {
+            hdd.xc_files().iter().map(|f| XcFileMeta::new(index, self.name().to_string(), f.clone())).collect()
         } else {
             vec![]
+        };
+        if let Some(program) = self.data.program.as_ref().filter(|p| !p.is_empty()) {
+            let mut meta = XcFileMeta::new(index, self.name().to_string(), XcFile {
+                name: PROGRAM_FILE.to_string(),
+                plain_code: program.clone(),
+            });
+            meta.program = true;
+            files.push(meta);
         }
+        files
     }
 
-    fn has_data_storage(&self) -> bool {
-        self.data.has_hdd()
+    fn has_code(&self) -> bool {
+        self.data.has_hdd() || self.data.has_program()
     }
 }
 
@@ -38,6 +57,10 @@ impl ComponentData {
     fn has_hdd(&self) -> bool {
         self.hdd.is_some()
     }
+
+    fn has_program(&self) -> bool {
+        self.program.as_ref().is_some_and(|p| !p.is_empty())
+    }
 }
 
 impl Hdd {
@@ -48,18 +71,31 @@ impl Hdd {
 
 #[derive(Debug, Clone)]
 pub struct XcFileMeta {
+    component_index: usize,
     component_name: String,
     inner: XcFile,
+    /// the component's inline `program` rather than a file on its HDD
+    program: bool,
 }
 
 impl XcFileMeta {
-    pub fn new(component_name: String, inner: XcFile) -> Self {
+    pub fn new(component_index: usize, component_name: String, inner: XcFile) -> Self {
         XcFileMeta {
+            component_index,
             component_name,
             inner,
+            program: false,
         }
     }
 
+    pub fn is_program(&self) -> bool {
+        self.program
+    }
+
+    pub fn component_index(&self) -> usize {
+        self.component_index
+    }
+
     pub fn component(&self) -> &str {
         &self.component_name
     }
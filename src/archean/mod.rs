@@ -1 +1,3 @@
 pub(crate) mod json;
+pub(crate) mod raw;
+pub(crate) mod geometry;
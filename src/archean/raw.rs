@@ -0,0 +1,118 @@
+use serde_json::{json, Map, Value};
+use crate::archean::json::XcFile;
+
+/// Untyped view of a blueprint used when writing changes back to disk.
+///
+/// The typed `Blueprint` model only knows a subset of the keys Archean writes, so round-tripping
+/// through it would drop anything we don't model. Edits go through this wrapper instead, which
+/// keeps every key (in its original order) and every number exactly as it was read.
+#[derive(Debug, Clone)]
+pub struct RawBlueprint {
+    value: Value,
+    pretty: bool,
+}
+
+impl RawBlueprint {
+    pub fn parse(source: &str) -> serde_json::Result<Self> {
+        let value: Value = serde_json::from_str(source)?;
+        Ok(RawBlueprint {
+            value,
+            pretty: source.trim_end().contains('\n'),
+        })
+    }
+
+    /// Serialize the blueprint, keeping the compact/pretty layout of the source file.
+    pub fn to_json(&self) -> String {
+        if self.pretty {
+            serde_json::to_string_pretty(&self.value).unwrap()
+        } else {
+            serde_json::to_string(&self.value).unwrap()
+        }
+    }
+
+    /// A top-level entry, e.g. `box_min`.
+    pub fn entry_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.value.get_mut(key)
+    }
+
+    /// An entry of `data`, e.g. `symmetry_axis`.
+    pub fn data_entry_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.value.get_mut("data")?.get_mut(key)
+    }
+
+    pub fn blocks_mut(&mut self) -> Option<&mut Vec<Value>> {
+        self.data_entry_mut("blocks")?.as_array_mut()
+    }
+
+    /// Set `box_min`, `box_max` and `box_size` from the lowest and highest corner, in meters.
+    pub fn set_bounds(&mut self, min: [f64; 3], max: [f64; 3]) {
+        let size = [0, 1, 2].map(|i| max[i] - min[i]);
+        for (key, corner) in [("box_min", min), ("box_max", max), ("box_size", size)] {
+            // `+ 0.0` turns -0.0 into 0.0
+            let [x, y, z] = corner.map(|v| v + 0.0);
+            self.value[key] = json!({ "x": x, "y": y, "z": z });
+        }
+    }
+
+    pub fn components_mut(&mut self) -> Option<&mut Vec<Value>> {
+        self.data_entry_mut("components")?.as_array_mut()
+    }
+
+    pub fn pipes_mut(&mut self) -> Option<&mut Vec<Value>> {
+        self.data_entry_mut("pipes")?.as_array_mut()
+    }
+
+    fn component_data_mut(&mut self, component: usize) -> Option<&mut Map<String, Value>> {
+        self.components_mut()?.get_mut(component)?.get_mut("data")?.as_object_mut()
+    }
+
+    /// Replace the `xc_files` of the HDD in the component at `component`.
+    ///
+    /// Returns `false` if the component doesn't exist or has no HDD.
+    pub fn set_xc_files(&mut self, component: usize, files: &[XcFile]) -> bool {
+        let Some(hdd) = self.component_data_mut(component).and_then(|d| d.get_mut("hdd")) else {
+            return false;
+        };
+        let Some(hdd) = hdd.as_object_mut() else {
+            return false;
+        };
+        hdd.insert("xc_files".to_string(), serde_json::to_value(files).unwrap());
+        true
+    }
+
+    /// Set the inline `program` of the component at `component`.
+    ///
+    /// Returns `false` if the component doesn't exist.
+    pub fn set_program(&mut self, component: usize, plain_code: &str) -> bool {
+        let Some(data) = self.component_data_mut(component) else {
+            return false;
+        };
+        data.insert("program".to_string(), Value::from(plain_code));
+        true
+    }
+
+    /// Set the code of the HDD file `name` in the component at `component`, adding the file if
+    /// the HDD doesn't have it yet.
+    ///
+    /// Returns `false` if the component doesn't exist or has no HDD.
+    pub fn set_xc_file(&mut self, component: usize, name: &str, plain_code: &str) -> bool {
+        let Some(files) = self.component_data_mut(component)
+            .and_then(|d| d.get_mut("hdd"))
+            .and_then(|hdd| hdd.get_mut("xc_files"))
+            .and_then(|files| files.as_array_mut()) else {
+            return false;
+        };
+        match files.iter_mut().find(|f| f.get("name").and_then(Value::as_str) == Some(name)) {
+            Some(file) => {
+                file["plain_code"] = Value::from(plain_code);
+            }
+            None => {
+                files.push(serde_json::to_value(XcFile {
+                    name: name.to_string(),
+                    plain_code: plain_code.to_string(),
+                }).unwrap());
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use clap::{ArgMatches, Error, FromArgMatches};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use crate::command;
+use crate::command::manifest::YANK_MANIFEST;
+use crate::command::package::BlueprintMetadata;
+use crate::command::sanitize;
+
+/// Extension of packaged blueprints.
+pub const ARCHIVE_EXTENSION: &str = "archbelt";
+/// Package metadata, at the root of the archive.
+pub const METADATA_FILE: &str = "archbelt.json";
+/// `sha256sum`-style list of every other entry, at the root of the archive.
+pub const CHECKSUMS_FILE: &str = "SHA256SUMS";
+/// Folder holding the blueprint `.json`.
+pub const BLUEPRINT_FOLDER: &str = "blueprint";
+/// Folder holding the yanked code and its yank manifest.
+pub const SOURCE_FOLDER: &str = "src";
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Io(PathBuf, std::io::Error),
+    Zip(PathBuf, zip::result::ZipError),
+    /// an entry that would be written outside the folder it is unpacked to
+    UnsafePath(String),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Io(path, e) => write!(f, "{:?}: {}", path, e),
+            ArchiveError::Zip(path, e) => write!(f, "{:?} is not a valid archive: {}", path, e),
+            ArchiveError::UnsafePath(name) => write!(f, "entry {:?} points outside the archive", name),
+        }
+    }
+}
+
+/// The entries of a `.archbelt` file, in the order they are stored.
+#[derive(Debug, Clone, Default)]
+pub struct Archive {
+    entries: Vec<(String, Vec<u8>)>,
+}
+
+impl Archive {
+    pub fn add(&mut self, path: impl Into<String>, data: impl Into<Vec<u8>>) {
+        self.entries.push((path.into(), data.into()));
+    }
+
+    pub fn entries(&self) -> &[(String, Vec<u8>)] {
+        &self.entries
+    }
+
+    pub fn get(&self, path: &str) -> Option<&[u8]> {
+        self.entries.iter().find(|(p, _)| p == path).map(|(_, data)| data.as_slice())
+    }
+
+    pub fn metadata(&self) -> Option<BlueprintMetadata> {
+        serde_json::from_slice(self.get(METADATA_FILE)?).ok()
+    }
+
+    /// The blueprint's file name and contents.
+    pub fn blueprint(&self) -> Option<(&str, &[u8])> {
+        self.entries.iter()
+            .find(|(p, _)| p.starts_with(&format!("{}/", BLUEPRINT_FOLDER)) && p.ends_with(".json"))
+            .map(|(p, data)| (&p[BLUEPRINT_FOLDER.len() + 1..], data.as_slice()))
+    }
+
+    /// The code of a library package: every file in its source folder but the yank manifest,
+    /// relative to that folder.
+    pub fn library_files(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.entries.iter()
+            .filter_map(|(p, data)| p.strip_prefix(&format!("{}/", SOURCE_FOLDER)).map(|p| (p, data.as_slice())))
+            .filter(|(p, _)| *p != YANK_MANIFEST)
+    }
+
+    /// A package of shared XenonCode rather than a blueprint.
+    pub fn is_library(&self) -> bool {
+        self.blueprint().is_none()
+    }
+
+    /// `sha256sum`-style checksums of every entry but the checksums themselves.
+    pub fn checksums(&self) -> String {
+        self.entries.iter()
+            .filter(|(name, _)| name != CHECKSUMS_FILE)
+            .map(|(name, data)| format!("{}  {}\n", command::content_hash(data), name))
+            .collect()
+    }
+
+    /// Write the archive, adding the checksums of every entry.
+    pub fn write(&self, path: &Path) -> Result<(), ArchiveError> {
+        let io = |e| ArchiveError::Io(path.to_path_buf(), e);
+        let zip = |e| ArchiveError::Zip(path.to_path_buf(), e);
+
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        let mut writer = ZipWriter::new(File::create(path).map_err(io)?);
+        // checksums read back from an unpacked folder are written afresh
+        for (name, data) in self.entries.iter().filter(|(name, _)| name != CHECKSUMS_FILE) {
+            writer.start_file(name.as_str(), options).map_err(zip)?;
+            writer.write_all(data).map_err(io)?;
+        }
+        writer.start_file(CHECKSUMS_FILE, options).map_err(zip)?;
+        writer.write_all(self.checksums().as_bytes()).map_err(io)?;
+        writer.finish().map_err(zip)?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Archive, ArchiveError> {
+        let io = |e| ArchiveError::Io(path.to_path_buf(), e);
+        let zip = |e| ArchiveError::Zip(path.to_path_buf(), e);
+
+        let mut reader = ZipArchive::new(File::open(path).map_err(io)?).map_err(zip)?;
+        let mut archive = Archive::default();
+        for index in 0..reader.len() {
+            let mut file = reader.by_index(index).map_err(zip)?;
+            if file.is_dir() {
+                continue;
+            }
+            if file.enclosed_name().is_none() {
+                return Err(ArchiveError::UnsafePath(file.name().to_string()));
+            }
+            let mut data = vec![];
+            file.read_to_end(&mut data).map_err(io)?;
+            archive.add(file.name(), data);
+        }
+        Ok(archive)
+    }
+
+    /// Read an unpacked archive back from `dir`.
+    pub fn from_dir(dir: &Path) -> Result<Archive, ArchiveError> {
+        let mut files = command::walk_files(dir);
+        files.sort();
+        let mut archive = Archive::default();
+        for path in files {
+            let data = std::fs::read(&path).map_err(|e| ArchiveError::Io(path.clone(), e))?;
+            let relative = path.strip_prefix(dir).unwrap_or(&path);
+            let name: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+            archive.add(name.join("/"), data);
+        }
+        Ok(archive)
+    }
+
+    /// Everything wrong with the archive; empty if it is intact.
+    pub fn verify(&self) -> Vec<String> {
+        let mut problems = self.verify_checksums();
+        problems.extend(self.verify_layout());
+        problems
+    }
+
+    /// Entries whose checksum doesn't match, is missing, or that aren't listed at all.
+    pub fn verify_checksums(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let Some(checksums) = self.get(CHECKSUMS_FILE) else {
+            return vec![format!("{} is missing", CHECKSUMS_FILE)];
+        };
+
+        let mut listed = vec![];
+        for line in String::from_utf8_lossy(checksums).lines().filter(|l| !l.trim().is_empty()) {
+            let Some((hash, name)) = line.split_once("  ") else {
+                problems.push(format!("{}: malformed line {:?}", CHECKSUMS_FILE, line));
+                continue;
+            };
+            listed.push(name.to_string());
+            match self.get(name) {
+                Some(data) if command::content_hash(data) == hash => {}
+                Some(_) => problems.push(format!("{}: checksum mismatch", name)),
+                None => problems.push(format!("{}: listed but missing", name)),
+            }
+        }
+        for (name, _) in &self.entries {
+            if name != CHECKSUMS_FILE && !listed.contains(name) {
+                problems.push(format!("{}: not covered by {}", name, CHECKSUMS_FILE));
+            }
+        }
+        problems
+    }
+
+    /// Missing metadata, or neither a blueprint nor library code.
+    pub fn verify_layout(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.metadata().is_none() {
+            problems.push(format!("{} is missing or invalid", METADATA_FILE));
+        }
+        if self.is_library() && !self.library_files().any(|(p, _)| p.ends_with(".xc")) {
+            problems.push(format!("no blueprint in {}/ and no code in {}/", BLUEPRINT_FOLDER, SOURCE_FOLDER));
+        }
+        problems
+    }
+
+    /// Write every entry below `dir`.
+    pub fn extract(&self, dir: &Path) -> Result<(), ArchiveError> {
+        for (name, data) in &self.entries {
+            let relative = Path::new(name);
+            if !sanitize::is_contained(relative) {
+                return Err(ArchiveError::UnsafePath(name.clone()));
+            }
+            let path = dir.join(relative);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| ArchiveError::Io(parent.to_path_buf(), e))?;
+            }
+            std::fs::write(&path, data).map_err(|e| ArchiveError::Io(path.clone(), e))?;
+        }
+        Ok(())
+    }
+}
+
+pub struct ArchiveConfig {
+    pub(crate) archive: PathBuf,
+    pub(crate) to: Option<PathBuf>,
+    pub(crate) list: bool
+}
+
+impl FromArgMatches for ArchiveConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        Ok(ArchiveConfig {
+            archive: matches.get_one::<String>("ARCHIVE").map(PathBuf::from).unwrap_or_default(),
+            to: matches.try_get_one::<String>("to").ok().flatten().map(PathBuf::from),
+            list: matches.try_get_one::<bool>("list").ok().flatten().copied().unwrap_or(false)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = ArchiveConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+fn read_archive(args: &ArgMatches) -> (ArchiveConfig, Archive) {
+    let config = ArchiveConfig::from_arg_matches(args).unwrap();
+    match Archive::read(&config.archive) {
+        Ok(archive) => (config, archive),
+        Err(e) => {
+            eprintln!("🚨 {} 🚨", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn print_summary(archive: &Archive) {
+    if let Some(metadata) = archive.metadata() {
+        println!("{} {} by {}", metadata.name.unwrap_or_default(), metadata.version, metadata.authors.join(", "));
+        if let Some(description) = &metadata.description {
+            println!("  {}", description);
+        }
+    }
+    for (name, data) in archive.entries() {
+        println!("  {:>9}  {}  {}", data.len(), &command::content_hash(data)[..12], name);
+    }
+}
+
+pub fn verify_archive(args: &ArgMatches) {
+    let (config, archive) = read_archive(args);
+    print_summary(&archive);
+
+    let problems = archive.verify();
+    if !problems.is_empty() {
+        problems.iter().for_each(|p| eprintln!("✘ {}", p));
+        eprintln!("🚨 {:?} failed verification! 🚨", config.archive);
+        std::process::exit(1);
+    }
+    println!("✔ {:?} is intact", config.archive);
+}
+
+pub fn unpack_archive(args: &ArgMatches) {
+    let (config, archive) = read_archive(args);
+    if config.list {
+        print_summary(&archive);
+        return;
+    }
+
+    let problems = archive.verify();
+    if !problems.is_empty() {
+        problems.iter().for_each(|p| eprintln!("✘ {}", p));
+        eprintln!("🚨 Not unpacking {:?}; it failed verification! 🚨", config.archive);
+        std::process::exit(1);
+    }
+    let to = config.to.clone()
+        .unwrap_or_else(|| PathBuf::from(config.archive.file_stem().unwrap_or_default()));
+    if let Err(e) = archive.extract(&to) {
+        eprintln!("🚨 {} 🚨", e);
+        std::process::exit(1);
+    }
+    println!("Unpacked {} file(s) to {:?}", archive.entries().len(), to);
+}
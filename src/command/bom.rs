@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use clap::{ArgMatches, Error, FromArgMatches};
+use crate::archean::json::Blueprint;
+use crate::command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BomFormat {
+    Table,
+    Csv,
+    Markdown,
+}
+
+pub struct BomConfig {
+    pub(crate) file_name: PathBuf,
+    pub(crate) format: BomFormat
+}
+
+impl FromArgMatches for BomConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let format = match matches.get_one::<String>("format").map(String::as_str) {
+            Some("csv") => BomFormat::Csv,
+            Some("markdown") => BomFormat::Markdown,
+            _ => BomFormat::Table,
+        };
+
+        Ok(BomConfig {
+            file_name: command::extract_filename("BLUEPRINT".to_string(), matches)?,
+            format
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = BomConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+/// One part of the bill of materials, e.g. the blocks.
+pub struct BomSection {
+    title: &'static str,
+    /// what rows are grouped by, e.g. material and type
+    keys: &'static [&'static str],
+    /// what is added up besides the count, e.g. volume
+    amount: Option<&'static str>,
+    rows: Vec<BomRow>,
+}
+
+pub struct BomRow {
+    keys: Vec<String>,
+    count: usize,
+    amount: Option<String>,
+}
+
+impl BomSection {
+    fn header(&self) -> Vec<&'static str> {
+        self.keys.iter().copied().chain(["COUNT"]).chain(self.amount).collect()
+    }
+}
+
+impl BomRow {
+    fn cells(&self) -> Vec<String> {
+        self.keys.iter().cloned().chain([self.count.to_string()]).chain(self.amount.clone()).collect()
+    }
+}
+
+/// Components by module and type, blocks by material and type with their volume in grid units, and
+/// pipes by type with their length.
+pub fn bill_of_materials(blueprint: &Blueprint) -> Vec<BomSection> {
+    let data = &blueprint.data;
+
+    let mut components: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for component in &data.components {
+        *components.entry((component.module.as_str(), component.type_field.as_str())).or_default() += 1;
+    }
+
+    let mut blocks: BTreeMap<(i64, i64), (usize, i64)> = BTreeMap::new();
+    for block in &data.blocks {
+        let (count, volume) = blocks.entry((block.material, block.type_field)).or_default();
+        *count += 1;
+        *volume += block.size_x * block.size_y * block.size_z;
+    }
+
+    let mut pipes: BTreeMap<&str, (usize, f64)> = BTreeMap::new();
+    for pipe in &data.pipes {
+        let (count, length) = pipes.entry(pipe.type_field.as_str()).or_default();
+        *count += 1;
+        *length += pipe.segments.iter().map(|s| s.length).sum::<f64>();
+    }
+
+    vec![
+        BomSection {
+            title: "Components",
+            keys: &["MODULE", "TYPE"],
+            amount: None,
+            rows: components.into_iter().map(|((module, type_field), count)| BomRow {
+                keys: vec![module.to_string(), type_field.to_string()],
+                count,
+                amount: None,
+            }).collect(),
+        },
+        BomSection {
+            title: "Blocks",
+            keys: &["MATERIAL", "TYPE"],
+            amount: Some("VOLUME"),
+            rows: blocks.into_iter().map(|((material, type_field), (count, volume))| BomRow {
+                keys: vec![material.to_string(), type_field.to_string()],
+                count,
+                amount: Some(volume.to_string()),
+            }).collect(),
+        },
+        BomSection {
+            title: "Pipes",
+            keys: &["TYPE"],
+            amount: Some("LENGTH"),
+            rows: pipes.into_iter().map(|(type_field, (count, length))| BomRow {
+                keys: vec![type_field.to_string()],
+                count,
+                amount: Some(format!("{:.2}", length)),
+            }).collect(),
+        },
+    ]
+}
+
+fn table(sections: &[BomSection]) -> String {
+    let mut out = String::new();
+    for section in sections.iter().filter(|s| !s.rows.is_empty()) {
+        let rows: Vec<Vec<String>> = section.rows.iter().map(BomRow::cells).collect();
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("{}\n", section.title));
+        out.push_str(&command::text_table(&section.header(), &rows, section.keys.len()));
+    }
+    out
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// All sections in one sheet: `name` and `kind` are a section's keys (module and type, material and
+/// type, pipe type), `amount` its volume or length.
+fn csv(sections: &[BomSection]) -> String {
+    let mut out = String::from("section,name,kind,count,amount\n");
+    for section in sections {
+        for row in &section.rows {
+            let fields = [
+                section.title.to_string(),
+                row.keys[0].clone(),
+                row.keys.get(1).cloned().unwrap_or_default(),
+                row.count.to_string(),
+                row.amount.clone().unwrap_or_default(),
+            ];
+            out.push_str(&format!("{}\n", fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",")));
+        }
+    }
+    out
+}
+
+fn markdown(title: &str, sections: &[BomSection]) -> String {
+    let cell = |text: &str| text.replace('|', "\\|");
+    let mut out = format!("## Bill of materials: {}\n", cell(title));
+    for section in sections.iter().filter(|s| !s.rows.is_empty()) {
+        let header: Vec<String> = section.header().iter().map(|h| format!("{}{}", &h[..1], h[1..].to_lowercase())).collect();
+        let alignment: Vec<&str> = (0..header.len()).map(|i| if i < section.keys.len() { "---" } else { "---:" }).collect();
+        out.push_str(&format!("\n### {}\n\n", section.title));
+        out.push_str(&format!("| {} |\n", header.join(" | ")));
+        out.push_str(&format!("|{}|\n", alignment.join("|")));
+        for row in &section.rows {
+            out.push_str(&format!("| {} |\n", row.cells().iter().map(|c| cell(c)).collect::<Vec<_>>().join(" | ")));
+        }
+    }
+    out
+}
+
+pub fn print_bom(args: &ArgMatches) {
+    let config = match BomConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let blueprint = command::get_blueprint_object(config.file_name.clone()).ok()
+        .and_then(|source| serde_json::from_str::<Blueprint>(&source).ok());
+    let Some(blueprint) = blueprint else {
+        eprintln!("🚨 Blueprint could not be parsed! 🚨");
+        std::process::exit(1);
+    };
+
+    let sections = bill_of_materials(&blueprint);
+    let name = config.file_name.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    match config.format {
+        BomFormat::Table => print!("{}", table(&sections)),
+        BomFormat::Csv => print!("{}", csv(&sections)),
+        BomFormat::Markdown => print!("{}", markdown(&name, &sections)),
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+use clap::{ArgMatches, Error, FromArgMatches};
+use crate::archean::json::Blueprint;
+use crate::archean::raw::RawBlueprint;
+use crate::command;
+use crate::xenon::bundle::{bundle, Library};
+
+pub struct BundleConfig {
+    pub(crate) file_name: PathBuf,
+    pub(crate) library: Library,
+    pub(crate) components: Vec<String>,
+    pub(crate) dry_run: bool
+}
+
+impl FromArgMatches for BundleConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let file_name = command::extract_filename("BLUEPRINT".to_string(), matches)?;
+        let lib_paths = matches.get_many::<String>("lib")
+            .map(|paths| paths.map(PathBuf::from).collect())
+            .unwrap_or_default();
+        let components = matches.get_many::<String>("component")
+            .map(|names| names.cloned().collect())
+            .unwrap_or_default();
+        let dry_run = matches.get_one::<bool>("dry-run").unwrap_or(&false);
+
+        Ok(BundleConfig {
+            file_name,
+            library: Library::new(lib_paths),
+            components,
+            dry_run: *dry_run
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = BundleConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+pub fn bundle_libraries(args: &ArgMatches) {
+    let config = match BundleConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    if config.library.search_paths().is_empty() {
+        eprintln!("🚨 No library paths given! Use --lib or set ARCHBELT_LIB 🚨");
+        std::process::exit(1);
+    }
+
+    let source = match command::get_blueprint_object(config.file_name.clone()) {
+        Ok(source) => source,
+        Err(_) => {
+            eprintln!("🚨 Blueprint could not be found or opened! 🚨");
+            std::process::exit(1);
+        }
+    };
+    let (blueprint, mut raw) = match (serde_json::from_str::<Blueprint>(&source), RawBlueprint::parse(&source)) {
+        (Ok(blueprint), Ok(raw)) => (blueprint, raw),
+        _ => {
+            eprintln!("🚨 Blueprint could not be parsed! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let mut changed = false;
+    for (index, component) in blueprint.data.components.iter().enumerate() {
+        let Some(hdd) = &component.data.hdd else {
+            continue;
+        };
+        if !config.components.is_empty() && !config.components.contains(&component.name()) {
+            continue;
+        }
+
+        match bundle(&hdd.xc_files, &config.library) {
+            Ok(result) => {
+                result.added.iter().for_each(|f| println!("{}: + {}", component.name(), f));
+                result.updated.iter().for_each(|f| println!("{}: ~ {}", component.name(), f));
+                if result.is_changed() {
+                    raw.set_xc_files(index, &result.files);
+                    changed = true;
+                }
+            }
+            Err(e) => {
+                eprintln!("🚨 {}: {} 🚨", component.name(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if !changed {
+        println!("All libraries are up to date.");
+        return;
+    }
+    if config.dry_run {
+        println!("Dry run; blueprint not modified.");
+        return;
+    }
+    if let Err(e) = std::fs::write(&config.file_name, raw.to_json()) {
+        eprintln!("Error saving blueprint: {:?}", e);
+        std::process::exit(1);
+    }
+    println!("Bundled libraries into {:?}", config.file_name);
+}
@@ -0,0 +1,240 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use clap::{ArgMatches, Error, FromArgMatches};
+use clap::error::ErrorKind;
+use serde_json::Value;
+use crate::archean::geometry::{self, GridBox, Part, GRID};
+use crate::archean::json::Blueprint;
+use crate::archean::raw::RawBlueprint;
+use crate::command;
+use crate::command::transform::{grid_offset, Transform};
+
+pub struct CombineConfig {
+    pub(crate) files: Vec<PathBuf>,
+    /// where each blueprint goes, in grid units; the first stays where it is
+    pub(crate) offsets: Vec<[i64; 3]>,
+    pub(crate) out: PathBuf,
+    pub(crate) allow_overlap: bool
+}
+
+/// A blueprint given by path, or by name in the blueprints folder (with or without `.json`).
+pub(crate) fn blueprint_file(name: &str, matches: &ArgMatches) -> Result<PathBuf, Error> {
+    let path = PathBuf::from(name);
+    if path.is_file() {
+        return Ok(path);
+    }
+    Ok(command::get_blueprint_path(format!("{}.json", name.strip_suffix(".json").unwrap_or(name)), matches)?)
+}
+
+impl FromArgMatches for CombineConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let files = matches.get_many::<String>("BLUEPRINTS")
+            .map(|names| names.map(|name| blueprint_file(name, matches)).collect::<Result<Vec<_>, _>>())
+            .transpose()?
+            .unwrap_or_default();
+        let at = matches.get_many::<String>("at")
+            .map(|offsets| offsets.map(|o| grid_offset(o)).collect::<Result<Vec<_>, _>>())
+            .transpose()?
+            .unwrap_or_default();
+        if at.len() >= files.len() {
+            return Err(Error::raw(ErrorKind::InvalidValue,
+                                  format!("{} --at given for {} blueprint(s); the first blueprint stays where it is\n", at.len(), files.len())));
+        }
+        let mut offsets = vec![[0; 3]];
+        offsets.extend(at);
+        offsets.resize(files.len(), [0; 3]);
+
+        Ok(CombineConfig {
+            files,
+            offsets,
+            out: PathBuf::from(matches.get_one::<String>("out").cloned().unwrap_or_default()),
+            allow_overlap: *matches.get_one::<bool>("allow-overlap").unwrap_or(&false)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = CombineConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+/// One of the blueprints being combined, already moved into place.
+struct Assembly {
+    name: String,
+    blueprint: Blueprint,
+    raw: RawBlueprint,
+    offset: [i64; 3],
+    boxes: Vec<GridBox>,
+}
+
+fn describe(part: Part, blueprint: &Blueprint) -> String {
+    match part {
+        Part::Block(index) => format!("block #{}", index),
+        Part::Component(index) => format!("{} #{}", blueprint.data.components[index].name(), index),
+    }
+}
+
+fn overlaps(a: &GridBox, b: &GridBox) -> bool {
+    let (a_max, b_max) = (a.max(), b.max());
+    (0..3).all(|i| a.min[i] < b_max[i] && b.min[i] < a_max[i])
+}
+
+/// Blocks and component occupancies of different assemblies that share cells.
+fn find_overlaps(assemblies: &[Assembly]) -> Vec<String> {
+    let mut problems = BTreeSet::new();
+    for (i, a) in assemblies.iter().enumerate() {
+        for b in &assemblies[i + 1..] {
+            for (x, y) in a.boxes.iter().flat_map(|x| b.boxes.iter().map(move |y| (x, y))) {
+                if overlaps(x, y) {
+                    problems.insert(format!("{}: {} overlaps {}: {}",
+                                            a.name, describe(x.part, &a.blueprint), b.name, describe(y.part, &b.blueprint)));
+                }
+            }
+        }
+    }
+    problems.into_iter().collect()
+}
+
+/// Composite build sub-build ids (`slaveBuildId`) used by more than one assembly. archbelt doesn't
+/// know what else refers to a sub-build, so it can't renumber them.
+fn shared_build_ids(assemblies: &[Assembly]) -> Vec<String> {
+    let mut owners: BTreeMap<i64, Vec<&str>> = BTreeMap::new();
+    for assembly in assemblies {
+        let ids: BTreeSet<i64> = assembly.blueprint.data.composite_builds.iter().map(|b| b.slave_build_id).collect();
+        ids.into_iter().for_each(|id| owners.entry(id).or_default().push(&assembly.name));
+    }
+    owners.into_iter()
+        .filter(|(_, names)| names.len() > 1)
+        .map(|(id, names)| format!("sub-build id {} is used by {}", id, names.join(" and ")))
+        .collect()
+}
+
+fn take_array(raw: &mut RawBlueprint, key: &str) -> Vec<Value> {
+    raw.data_entry_mut(key).and_then(Value::as_array_mut).map(std::mem::take).unwrap_or_default()
+}
+
+fn append_array(raw: &mut RawBlueprint, key: &str, values: Vec<Value>) {
+    if let Some(array) = raw.data_entry_mut(key).and_then(Value::as_array_mut) {
+        array.extend(values);
+    }
+}
+
+/// Shift a component index by `by`, leaving negative ones (no component) alone.
+fn shift_index(value: Option<&mut Value>, by: i64) {
+    if let Some(value) = value {
+        if let Some(index) = value.as_i64().filter(|i| *i >= 0) {
+            *value = Value::from(index + by);
+        }
+    }
+}
+
+/// Merge `assemblies` into the first: blocks, components and pipes are appended, and component
+/// indices in pipes and composite builds are shifted past the components before them.
+fn merge(mut assemblies: Vec<Assembly>) -> RawBlueprint {
+    let mut rest = assemblies.split_off(1);
+    let mut base = assemblies.remove(0);
+    let mut components = base.blueprint.data.components.len() as i64;
+    let mut mass = base.blueprint.mass;
+    let (mut min, mut max) = ([f64::MAX; 3], [f64::MIN; 3]);
+
+    for part in std::iter::once(&mut base).chain(rest.iter_mut()) {
+        let shift = part.offset.map(|v| v as f64 * GRID);
+        let b = &part.blueprint;
+        for (i, (low, high)) in [(b.box_min.x, b.box_max.x), (b.box_min.y, b.box_max.y), (b.box_min.z, b.box_max.z)].into_iter().enumerate() {
+            min[i] = min[i].min(low + shift[i]);
+            max[i] = max[i].max(high + shift[i]);
+        }
+    }
+
+    for part in &mut rest {
+        let mut pipes = take_array(&mut part.raw, "pipes");
+        for pipe in &mut pipes {
+            shift_index(pipe.get_mut("a_component"), components);
+            shift_index(pipe.get_mut("b_component"), components);
+        }
+        let mut builds = take_array(&mut part.raw, "composite_builds");
+        for build in &mut builds {
+            shift_index(build.get_mut("component"), components);
+        }
+        for key in ["blocks", "components", "doors", "frames", "labels"] {
+            let values = take_array(&mut part.raw, key);
+            append_array(&mut base.raw, key, values);
+        }
+        append_array(&mut base.raw, "pipes", pipes);
+        append_array(&mut base.raw, "composite_builds", builds);
+        components += part.blueprint.data.components.len() as i64;
+        mass += part.blueprint.mass;
+    }
+
+    base.raw.set_bounds(min, max);
+    if let Some(entry) = base.raw.entry_mut("mass") {
+        *entry = Value::from(mass);
+    }
+    base.raw
+}
+
+pub fn combine_blueprints(args: &ArgMatches) {
+    let config = match CombineConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(e) if e.kind() == ErrorKind::InvalidValue => {
+            eprint!("{}", e);
+            std::process::exit(1);
+        }
+        Err(_) => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let mut assemblies = vec![];
+    for (file, offset) in config.files.iter().zip(&config.offsets) {
+        let source = command::get_blueprint_object(file.clone()).unwrap_or_default();
+        let (blueprint, mut raw) = match (serde_json::from_str::<Blueprint>(&source), RawBlueprint::parse(&source)) {
+            (Ok(blueprint), Ok(raw)) => (blueprint, raw),
+            _ => {
+                eprintln!("🚨 Blueprint {:?} could not be parsed! 🚨", file);
+                std::process::exit(1);
+            }
+        };
+        Transform::translation(*offset).apply(&mut raw);
+        let mut boxes = geometry::blocks(&blueprint);
+        boxes.extend(geometry::occupancies(&blueprint));
+        for grid_box in &mut boxes {
+            (0..3).for_each(|i| grid_box.min[i] += offset[i]);
+        }
+        let name = file.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let data = &blueprint.data;
+        println!("+ {} at ({}, {}, {}): {} block(s), {} component(s), {} pipe(s)", name,
+                 offset[0] as f64 * GRID, offset[1] as f64 * GRID, offset[2] as f64 * GRID,
+                 data.blocks.len(), data.components.len(), data.pipes.len());
+        if *offset != [0; 3] && data.doors.len() + data.labels.len() + data.frames.len() > 0 {
+            eprintln!("Warning: doors, labels and frames of {} were left where they were", name);
+        }
+        assemblies.push(Assembly { name, blueprint, raw, offset: *offset, boxes });
+    }
+
+    let problems = find_overlaps(&assemblies);
+    problems.iter().for_each(|p| eprintln!("✘ {}", p));
+    if !problems.is_empty() && !config.allow_overlap {
+        eprintln!("🚨 {} overlap(s) found; move the blueprints with --at or pass --allow-overlap 🚨", problems.len());
+        std::process::exit(1);
+    }
+
+    let shared = shared_build_ids(&assemblies);
+    if !shared.is_empty() {
+        shared.iter().for_each(|p| eprintln!("✘ {}", p));
+        eprintln!("🚨 The blueprints share sub-build ids, so their composite builds would be mixed up; nothing was written 🚨");
+        std::process::exit(1);
+    }
+
+    let combined = merge(assemblies);
+    if serde_json::from_str::<Blueprint>(&combined.to_json()).is_err() {
+        eprintln!("🚨 Combined blueprint could not be parsed; nothing was written 🚨");
+        std::process::exit(1);
+    }
+    if let Err(e) = std::fs::write(&config.out, combined.to_json()) {
+        eprintln!("Error saving {:?}: {:?}", config.out, e);
+        std::process::exit(1);
+    }
+    println!("Wrote {:?}", config.out);
+}
@@ -1,8 +1,114 @@
 use clap::{Arg, arg, ArgAction, Command, value_parser};
+use clap::builder::PossibleValue;
 use clap_complete::Shell;
 
 // TODO: implement clap Parser struct for v0.4.0 w/ full config
 
+pub(crate) fn bundle_command() -> Command {
+    Command::new("bundle")
+        .about("Copy included XenonCode library files into the blueprint's HDDs")
+        .arg(blueprint_path_arg())
+        .arg(arg!(-l --lib <DIR> "library folder to resolve includes from; may be repeated (also read from ARCHBELT_LIB)")
+            .action(ArgAction::Append))
+        .arg(arg!(-c --component <NAME> "only bundle into the named component; may be repeated")
+            .action(ArgAction::Append))
+        .arg(arg!(-n --"dry-run" "show what would change without modifying the blueprint")
+            .action(ArgAction::SetTrue))
+        .arg(
+            arg!([BLUEPRINT] "name of the blueprint without .json")
+                .num_args(0..)
+                .required(true)
+                .trailing_var_arg(true))
+}
+
+pub(crate) fn doc_command() -> Command {
+    Command::new("doc")
+        .about("Generate Markdown or HTML documentation for a blueprint's XenonCode")
+        .arg(blueprint_path_arg())
+        .arg(arg!(-d --dir <DIR> "document the .xc files in a folder (e.g. a library) instead of a blueprint"))
+        .arg(arg!(-o --out <DIR> "folder to write the documentation to (defaults to ./docs)"))
+        .arg(arg!(--format <FORMAT> "output format")
+            .value_parser(["markdown", "html"])
+            .default_value("markdown"))
+        .arg(
+            arg!([BLUEPRINT] "name of the blueprint without .json")
+                .num_args(0..)
+                .required_unless_present("dir")
+                .trailing_var_arg(true))
+}
+
+pub(crate) fn lsp_command() -> Command {
+    Command::new("lsp")
+        .about("Run a XenonCode language server over stdio")
+        .arg(arg!(-l --lib <DIR> "library folder to resolve includes from; may be repeated (also read from ARCHBELT_LIB)")
+            .action(ArgAction::Append))
+        .arg(arg!(--stdio "communicate over stdio (the default; accepted for editor compatibility)")
+            .action(ArgAction::SetTrue))
+}
+
+pub(crate) fn refactor_command() -> Command {
+    let blueprint_arg = arg!(-b --blueprint <NAME> "blueprint to change, without .json; may be repeated (defaults to every blueprint)")
+        .action(ArgAction::Append);
+    let dry_run_arg = arg!(-n --"dry-run" "show a diff of what would change without modifying any blueprint")
+        .action(ArgAction::SetTrue);
+
+    Command::new("refactor")
+        .about("Change XenonCode across several blueprints at once")
+        .subcommand_required(true)
+        .subcommand(Command::new("rename")
+            .about("Rename a variable or function wherever it is declared or used")
+            .arg(blueprint_path_arg())
+            .arg(blueprint_arg.clone())
+            .arg(dry_run_arg.clone())
+            .arg(arg!(<FROM> "current name, e.g. $speed or @pid"))
+            .arg(arg!(<TO> "new name, with or without the $ or @")))
+        .subcommand(Command::new("replace")
+            .about("Replace every HDD file with the given name and content hash by a new version")
+            .arg(blueprint_path_arg())
+            .arg(blueprint_arg)
+            .arg(dry_run_arg)
+            .arg(arg!(--with <PATH> "file with the new code").required(true))
+            .arg(arg!(<FILE> "name of the file on the HDD, e.g. pid.xc"))
+            .arg(arg!(<HASH> "SHA-256 (or a prefix of it) of the version to replace")))
+}
+
+pub(crate) fn version_command() -> Command {
+    Command::new("version")
+        .about("Show or change the package version in the project's archbelt.json")
+        .subcommand(Command::new("bump")
+            .about("Increase the version, resetting the parts after it")
+            .arg(arg!(<PART> "part of the version to increase")
+                .value_parser(["major", "minor", "patch"])))
+        .subcommand(Command::new("set")
+            .about("Set the version, creating archbelt.json if there is none")
+            .arg(arg!(<VERSION> "a semver version, e.g. 1.0.0")
+                .value_parser(|v: &str| semver::Version::parse(v))))
+}
+
+pub(crate) fn search_command() -> Command {
+    Command::new("search")
+        .about("Search the XenonCode of every blueprint for text or an identifier")
+        .arg(blueprint_path_arg())
+        .arg(arg!(-s --symbol "match identifiers instead of text (implied by a leading $ or @)")
+            .action(ArgAction::SetTrue))
+        .arg(arg!(-d --definitions "only show where the identifier is declared; implies -s")
+            .action(ArgAction::SetTrue))
+        .arg(arg!(-i --"ignore-case" "match regardless of case")
+            .action(ArgAction::SetTrue))
+        .arg(arg!(--rebuild "ignore the cached index and re-read every blueprint")
+            .action(ArgAction::SetTrue))
+        .arg(arg!(<QUERY> "text to look for, or $variable / @function"))
+}
+
+pub(crate) fn test_command() -> Command {
+    Command::new("test")
+        .about("Run XenonCode test cases (*.test.json) against yanked code")
+        .arg(arg!(-l --lib <DIR> "library folder to resolve includes from; may be repeated (also read from ARCHBELT_LIB)")
+            .action(ArgAction::Append))
+        .arg(arg!([PATH] "test files or folders to search for them (defaults to the current folder)")
+            .num_args(0..))
+}
+
 pub(crate) fn complete_command() -> Command {
     Command::new("complete")
         .about("Generate shell completion for zsh & bash")
@@ -17,12 +123,189 @@ pub(crate) fn info_command() -> Command {
     Command::new("info")
         .about("Display information about the blueprint")
         .arg(blueprint_path_arg())
+        .arg(arg!(--env "show where Archean and the blueprints folder were found")
+            .action(ArgAction::SetTrue))
+        .arg(
+            arg!([BLUEPRINT] "name of the blueprint without .json")
+                .num_args(0..)
+                .required_unless_present("env")
+                .trailing_var_arg(true))
+}
+
+pub(crate) fn list_command() -> Command {
+    Command::new("list")
+        .about("List the blueprints in the blueprints folder")
+        .arg(blueprint_path_arg())
+        .arg(arg!(-s --sort <KEY> "column to sort by")
+            .value_parser(["name", "author", "date", "mass", "components", "files"])
+            .default_value("name"))
+        .arg(arg!(-r --reverse "reverse the order")
+            .action(ArgAction::SetTrue))
+        .arg(arg!(--author <NAME> "only blueprints whose author contains NAME, ignoring case"))
+        .arg(arg!(--since <DATE> "only blueprints saved on or after DATE, e.g. 2024-07 or 2024-07-20"))
+        .arg(arg!(--until <DATE> "only blueprints saved on or before DATE"))
+        .arg(arg!(--"has-code" "only blueprints with XenonCode")
+            .action(ArgAction::SetTrue))
+        .arg(arg!(-m --module <TYPE> "only blueprints with a component of this module, e.g. Computer; may be repeated")
+            .action(ArgAction::Append))
+        .arg(arg!(--json "print JSON instead of a table")
+            .action(ArgAction::SetTrue))
+}
+
+pub(crate) fn bom_command() -> Command {
+    Command::new("bom")
+        .about("Print a bill of materials: components, blocks and pipes of a blueprint")
+        .arg(blueprint_path_arg())
+        .arg(arg!(--format <FORMAT> "output format")
+            .value_parser(["table", "csv", "markdown"])
+            .default_value("table"))
+        .arg(
+            arg!([BLUEPRINT] "name of the blueprint without .json")
+                .num_args(0..)
+                .required(true)
+                .trailing_var_arg(true))
+}
+
+pub(crate) fn graph_command() -> Command {
+    Command::new("graph")
+        .about("Export how a blueprint's components are connected by pipes, as GraphViz DOT, Mermaid or JSON")
+        .arg(blueprint_path_arg())
+        .arg(arg!(--format <FORMAT> "output format")
+            .value_parser(["dot", "mermaid", "json"])
+            .default_value("dot"))
+        .arg(arg!(-t --type <TYPE> "only pipes of this type, e.g. data; may be repeated")
+            .action(ArgAction::Append))
+        .arg(arg!(--all "include components without pipes")
+            .action(ArgAction::SetTrue))
+        .arg(
+            arg!([BLUEPRINT] "name of the blueprint without .json")
+                .num_args(0..)
+                .required(true)
+                .trailing_var_arg(true))
+}
+
+pub(crate) fn validate_command() -> Command {
+    Command::new("validate")
+        .about("Check a blueprint's pipes for missing components, shared ports, gaps and unconnected components")
+        .arg(blueprint_path_arg())
+        .arg(arg!(-k --kind <MODULE> "report components of this module that have no pipes at all; may be repeated")
+            .action(ArgAction::Append)
+            .default_value("Computer"))
+        .arg(
+            arg!([BLUEPRINT] "name of the blueprint without .json")
+                .num_args(0..)
+                .required(true)
+                .trailing_var_arg(true))
+}
+
+pub(crate) fn export_command() -> Command {
+    Command::new("export")
+        .about("Export a blueprint's blocks, components and pipes as a 3D model, or its grid as voxels")
+        .arg(blueprint_path_arg())
+        .arg(arg!(--format <FORMAT> "file format (defaults to the extension of --out, or obj)")
+            .value_parser(["obj", "gltf", "stl", "vox"]))
+        .arg(arg!(-o --out <FILE> "file to write (defaults to the blueprint's name in the current folder)"))
+        .arg(arg!(--scale <FACTOR> "multiply every size, e.g. 1000 for an STL in millimeters (not for vox)")
+            .value_parser(value_parser!(f32))
+            .default_value("1"))
+        .arg(arg!(--"no-components" "leave out component occupancies")
+            .action(ArgAction::SetTrue))
+        .arg(arg!(--"no-pipes" "leave out pipes (vox never has them)")
+            .action(ArgAction::SetTrue))
+        .arg(
+            arg!([BLUEPRINT] "name of the blueprint without .json")
+                .num_args(0..)
+                .required(true)
+                .trailing_var_arg(true))
+}
+
+pub(crate) fn preview_command() -> Command {
+    Command::new("preview")
+        .about("Draw top, front and side views of a blueprint in the terminal or as SVG files")
+        .arg(blueprint_path_arg())
+        .arg(arg!(--view <VIEW> "view to draw; may be repeated (defaults to all three)")
+            .value_parser(["top", "front", "side"])
+            .action(ArgAction::Append))
+        .arg(arg!(--slice <HEIGHT> "draw the cells at this height, in meters, seen from the top")
+            .value_parser(value_parser!(f64)))
+        .arg(arg!(--svg <DIR> "write an SVG file per view to this folder instead of printing"))
+        .arg(arg!(-w --width <COLUMNS> "most terminal columns to use (defaults to $COLUMNS, or 80)")
+            .value_parser(value_parser!(usize)))
+        .arg(arg!(--"no-components" "leave out component occupancies")
+            .action(ArgAction::SetTrue))
+        .arg(
+            arg!([BLUEPRINT] "name of the blueprint without .json")
+                .num_args(0..)
+                .required(true)
+                .trailing_var_arg(true))
+}
+
+pub(crate) fn transform_command() -> Command {
+    Command::new("transform")
+        .about("Mirror, rotate and move everything in a blueprint")
+        .arg(blueprint_path_arg())
+        .arg(arg!(--mirror [AXIS] "mirror across the plane through the origin facing x, y or z (defaults to the blueprint's symmetry axis)")
+            .value_parser([PossibleValue::new("x"), PossibleValue::new("y"), PossibleValue::new("z"), PossibleValue::new("symmetry").hide(true)])
+            .num_args(0..=1)
+            .default_missing_value("symmetry"))
+        .arg(arg!(--rotate <TURN> "turn about an axis through the origin, e.g. y for 90 degrees or z:-90; may be repeated")
+            .action(ArgAction::Append))
+        .arg(arg!(--translate <OFFSET> "move by x,y,z meters, in steps of 0.25, e.g. 0,1.5,-2")
+            .allow_hyphen_values(true))
+        .arg(arg!(-o --out <FILE> "file to write (defaults to replacing the blueprint)"))
+        .arg(arg!(-n --"dry-run" "show the new bounding box without writing anything")
+            .action(ArgAction::SetTrue))
+        .arg(
+            arg!([BLUEPRINT] "name of the blueprint without .json")
+                .num_args(0..)
+                .required(true)
+                .trailing_var_arg(true))
+}
+
+pub(crate) fn combine_command() -> Command {
+    Command::new("combine")
+        .about("Combine several blueprints into one assembly")
+        .arg(blueprint_path_arg())
+        .arg(arg!(--at <OFFSET> "where to put the second, third, ... blueprint, as x,y,z meters in steps of 0.25; may be repeated")
+            .action(ArgAction::Append)
+            .allow_hyphen_values(true))
+        .arg(arg!(-o --out <FILE> "file to write the combined blueprint to").required(true))
+        .arg(arg!(--"allow-overlap" "write the blueprint even if blocks or components of different blueprints overlap")
+            .action(ArgAction::SetTrue))
+        .arg(arg!(<BLUEPRINTS> "blueprints to combine, by name or path; the first stays where it is")
+            .num_args(2..))
+}
+
+pub(crate) fn extract_command() -> Command {
+    Command::new("extract")
+        .about("Copy part of a blueprint, by region or by component, into a new blueprint")
+        .arg(blueprint_path_arg())
+        .arg(arg!(--region <CORNER> "keep what lies inside the box between two x,y,z corners, in meters in steps of 0.25")
+            .num_args(2)
+            .value_names(["MIN", "MAX"])
+            .allow_hyphen_values(true))
+        .arg(arg!(-c --component <NAME> "keep components with this alias (or module, if they have none); may be repeated")
+            .action(ArgAction::Append))
+        .arg(arg!(-m --module <MODULE> "keep components of a module, e.g. Computer; may be repeated")
+            .action(ArgAction::Append))
+        .arg(arg!(-o --out <FILE> "file to write the new blueprint to").required(true))
+        .arg(
+            arg!([BLUEPRINT] "name of the blueprint without .json")
+                .num_args(0..)
+                .required(true)
+                .trailing_var_arg(true))
 }
 
 pub(crate) fn package_command() -> Command {
     Command::new("package")
         .about("Gather metadata and files from blueprint for packaging")
         .arg(blueprint_path_arg())
+        .arg(arg!(-a --archive "write a single .archbelt file with checksums instead of loose files")
+            .action(ArgAction::SetTrue))
+        .arg(arg!(-o --out <DIR> "project folder to write to and read archbelt.json from (defaults to the current folder); with --archive, may also be the .archbelt file to write"))
+        .arg(layout_arg())
+        .arg(arg!(--readme <PATH> "README to include in the archive")
+            .requires("archive"))
         .arg(
             arg!([BLUEPRINT] "name of the blueprint without .json")
                 .num_args(0..)
@@ -34,7 +317,12 @@ pub(crate) fn yank_command() -> Command {
     Command::new("yank")
         .about("Yank code files from a blueprint")
         .arg(blueprint_path_arg())
+        .arg(out_arg())
+        .arg(layout_arg())
         .arg(arg!(-x --"no-collate" "do not collate the files by component")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("layout"))
+        .arg(arg!(--"no-dedup" "write identical files once per component instead of once into _shared")
             .action(ArgAction::SetTrue))
         .arg(arg!(-f --folder "yank blueprint files to folder named after blueprint")
             .action(ArgAction::SetTrue))
@@ -47,11 +335,77 @@ pub(crate) fn yank_command() -> Command {
                 .trailing_var_arg(true))
 }
 
+pub(crate) fn unpack_command() -> Command {
+    Command::new("unpack")
+        .about("Verify and extract a .archbelt package")
+        .arg(arg!(--to <DIR> "folder to extract to (defaults to the archive's name)"))
+        .arg(arg!(-l --list "only list the contents").action(ArgAction::SetTrue))
+        .arg(arg!(<ARCHIVE> "path to the .archbelt file"))
+}
+
+pub(crate) fn verify_command() -> Command {
+    Command::new("verify")
+        .about("Check the checksums and contents of a .archbelt package")
+        .arg(arg!(<ARCHIVE> "path to the .archbelt file"))
+}
+
+pub(crate) fn install_command() -> Command {
+    Command::new("install")
+        .about("Install a packaged blueprint into the blueprints folder")
+        .arg(blueprint_path_arg())
+        .arg(lib_install_arg())
+        .arg(registry_arg())
+        .arg(arg!(--"on-conflict" <ACTION> "what to do if a blueprint with the same name exists")
+            .value_parser(["rename", "overwrite", "skip"]))
+        .arg(arg!(-l --list "list the packages installed in the blueprints and library folders")
+            .action(ArgAction::SetTrue))
+        .arg(arg!([SOURCE] ".archbelt file, a folder it was unpacked to, or NAME[@VERSION] from the registry")
+            .required_unless_present("list"))
+}
+
+pub(crate) fn publish_command() -> Command {
+    Command::new("publish")
+        .about("Add a package to a local registry so it can be installed, and depended on, by name")
+        .arg(registry_arg())
+        .arg(arg!(<SOURCE> ".archbelt file or a package folder"))
+}
+
+
+pub(crate) fn uninstall_command() -> Command {
+    Command::new("uninstall")
+        .about("Remove an installed package from the blueprints or library folder")
+        .arg(blueprint_path_arg())
+        .arg(lib_install_arg())
+        .arg(arg!(-f --force "remove the blueprint even if it was changed since it was installed")
+            .action(ArgAction::SetTrue))
+        .arg(arg!(<NAME> "package name, or the name of the installed blueprint without .json"))
+}
+
+pub(crate) fn push_command() -> Command {
+    Command::new("push")
+        .about("Write yanked code files back into a blueprint")
+        .arg(blueprint_path_arg())
+        .arg(arg!(--from <DIR> "folder the blueprint was yanked to (defaults to the blueprint's folder, or the current one)")
+            .required(false))
+        .arg(arg!(-n --"dry-run" "show what would change without modifying the blueprint")
+            .action(ArgAction::SetTrue))
+        .arg(
+            arg!([BLUEPRINT] "name of the blueprint without .json")
+                .num_args(0..)
+                .required(true)
+                .trailing_var_arg(true))
+}
+
 pub(crate) fn watch_command() -> Command {
     Command::new("watch")
         .about("watch for changes to blueprint file(s); will yank on change as if -f is set")
         .arg(blueprint_path_arg())
+        .arg(out_arg())
+        .arg(layout_arg())
         .arg(arg!(-x --"no-collate" "do not collate the files by component (use at own risk; this can get very messy..)")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("layout"))
+        .arg(arg!(--"no-dedup" "write identical files once per component instead of once into _shared")
             .action(ArgAction::SetTrue))
         .arg(arg!([TARGET] "target blueprint name (if none given, watches all)")
             .num_args(0..)
@@ -63,4 +417,24 @@ fn blueprint_path_arg() -> Arg {
     arg!(--"blueprint-path" <PATH> "path to blueprints folder")
         .num_args(1)
         .required(false)
+}
+
+fn out_arg() -> Arg {
+    arg!(-o --out <DIR> "folder to write to (defaults to the current folder)")
+        .required(false)
+}
+
+fn layout_arg() -> Arg {
+    arg!(--layout <TEMPLATE> "where files go below the output folder, using {blueprint}, {component} and {file} (defaults to {component}/{file})")
+        .required(false)
+}
+
+fn registry_arg() -> Arg {
+    arg!(--registry <DIR> "registry folder or file:// URL (also read from ARCHBELT_REGISTRY)")
+        .required(false)
+}
+
+fn lib_install_arg() -> Arg {
+    arg!(--lib <DIR> "folder library packages are installed to (defaults to the archbelt data folder)")
+        .required(false)
 }
\ No newline at end of file
@@ -0,0 +1,278 @@
+use std::path::{Path, PathBuf};
+use clap::{ArgMatches, Error, FromArgMatches};
+use crate::archean::json::Blueprint;
+use crate::command;
+use crate::command::sanitize;
+use crate::xenon::doc::{document, Direction, FileDoc, PortUse};
+
+pub enum DocSource {
+    Blueprint(PathBuf),
+    /// a folder of `.xc` files, e.g. a shared library or a yanked blueprint
+    Dir(PathBuf),
+}
+
+pub struct DocConfig {
+    pub(crate) source: DocSource,
+    pub(crate) out: PathBuf,
+    pub(crate) html: bool
+}
+
+impl FromArgMatches for DocConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let source = match matches.get_one::<String>("dir") {
+            Some(dir) => DocSource::Dir(PathBuf::from(dir)),
+            None => DocSource::Blueprint(command::extract_filename("BLUEPRINT".to_string(), matches)?),
+        };
+        let out = matches.get_one::<String>("out").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("docs"));
+        let html = matches.get_one::<String>("format").is_some_and(|f| f == "html");
+
+        Ok(DocConfig {
+            source,
+            out,
+            html
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = DocConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+struct ComponentDoc {
+    name: String,
+    files: Vec<FileDoc>,
+}
+
+impl ComponentDoc {
+    /// Every port use of the component, ordered by port so readers and writers line up.
+    fn ports(&self) -> Vec<(&str, &PortUse)> {
+        let mut ports: Vec<(&str, &PortUse)> = self.files.iter()
+            .flat_map(|f| f.ports.iter().map(move |p| (f.name.as_str(), p)))
+            .collect();
+        ports.sort_by(|(a_file, a), (b_file, b)| {
+            let number = |p: &PortUse| p.port.parse::<f64>().ok();
+            number(a).partial_cmp(&number(b)).unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.port.cmp(&b.port))
+                .then(a.direction.cmp(&b.direction))
+                .then(a_file.cmp(b_file))
+                .then(a.line.cmp(&b.line))
+        });
+        ports
+    }
+}
+
+fn direction(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Read => "read",
+        Direction::Write => "write",
+    }
+}
+
+fn from_blueprint(path: &Path) -> Result<Vec<ComponentDoc>, String> {
+    let source = command::get_blueprint_object(path.to_path_buf()).map_err(|_| "Blueprint could not be opened".to_string())?;
+    let blueprint = serde_json::from_str::<Blueprint>(&source).map_err(|_| "Blueprint could not be parsed".to_string())?;
+
+    let mut components: Vec<(usize, ComponentDoc)> = vec![];
+    for file in blueprint.xc_files() {
+        let doc = document(file.file_name(), file.file_content());
+        match components.iter_mut().find(|(index, _)| *index == file.component_index()) {
+            Some((_, component)) => component.files.push(doc),
+            None => components.push((file.component_index(), ComponentDoc {
+                name: file.component().to_string(),
+                files: vec![doc],
+            })),
+        }
+    }
+    Ok(components.into_iter().map(|(_, c)| c).collect())
+}
+
+fn folder_name(dir: &Path) -> String {
+    dir.canonicalize().ok()
+        .and_then(|d| d.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "docs".to_string())
+}
+
+/// A yanked folder: `.xc` files in subfolders belong to the component named after the folder,
+/// with the names yank had to escape restored.
+fn from_dir(dir: &Path) -> Vec<ComponentDoc> {
+    let mut files: Vec<PathBuf> = command::walk_files(dir).into_iter()
+        .filter(|p| p.extension().is_some_and(|e| e == "xc"))
+        .collect();
+    files.sort();
+
+    let mut components: Vec<ComponentDoc> = vec![];
+    for path in files {
+        let Ok(code) = std::fs::read_to_string(&path) else {
+            eprintln!("Skipping {:?}: could not be read", path);
+            continue;
+        };
+        let folder = path.parent().and_then(|p| p.strip_prefix(dir).ok()).unwrap_or(Path::new(""));
+        let name = if folder.as_os_str().is_empty() {
+            folder_name(dir)
+        } else {
+            sanitize::unescape(&folder.to_string_lossy())
+        };
+        let doc = document(&sanitize::unescape(&path.file_name().unwrap_or_default().to_string_lossy()), &code);
+        match components.iter_mut().find(|c| c.name == name) {
+            Some(component) => component.files.push(doc),
+            None => components.push(ComponentDoc { name, files: vec![doc] }),
+        }
+    }
+    components
+}
+
+fn markdown(title: &str, components: &[ComponentDoc]) -> String {
+    let mut out = format!("# {}\n", title);
+    for component in components {
+        out += &format!("\n## {}\n", component.name);
+
+        let ports = component.ports();
+        if !ports.is_empty() {
+            out += "\n### IO ports\n\n| Port | Channel | Direction | Via | Where |\n|---|---|---|---|---|\n";
+            for (file, port) in ports {
+                out += &format!("| `{}` | {} | {} | `{}` | {}:{} |\n",
+                    port.port,
+                    port.channel.as_ref().map(|c| format!("`{}`", c)).unwrap_or_default(),
+                    direction(port.direction),
+                    port.via,
+                    file,
+                    port.line + 1);
+            }
+        }
+
+        for file in &component.files {
+            out += &format!("\n### {}\n", file.name);
+            if let Some(summary) = &file.summary {
+                out += &format!("\n{}\n", summary);
+            }
+            if !file.entries.is_empty() {
+                let entries: Vec<String> = file.entries.iter().map(|e| format!("`{}`", e)).collect();
+                out += &format!("\nEntry points: {}\n", entries.join(", "));
+            }
+            if !file.consts.is_empty() {
+                out += "\n#### Constants\n\n";
+                for constant in &file.consts {
+                    out += &format!("- `{}`", constant.signature);
+                    if let Some(doc) = &constant.doc {
+                        out += &format!(" — {}", doc.replace('\n', " "));
+                    }
+                    out += "\n";
+                }
+            }
+            if !file.functions.is_empty() {
+                out += "\n#### Functions\n";
+                for function in &file.functions {
+                    out += &format!("\n`{}`\n", function.signature);
+                    if let Some(doc) = &function.doc {
+                        out += &format!("\n{}\n", doc);
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn html(title: &str, components: &[ComponentDoc]) -> String {
+    let mut out = format!(concat!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n",
+        "<style>body{{font-family:sans-serif;max-width:60em;margin:auto;padding:1em}}",
+        "code{{background:#eee;padding:0 .2em}}table{{border-collapse:collapse}}",
+        "td,th{{border:1px solid #ccc;padding:.2em .5em;text-align:left}}</style>\n",
+        "</head>\n<body>\n<h1>{0}</h1>\n"), escape(title));
+    for component in components {
+        out += &format!("<h2>{}</h2>\n", escape(&component.name));
+
+        let ports = component.ports();
+        if !ports.is_empty() {
+            out += "<h3>IO ports</h3>\n<table>\n<tr><th>Port</th><th>Channel</th><th>Direction</th><th>Via</th><th>Where</th></tr>\n";
+            for (file, port) in ports {
+                out += &format!("<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td><code>{}</code></td><td>{}:{}</td></tr>\n",
+                    escape(&port.port),
+                    port.channel.as_ref().map(|c| format!("<code>{}</code>", escape(c))).unwrap_or_default(),
+                    direction(port.direction),
+                    escape(&port.via),
+                    escape(file),
+                    port.line + 1);
+            }
+            out += "</table>\n";
+        }
+
+        for file in &component.files {
+            out += &format!("<h3>{}</h3>\n", escape(&file.name));
+            if let Some(summary) = &file.summary {
+                out += &format!("<p>{}</p>\n", escape(summary));
+            }
+            if !file.entries.is_empty() {
+                let entries: Vec<String> = file.entries.iter().map(|e| format!("<code>{}</code>", escape(e))).collect();
+                out += &format!("<p>Entry points: {}</p>\n", entries.join(", "));
+            }
+            if !file.consts.is_empty() {
+                out += "<h4>Constants</h4>\n<ul>\n";
+                for constant in &file.consts {
+                    out += &format!("<li><code>{}</code>", escape(&constant.signature));
+                    if let Some(doc) = &constant.doc {
+                        out += &format!(" — {}", escape(doc));
+                    }
+                    out += "</li>\n";
+                }
+                out += "</ul>\n";
+            }
+            if !file.functions.is_empty() {
+                out += "<h4>Functions</h4>\n<dl>\n";
+                for function in &file.functions {
+                    out += &format!("<dt><code>{}</code></dt>\n", escape(&function.signature));
+                    if let Some(doc) = &function.doc {
+                        out += &format!("<dd>{}</dd>\n", escape(doc));
+                    }
+                }
+                out += "</dl>\n";
+            }
+        }
+    }
+    out += "</body>\n</html>\n";
+    out
+}
+
+pub fn generate_docs(args: &ArgMatches) {
+    let config = match DocConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let (title, components) = match &config.source {
+        DocSource::Dir(dir) => (folder_name(dir), from_dir(dir)),
+        DocSource::Blueprint(file_name) => match from_blueprint(file_name) {
+            Ok(components) => (file_name.file_stem().unwrap_or_default().to_string_lossy().to_string(), components),
+            Err(e) => {
+                eprintln!("🚨 {}! 🚨", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    if components.is_empty() {
+        eprintln!("🚨 No XenonCode found! 🚨");
+        std::process::exit(1);
+    }
+
+    let (content, extension) = if config.html {
+        (html(&title, &components), "html")
+    } else {
+        (markdown(&title, &components), "md")
+    };
+    let path = config.out.join(format!("{}.{}", title, extension));
+    let written = std::fs::create_dir_all(&config.out).and_then(|_| std::fs::write(&path, content));
+    if let Err(e) = written {
+        eprintln!("Error writing {:?}: {:?}", path, e);
+        std::process::exit(1);
+    }
+    println!("Wrote {:?}", path);
+}
@@ -0,0 +1,332 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use clap::{ArgMatches, Error, FromArgMatches};
+use serde_json::json;
+use crate::archean::geometry::{self, Mesh, Rgba};
+use crate::archean::json::Blueprint;
+use crate::command;
+use crate::command::vox::vox;
+
+/// Sides of the cylinders pipes are drawn as.
+const PIPE_SIDES: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Obj,
+    Gltf,
+    Stl,
+    Vox,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Obj => "obj",
+            ExportFormat::Gltf => "gltf",
+            ExportFormat::Stl => "stl",
+            ExportFormat::Vox => "vox",
+        }
+    }
+}
+
+pub struct ExportConfig {
+    pub(crate) file_name: PathBuf,
+    pub(crate) format: ExportFormat,
+    pub(crate) out: PathBuf,
+    pub(crate) scale: f32,
+    pub(crate) components: bool,
+    pub(crate) pipes: bool
+}
+
+impl FromArgMatches for ExportConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let file_name = command::extract_filename("BLUEPRINT".to_string(), matches)?;
+        let out = matches.get_one::<String>("out").map(PathBuf::from);
+        // without --format, the extension of --out decides
+        let format = match matches.get_one::<String>("format").map(String::as_str)
+            .or_else(|| out.as_ref().and_then(|o| o.extension()).and_then(|e| e.to_str())) {
+            Some("gltf") => ExportFormat::Gltf,
+            Some("stl") => ExportFormat::Stl,
+            Some("vox") => ExportFormat::Vox,
+            _ => ExportFormat::Obj,
+        };
+        let out = out.unwrap_or_else(|| {
+            PathBuf::from(file_name.file_stem().unwrap_or_default()).with_extension(format.extension())
+        });
+
+        Ok(ExportConfig {
+            file_name,
+            format,
+            out,
+            scale: *matches.get_one::<f32>("scale").unwrap_or(&1.0),
+            components: !*matches.get_one::<bool>("no-components").unwrap_or(&false),
+            pipes: !*matches.get_one::<bool>("no-pipes").unwrap_or(&false)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = ExportConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+/// Meshes for a blueprint, in meters: blocks merged by color, then one mesh per component and per
+/// pipe so they keep their names.
+pub fn meshes(blueprint: &Blueprint, components: bool, pipes: bool) -> Vec<Mesh> {
+    let mut blocks: BTreeMap<Rgba, Mesh> = BTreeMap::new();
+    for block in geometry::blocks(blueprint) {
+        let (min, max) = block.bounds();
+        blocks.entry(block.color)
+            .or_insert_with(|| Mesh::new(&format!("blocks_{}", block.color.hex()), block.color))
+            .cuboid(min, max);
+    }
+    let mut meshes: Vec<Mesh> = blocks.into_values().collect();
+
+    if components {
+        let mut parts: BTreeMap<usize, Mesh> = BTreeMap::new();
+        for cells in geometry::occupancies(blueprint) {
+            let geometry::Part::Component(index) = cells.part else {
+                continue;
+            };
+            let (min, max) = cells.bounds();
+            parts.entry(index)
+                .or_insert_with(|| Mesh::new(&format!("{} #{}", blueprint.data.components[index].name(), index), cells.color))
+                .cuboid(min, max);
+        }
+        meshes.extend(parts.into_values());
+    }
+
+    if pipes {
+        let mut parts: BTreeMap<usize, Mesh> = BTreeMap::new();
+        for run in geometry::pipe_runs(blueprint) {
+            parts.entry(run.pipe)
+                .or_insert_with(|| Mesh::new(&format!("pipe #{} ({})", run.pipe, blueprint.data.pipes[run.pipe].type_field), run.color))
+                .cylinder(run.start, run.end, run.radius, PIPE_SIDES);
+        }
+        meshes.extend(parts.into_values());
+    }
+    meshes
+}
+
+/// Name for OBJ objects and materials, which end at whitespace.
+fn obj_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+fn material_name(color: Rgba) -> String {
+    format!("color_{}{:02x}", color.hex(), color.0[3])
+}
+
+fn obj(meshes: &[Mesh], library: &str) -> (String, String) {
+    let mut obj = format!("mtllib {}\n", library);
+    let mut offset = 1;
+    for mesh in meshes {
+        obj.push_str(&format!("o {}\nusemtl {}\n", obj_name(&mesh.name), material_name(mesh.color)));
+        for [x, y, z] in &mesh.positions {
+            obj.push_str(&format!("v {} {} {}\n", x, y, z));
+        }
+        for [x, y, z] in &mesh.normals {
+            obj.push_str(&format!("vn {} {} {}\n", x, y, z));
+        }
+        for triangle in &mesh.triangles {
+            let [a, b, c] = triangle.map(|i| i + offset);
+            obj.push_str(&format!("f {}//{} {}//{} {}//{}\n", a, a, b, b, c, c));
+        }
+        offset += mesh.positions.len() as u32;
+    }
+
+    let mut mtl = String::new();
+    let colors: std::collections::BTreeSet<Rgba> = meshes.iter().map(|m| m.color).collect();
+    for color in colors {
+        let [r, g, b, a] = color.0.map(|c| c as f32 / 255.0);
+        mtl.push_str(&format!("newmtl {}\nKd {:.4} {:.4} {:.4}\nd {:.4}\n\n", material_name(color), r, g, b, a));
+    }
+    (obj, mtl)
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// sRGB byte to the linear value glTF colors use.
+fn linear(c: u8) -> f32 {
+    (c as f32 / 255.0).powf(2.2)
+}
+
+/// A self-contained glTF 2.0 file, with the buffer embedded as a data URI.
+fn gltf(title: &str, meshes: &[Mesh]) -> String {
+    const ARRAY_BUFFER: u32 = 34962;
+    const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+    const FLOAT: u32 = 5126;
+    const UNSIGNED_INT: u32 = 5125;
+
+    let mut buffer: Vec<u8> = vec![];
+    let mut views = vec![];
+    let mut accessors = vec![];
+    let mut view = |buffer: &mut Vec<u8>, data: Vec<u8>, target: u32| {
+        views.push(json!({ "buffer": 0, "byteOffset": buffer.len(), "byteLength": data.len(), "target": target }));
+        buffer.extend(data);
+        views.len() - 1
+    };
+
+    let mut materials: Vec<Rgba> = vec![];
+    let mut gltf_meshes = vec![];
+    for mesh in meshes {
+        let (min, max) = mesh.bounds();
+        let positions = view(&mut buffer, mesh.positions.iter().flatten().flat_map(|v| v.to_le_bytes()).collect(), ARRAY_BUFFER);
+        accessors.push(json!({ "bufferView": positions, "componentType": FLOAT, "count": mesh.positions.len(), "type": "VEC3", "min": min, "max": max }));
+        let normals = view(&mut buffer, mesh.normals.iter().flatten().flat_map(|v| v.to_le_bytes()).collect(), ARRAY_BUFFER);
+        accessors.push(json!({ "bufferView": normals, "componentType": FLOAT, "count": mesh.normals.len(), "type": "VEC3" }));
+        let indices = view(&mut buffer, mesh.triangles.iter().flatten().flat_map(|i| i.to_le_bytes()).collect(), ELEMENT_ARRAY_BUFFER);
+        accessors.push(json!({ "bufferView": indices, "componentType": UNSIGNED_INT, "count": mesh.triangles.len() * 3, "type": "SCALAR" }));
+
+        let material = match materials.iter().position(|c| *c == mesh.color) {
+            Some(material) => material,
+            None => {
+                materials.push(mesh.color);
+                materials.len() - 1
+            }
+        };
+        gltf_meshes.push(json!({
+            "name": mesh.name,
+            "primitives": [{
+                "attributes": { "POSITION": accessors.len() - 3, "NORMAL": accessors.len() - 2 },
+                "indices": accessors.len() - 1,
+                "material": material
+            }]
+        }));
+    }
+
+    let materials: Vec<_> = materials.iter().map(|color| {
+        let [r, g, b, a] = color.0;
+        let mut material = json!({
+            "name": material_name(*color),
+            "pbrMetallicRoughness": { "baseColorFactor": [linear(r), linear(g), linear(b), a as f32 / 255.0], "metallicFactor": 0.0, "roughnessFactor": 0.8 }
+        });
+        if a < 255 {
+            material["alphaMode"] = json!("BLEND");
+        }
+        material
+    }).collect();
+
+    let gltf = json!({
+        "asset": { "version": "2.0", "generator": format!("{} {}", crate::statics::COMMAND, crate::statics::VERSION) },
+        "scene": 0,
+        "scenes": [{ "name": title, "nodes": (0..meshes.len()).collect::<Vec<_>>() }],
+        "nodes": (0..meshes.len()).map(|i| json!({ "name": meshes[i].name, "mesh": i })).collect::<Vec<_>>(),
+        "meshes": gltf_meshes,
+        "materials": materials,
+        "accessors": accessors,
+        "bufferViews": views,
+        "buffers": [{ "byteLength": buffer.len(), "uri": format!("data:application/octet-stream;base64,{}", base64(&buffer)) }]
+    });
+    serde_json::to_string(&gltf).unwrap()
+}
+
+/// Binary STL of every mesh; colors are dropped.
+fn stl(title: &str, meshes: &[Mesh]) -> Vec<u8> {
+    let count: usize = meshes.iter().map(|m| m.triangles.len()).sum();
+    let mut header = format!("{} exported by {}", title, crate::statics::COMMAND).into_bytes();
+    header.resize(80, b' ');
+
+    let mut out = header;
+    out.extend((count as u32).to_le_bytes());
+    for mesh in meshes {
+        for triangle in &mesh.triangles {
+            let [a, b, c] = triangle.map(|i| mesh.positions[i as usize]);
+            let normal = mesh.normals[triangle[0] as usize];
+            for vertex in [normal, a, b, c] {
+                vertex.iter().for_each(|v| out.extend(v.to_le_bytes()));
+            }
+            out.extend(0u16.to_le_bytes());
+        }
+    }
+    out
+}
+
+fn write(path: &Path, data: &[u8]) {
+    let written = match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(parent) => std::fs::create_dir_all(parent).and_then(|_| std::fs::write(path, data)),
+        None => std::fs::write(path, data),
+    };
+    if let Err(e) = written {
+        eprintln!("Error saving {:?}: {:?}", path, e);
+        std::process::exit(1);
+    }
+}
+
+pub fn export_blueprint(args: &ArgMatches) {
+    let config = match ExportConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let blueprint = command::get_blueprint_object(config.file_name.clone()).ok()
+        .and_then(|source| serde_json::from_str::<Blueprint>(&source).ok());
+    let Some(blueprint) = blueprint else {
+        eprintln!("🚨 Blueprint could not be parsed! 🚨");
+        std::process::exit(1);
+    };
+
+    let title = config.file_name.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    match config.format {
+        ExportFormat::Vox => export_voxels(&config, &blueprint),
+        _ => export_meshes(&config, &title, &blueprint),
+    }
+}
+
+fn export_voxels(config: &ExportConfig, blueprint: &Blueprint) {
+    let mut boxes = geometry::blocks(blueprint);
+    if config.components {
+        boxes.extend(geometry::occupancies(blueprint));
+    }
+    if boxes.is_empty() {
+        eprintln!("🚨 Nothing to export! 🚨");
+        std::process::exit(1);
+    }
+    write(&config.out, &vox(&boxes));
+    println!("Exported {} box(es) as voxels to {:?}", boxes.len(), config.out);
+}
+
+fn export_meshes(config: &ExportConfig, title: &str, blueprint: &Blueprint) {
+    let mut meshes = meshes(blueprint, config.components, config.pipes);
+    if meshes.is_empty() {
+        eprintln!("🚨 Nothing to export! 🚨");
+        std::process::exit(1);
+    }
+    for mesh in &mut meshes {
+        mesh.scale(config.scale);
+        if config.format == ExportFormat::Stl {
+            mesh.z_up();
+        }
+    }
+
+    if config.format == ExportFormat::Obj {
+        let library = config.out.with_extension("mtl");
+        let (obj, mtl) = obj(&meshes, &library.file_name().unwrap_or_default().to_string_lossy());
+        write(&config.out, obj.as_bytes());
+        write(&library, mtl.as_bytes());
+    } else if config.format == ExportFormat::Gltf {
+        write(&config.out, gltf(title, &meshes).as_bytes());
+    } else {
+        write(&config.out, &stl(title, &meshes));
+    }
+    let triangles: usize = meshes.iter().map(|m| m.triangles.len()).sum();
+    println!("Exported {} mesh(es), {} triangles, to {:?}", meshes.len(), triangles, config.out);
+}
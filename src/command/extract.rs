@@ -0,0 +1,257 @@
+use std::path::PathBuf;
+use clap::{ArgMatches, Error, FromArgMatches};
+use clap::error::ErrorKind;
+use serde_json::Value;
+use crate::archean::geometry::{self, segment_end, segment_start, GridBox, Part, Point, GRID};
+use crate::archean::json::{Blueprint, Component};
+use crate::archean::raw::RawBlueprint;
+use crate::command;
+use crate::command::transform::grid_offset;
+
+/// How far outside the region a pipe may reach and still count as inside, in meters.
+const TOLERANCE: f64 = 0.01;
+
+pub struct ExtractConfig {
+    pub(crate) file_name: PathBuf,
+    /// lowest and highest corner, in grid units (highest exclusive)
+    pub(crate) region: Option<([i64; 3], [i64; 3])>,
+    /// component names (alias, or module if it has none)
+    pub(crate) components: Vec<String>,
+    /// modules, lowercase
+    pub(crate) modules: Vec<String>,
+    pub(crate) out: PathBuf
+}
+
+impl FromArgMatches for ExtractConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let file_name = command::extract_filename("BLUEPRINT".to_string(), matches)?;
+        let region = match matches.get_many::<String>("region") {
+            Some(corners) => {
+                let corners = corners.map(|c| grid_offset(c)).collect::<Result<Vec<_>, _>>()?;
+                let [a, b] = corners[..] else {
+                    return Err(Error::raw(ErrorKind::InvalidValue, "--region needs two corners\n"));
+                };
+                Some(([0, 1, 2].map(|i| a[i].min(b[i])), [0, 1, 2].map(|i| a[i].max(b[i]))))
+            }
+            None => None,
+        };
+        let components: Vec<String> = matches.get_many::<String>("component").map(|c| c.cloned().collect()).unwrap_or_default();
+        let modules: Vec<String> = matches.get_many::<String>("module")
+            .map(|modules| modules.map(|m| m.to_lowercase()).collect())
+            .unwrap_or_default();
+        if region.is_none() && components.is_empty() && modules.is_empty() {
+            return Err(Error::raw(ErrorKind::InvalidValue, "select something with --region, --component or --module\n"));
+        }
+
+        Ok(ExtractConfig {
+            file_name,
+            region,
+            components,
+            modules,
+            out: PathBuf::from(matches.get_one::<String>("out").cloned().unwrap_or_default())
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = ExtractConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+/// What to keep of a blueprint.
+struct Selection {
+    blocks: Vec<bool>,
+    /// the new index of each component that is kept
+    components: Vec<Option<usize>>,
+    pipes: Vec<bool>,
+    /// pipes left out because only one end, or part of the run, is selected
+    cut_pipes: usize,
+}
+
+impl Selection {
+    fn is_everything(&self) -> bool {
+        self.blocks.iter().all(|k| *k) && self.components.iter().all(Option::is_some)
+    }
+}
+
+impl ExtractConfig {
+    fn cells_inside(&self, grid_box: &GridBox) -> bool {
+        self.region.is_none_or(|(min, max)| (0..3).all(|i| min[i] <= grid_box.min[i] && grid_box.max()[i] <= max[i]))
+    }
+
+    fn point_inside(&self, point: Point) -> bool {
+        self.region.is_none_or(|(min, max)| {
+            (0..3).all(|i| min[i] as f64 * GRID - TOLERANCE <= point[i] && point[i] <= max[i] as f64 * GRID + TOLERANCE)
+        })
+    }
+
+    fn matches(&self, component: &Component) -> bool {
+        let filtered = !self.components.is_empty() || !self.modules.is_empty();
+        !filtered || self.components.contains(&component.name()) || self.modules.contains(&component.module.to_lowercase())
+    }
+
+    /// Blocks inside the region; components that match the filters and are inside the region;
+    /// and pipes that stay inside the region with every attached end on a kept component. Pipes
+    /// attached to no component are only kept with a region.
+    fn select(&self, blueprint: &Blueprint) -> Selection {
+        let blocks = geometry::blocks(blueprint).iter()
+            .map(|b| self.region.is_some() && self.cells_inside(b))
+            .collect();
+
+        let occupancies = geometry::occupancies(blueprint);
+        let mut next = 0;
+        let components = blueprint.data.components.iter().enumerate().map(|(index, c)| {
+            let inside = self.point_inside([c.position.x, c.position.y, c.position.z])
+                && occupancies.iter().filter(|o| o.part == Part::Component(index)).all(|o| self.cells_inside(o));
+            (self.matches(c) && inside).then(|| {
+                next += 1;
+                next - 1
+            })
+        }).collect::<Vec<_>>();
+
+        let kept = |index: i64| usize::try_from(index).ok().and_then(|i| components.get(i).copied().flatten()).is_some();
+        let mut cut_pipes = 0;
+        let pipes = blueprint.data.pipes.iter().map(|p| {
+            // a negative index is an end that isn't attached to a component
+            let ends: Vec<bool> = [p.a_component, p.b_component].into_iter().filter(|i| *i >= 0).map(kept).collect();
+            let inside = p.segments.iter().all(|s| self.point_inside(segment_start(s)) && segment_end(s).is_some_and(|e| self.point_inside(e)));
+            let keep = inside && ends.iter().all(|e| *e) && (!ends.is_empty() || self.region.is_some());
+            if ends.iter().any(|e| *e) && !keep {
+                cut_pipes += 1;
+            }
+            keep
+        }).collect();
+
+        Selection { blocks, components, pipes, cut_pipes }
+    }
+}
+
+fn keep(values: Vec<Value>, kept: &[bool]) -> Vec<Value> {
+    values.into_iter().zip(kept).filter(|(_, k)| **k).map(|(v, _)| v).collect()
+}
+
+fn data_array<'a>(raw: &'a mut RawBlueprint, key: &str) -> Option<&'a mut Vec<Value>> {
+    raw.data_entry_mut(key).and_then(Value::as_array_mut)
+}
+
+/// Renumber the component index in `value` by `remap`.
+fn remap(value: Option<&mut Value>, components: &[Option<usize>]) {
+    if let Some(value) = value {
+        let new = value.as_i64().and_then(|i| usize::try_from(i).ok()).and_then(|i| components.get(i).copied().flatten());
+        if let Some(new) = new {
+            *value = Value::from(new as i64);
+        }
+    }
+}
+
+/// Drop everything that isn't selected, renumber components and recompute the bounding box. Unless
+/// everything is kept, doors, frames and labels are dropped and the mass is reset to zero, since
+/// archbelt can't tell where they belong or weigh parts.
+fn extract(blueprint: &Blueprint, raw: &mut RawBlueprint, selection: &Selection) {
+    if let Some(blocks) = raw.blocks_mut() {
+        *blocks = keep(std::mem::take(blocks), &selection.blocks);
+    }
+    let kept_components: Vec<bool> = selection.components.iter().map(Option::is_some).collect();
+    if let Some(components) = raw.components_mut() {
+        *components = keep(std::mem::take(components), &kept_components);
+    }
+    if let Some(pipes) = raw.pipes_mut() {
+        *pipes = keep(std::mem::take(pipes), &selection.pipes);
+        for pipe in pipes.iter_mut() {
+            remap(pipe.get_mut("a_component"), &selection.components);
+            remap(pipe.get_mut("b_component"), &selection.components);
+        }
+    }
+    if let Some(builds) = data_array(raw, "composite_builds") {
+        let kept: Vec<bool> = blueprint.data.composite_builds.iter()
+            .map(|b| usize::try_from(b.component).ok().and_then(|i| selection.components.get(i).copied().flatten()).is_some())
+            .collect();
+        *builds = keep(std::mem::take(builds), &kept);
+        for build in builds.iter_mut() {
+            remap(build.get_mut("component"), &selection.components);
+        }
+    }
+    if !selection.is_everything() {
+        for key in ["doors", "frames", "labels"] {
+            if let Some(values) = data_array(raw, key) {
+                values.clear();
+            }
+        }
+    }
+
+    let mut boxes: Vec<GridBox> = geometry::blocks(blueprint).into_iter().zip(&selection.blocks).filter(|(_, k)| **k).map(|(b, _)| b).collect();
+    boxes.extend(geometry::occupancies(blueprint).into_iter().filter(|o| match o.part {
+        Part::Component(index) => selection.components[index].is_some(),
+        Part::Block(_) => false,
+    }));
+    let (mut min, mut max) = ([f64::MAX; 3], [f64::MIN; 3]);
+    for (low, high) in boxes.iter().map(GridBox::bounds) {
+        (0..3).for_each(|i| {
+            min[i] = min[i].min(low[i]);
+            max[i] = max[i].max(high[i]);
+        });
+    }
+    if boxes.is_empty() {
+        (min, max) = ([0.0; 3], [0.0; 3]);
+    }
+    raw.set_bounds(min, max);
+    if !selection.is_everything() {
+        if let Some(entry) = raw.entry_mut("mass") {
+            *entry = Value::from(0.0);
+        }
+    }
+}
+
+pub fn extract_blueprint(args: &ArgMatches) {
+    let config = match ExtractConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(e) if e.kind() == ErrorKind::InvalidValue => {
+            eprint!("{}", e);
+            std::process::exit(1);
+        }
+        Err(_) => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let source = command::get_blueprint_object(config.file_name.clone()).unwrap_or_default();
+    let (blueprint, mut raw) = match (serde_json::from_str::<Blueprint>(&source), RawBlueprint::parse(&source)) {
+        (Ok(blueprint), Ok(raw)) => (blueprint, raw),
+        _ => {
+            eprintln!("🚨 Blueprint could not be parsed! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let selection = config.select(&blueprint);
+    let count = |kept: &[bool]| kept.iter().filter(|k| **k).count();
+    let components = selection.components.iter().flatten().count();
+    if count(&selection.blocks) == 0 && components == 0 {
+        eprintln!("🚨 Nothing in the blueprint matches the selection 🚨");
+        std::process::exit(1);
+    }
+    extract(&blueprint, &mut raw, &selection);
+
+    println!("Extracted {} block(s), {} component(s) and {} pipe(s)", count(&selection.blocks), components, count(&selection.pipes));
+    if selection.cut_pipes > 0 {
+        eprintln!("Warning: {} pipe(s) leading out of the selection were left out", selection.cut_pipes);
+    }
+    let data = &blueprint.data;
+    if !selection.is_everything() && data.doors.len() + data.frames.len() + data.labels.len() > 0 {
+        eprintln!("Warning: {} door(s), {} frame(s) and {} label(s) were left out",
+                  data.doors.len(), data.frames.len(), data.labels.len());
+    }
+    if !selection.is_everything() && blueprint.mass != 0.0 {
+        eprintln!("Warning: the mass was set to 0 since archbelt can't weigh the extracted parts");
+    }
+    if serde_json::from_str::<Blueprint>(&raw.to_json()).is_err() {
+        eprintln!("🚨 Extracted blueprint could not be parsed; nothing was written 🚨");
+        std::process::exit(1);
+    }
+    if let Err(e) = std::fs::write(&config.out, raw.to_json()) {
+        eprintln!("Error saving {:?}: {:?}", config.out, e);
+        std::process::exit(1);
+    }
+    println!("Wrote {:?}", config.out);
+}
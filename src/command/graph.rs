@@ -0,0 +1,197 @@
+use std::path::PathBuf;
+use clap::{ArgMatches, Error, FromArgMatches};
+use serde_derive::Serialize;
+use crate::archean::json::Blueprint;
+use crate::command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+}
+
+pub struct GraphConfig {
+    pub(crate) file_name: PathBuf,
+    pub(crate) format: GraphFormat,
+    /// pipe types to keep, any case; empty keeps every pipe
+    pub(crate) pipe_types: Vec<String>,
+    /// include components without any (matching) pipes
+    pub(crate) all: bool
+}
+
+impl FromArgMatches for GraphConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let format = match matches.get_one::<String>("format").map(String::as_str) {
+            Some("mermaid") => GraphFormat::Mermaid,
+            Some("json") => GraphFormat::Json,
+            _ => GraphFormat::Dot,
+        };
+
+        Ok(GraphConfig {
+            file_name: command::extract_filename("BLUEPRINT".to_string(), matches)?,
+            format,
+            pipe_types: matches.get_many::<String>("type")
+                .map(|types| types.map(|t| t.to_lowercase()).collect())
+                .unwrap_or_default(),
+            all: *matches.get_one::<bool>("all").unwrap_or(&false)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = GraphConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Node {
+    /// index of the component in the blueprint
+    pub index: usize,
+    pub name: String,
+    pub module: String,
+    #[serde(rename = "type")]
+    pub type_field: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Edge {
+    /// index of the pipe in the blueprint
+    pub pipe: usize,
+    pub from: usize,
+    pub from_port: String,
+    pub to: usize,
+    pub to_port: String,
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub length: f64,
+}
+
+/// Components as nodes and the pipes between them as edges.
+#[derive(Debug, Clone, Serialize)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// Build the graph of a blueprint, keeping pipes for which `keep` is true. Pipes with an end
+    /// on a component that doesn't exist are left out.
+    pub fn new<F: Fn(&str) -> bool>(blueprint: &Blueprint, keep: F) -> Graph {
+        let components = &blueprint.data.components;
+        let exists = |index: i64| usize::try_from(index).is_ok_and(|i| i < components.len());
+        let edges = blueprint.data.pipes.iter().enumerate()
+            .filter(|(_, p)| keep(&p.type_field) && exists(p.a_component) && exists(p.b_component))
+            .map(|(pipe, p)| Edge {
+                pipe,
+                from: p.a_component as usize,
+                from_port: p.a_port.clone(),
+                to: p.b_component as usize,
+                to_port: p.b_port.clone(),
+                type_field: p.type_field.clone(),
+                length: p.segments.iter().map(|s| s.length).sum(),
+            })
+            .collect();
+        let nodes = components.iter().enumerate()
+            .map(|(index, c)| Node {
+                index,
+                name: c.name(),
+                module: c.module.clone(),
+                type_field: c.type_field.clone(),
+            })
+            .collect();
+        Graph { nodes, edges }
+    }
+
+    pub fn is_connected(&self, index: usize) -> bool {
+        self.edges.iter().any(|e| e.from == index || e.to == index)
+    }
+
+    /// Drop the nodes no edge touches.
+    pub fn without_isolated(mut self) -> Graph {
+        let connected: Vec<bool> = self.nodes.iter().map(|n| self.is_connected(n.index)).collect();
+        let mut keep = connected.into_iter();
+        self.nodes.retain(|_| keep.next().unwrap_or(false));
+        self
+    }
+}
+
+fn node_id(index: usize) -> String {
+    format!("c{}", index)
+}
+
+/// Name and index, since unnamed components share their module's name, then the module if the
+/// name doesn't already say it.
+fn node_label(node: &Node) -> Vec<String> {
+    let mut lines = vec![format!("{} #{}", node.name, node.index)];
+    if node.name != node.module {
+        lines.push(node.module.clone());
+    }
+    lines
+}
+
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+fn dot(title: &str, graph: &Graph) -> String {
+    let mut out = format!("graph {} {{\n    node [shape=box];\n", dot_string(title));
+    for node in &graph.nodes {
+        out.push_str(&format!("    {} [label={}];\n", node_id(node.index), dot_string(&node_label(node).join("\n"))));
+    }
+    for edge in &graph.edges {
+        out.push_str(&format!("    {} -- {} [label={}, taillabel={}, headlabel={}];\n",
+                              node_id(edge.from), node_id(edge.to),
+                              dot_string(&edge.type_field), dot_string(&edge.from_port), dot_string(&edge.to_port)));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn mermaid_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "#quot;"))
+}
+
+fn mermaid(graph: &Graph) -> String {
+    let mut out = String::from("graph LR\n");
+    for node in &graph.nodes {
+        out.push_str(&format!("    {}[{}]\n", node_id(node.index), mermaid_string(&node_label(node).join("<br/>"))));
+    }
+    for edge in &graph.edges {
+        let label = format!("{}: {} → {}", edge.type_field, edge.from_port, edge.to_port);
+        out.push_str(&format!("    {} ---|{}| {}\n", node_id(edge.from), mermaid_string(&label), node_id(edge.to)));
+    }
+    out
+}
+
+pub fn export_graph(args: &ArgMatches) {
+    let config = match GraphConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let blueprint = command::get_blueprint_object(config.file_name.clone()).ok()
+        .and_then(|source| serde_json::from_str::<Blueprint>(&source).ok());
+    let Some(blueprint) = blueprint else {
+        eprintln!("🚨 Blueprint could not be parsed! 🚨");
+        std::process::exit(1);
+    };
+
+    let graph = Graph::new(&blueprint, |pipe_type| {
+        config.pipe_types.is_empty() || config.pipe_types.contains(&pipe_type.to_lowercase())
+    });
+    let graph = if config.all { graph } else { graph.without_isolated() };
+    if graph.edges.is_empty() && !config.pipe_types.is_empty() {
+        eprintln!("No pipes of type {} found", config.pipe_types.join(", "));
+    }
+
+    let title = config.file_name.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    match config.format {
+        GraphFormat::Dot => print!("{}", dot(&title, &graph)),
+        GraphFormat::Mermaid => print!("{}", mermaid(&graph)),
+        GraphFormat::Json => println!("{}", serde_json::to_string_pretty(&graph).unwrap()),
+    }
+}
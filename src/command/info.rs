@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use clap::{ArgMatches, Error, FromArgMatches};
+use crate::archean::json::Blueprint;
+use crate::command;
+use crate::command::prelude;
+
+pub struct InfoConfig {
+    /// the blueprint to report on; `None` with `--env` only
+    pub(crate) blueprint: Option<PathBuf>,
+    pub(crate) env: bool
+}
+
+impl FromArgMatches for InfoConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let blueprint = match matches.contains_id("BLUEPRINT") {
+            true => Some(command::extract_filename("BLUEPRINT".to_string(), matches)?),
+            false => None,
+        };
+
+        Ok(InfoConfig {
+            blueprint,
+            env: *matches.get_one::<bool>("env").unwrap_or(&false)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = InfoConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+/// Where archbelt looks for Archean and its blueprints.
+fn show_environment(args: &ArgMatches) {
+    match prelude::get_archean_path() {
+        Ok(archean_path) => println!("Archean path: {:?} (exists: {})", archean_path, archean_path.exists()),
+        Err(_) => eprintln!("Could not get Archean path; is it installed?"),
+    }
+
+    let blueprints_path = command::get_blueprints_path(args);
+    match blueprints_path {
+        Ok(path) => {
+            println!("Blueprints path: {:?} (exists: {})", path, path.exists());
+        }
+        Err(_) => {
+            eprintln!("Could not get blueprints path; is Archean installed? did you provide an invalid path to --blueprint-path?");
+        }
+    }
+}
+
+fn percent(used: usize, capacity: i64) -> String {
+    match capacity {
+        capacity if capacity > 0 => format!("{:.1}%", used as f64 * 100.0 / capacity as f64),
+        _ => "-".to_string(),
+    }
+}
+
+fn show_report(name: &str, blueprint: &Blueprint) {
+    let data = &blueprint.data;
+    println!("{}", name);
+    println!("  Author:          {}", blueprint.author);
+    println!("  Saved:           {}", blueprint.datetime);
+    println!("  Format version:  {}", blueprint.version);
+    println!("  Mass:            {:.1} kg", blueprint.mass);
+    println!("  Box min:         {} {} {}", blueprint.box_min.x, blueprint.box_min.y, blueprint.box_min.z);
+    println!("  Box max:         {} {} {}", blueprint.box_max.x, blueprint.box_max.y, blueprint.box_max.z);
+    println!("  Box size:        {} × {} × {}", blueprint.box_size.x, blueprint.box_size.y, blueprint.box_size.z);
+    println!("  Symmetry axis:   {}", data.symmetry_axis);
+
+    println!();
+    println!("Contents");
+    for (label, count) in [
+        ("Blocks", data.blocks.len()),
+        ("Components", data.components.len()),
+        ("Pipes", data.pipes.len()),
+        ("Doors", data.doors.len()),
+        ("Frames", data.frames.len()),
+        ("Labels", data.labels.len()),
+    ] {
+        println!("  {:<16} {}", format!("{}:", label), count);
+    }
+
+    if !data.components.is_empty() {
+        let mut modules: BTreeMap<&str, usize> = BTreeMap::new();
+        for component in &data.components {
+            *modules.entry(component.module.as_str()).or_default() += 1;
+        }
+        let mut modules: Vec<(&str, usize)> = modules.into_iter().collect();
+        // most common first, then by name
+        modules.sort_by(|(a_module, a), (b_module, b)| b.cmp(a).then(a_module.cmp(b_module)));
+        let width = modules.iter().map(|(m, _)| m.chars().count()).max().unwrap_or(0);
+
+        println!();
+        println!("Components by module");
+        for (module, count) in modules {
+            println!("  {:<width$}  {}", module, count, width = width);
+        }
+    }
+
+    let computers: Vec<_> = data.components.iter().enumerate()
+        .filter_map(|(index, c)| c.data.hdd.as_ref().map(|hdd| (index, c, hdd)))
+        .collect();
+    if !computers.is_empty() {
+        println!();
+        println!("HDD usage");
+        for (index, component, hdd) in computers {
+            let used: usize = hdd.xc_files.iter().map(|f| f.plain_code.len()).sum();
+            let label = if hdd.label.is_empty() { String::new() } else { format!(" \"{}\"", hdd.label) };
+            println!("  #{} {}{}: {} / {} bytes ({}), {} file(s)",
+                     index, component.name(), label, used, hdd.capacity, percent(used, hdd.capacity), hdd.xc_files.len());
+        }
+    }
+
+    let files = blueprint.xc_files();
+    println!();
+    if files.is_empty() {
+        println!("No code files");
+    } else {
+        println!("Code files");
+        for file in files {
+            let content = file.file_content();
+            println!("  #{} {} › {} ({} lines, {} bytes)",
+                     file.component_index(), file.component(), file.file_name(), content.lines().count(), content.len());
+        }
+    }
+}
+
+pub fn show_info(args: &ArgMatches) {
+    let config = match InfoConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    if config.env {
+        show_environment(args);
+    }
+    let Some(file_name) = config.blueprint else {
+        return;
+    };
+    if config.env {
+        println!();
+    }
+
+    let blueprint = command::get_blueprint_object(file_name.clone()).ok()
+        .and_then(|source| serde_json::from_str::<Blueprint>(&source).ok());
+    match blueprint {
+        Some(blueprint) => show_report(&file_name.file_stem().unwrap_or_default().to_string_lossy(), &blueprint),
+        None => {
+            eprintln!("🚨 Blueprint could not be parsed! 🚨");
+            std::process::exit(1);
+        }
+    }
+}
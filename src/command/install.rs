@@ -0,0 +1,477 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use clap::{ArgMatches, Error, FromArgMatches};
+use serde_derive::{Deserialize, Serialize};
+use crate::archean::json::Blueprint;
+use crate::command;
+use crate::command::archive::{Archive, CHECKSUMS_FILE};
+use crate::command::package;
+use crate::command::registry::{self, Dependency, LockedPackage, Lockfile, Registry, LOCKFILE};
+use crate::command::sanitize;
+
+/// Record of installed packages, in the data folder.
+pub const INSTALL_RECORD: &str = "installed.json";
+
+/// The blueprints folder as an absolute path, so records match whatever the cwd was.
+fn blueprints_path(matches: &ArgMatches) -> Result<PathBuf, Error> {
+    let path = command::get_blueprints_path(matches)?;
+    Ok(path.canonicalize().unwrap_or(path))
+}
+
+/// Where library packages are installed: `--lib`, or `lib` in the data folder.
+fn lib_path(matches: &ArgMatches) -> Option<PathBuf> {
+    let path = matches.get_one::<String>("lib").map(PathBuf::from)
+        .or_else(|| command::data_dir().map(|dir| dir.join("lib")))?;
+    Some(path.canonicalize().unwrap_or(path))
+}
+
+/// What to do when the blueprints folder already has a blueprint with the package's name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict {
+    /// install as `Name (2).json`
+    Rename,
+    Overwrite,
+    Skip,
+}
+
+pub struct InstallConfig {
+    /// a package file or folder, or `name[@requirement]` to install from the registry
+    pub(crate) source: Option<String>,
+    pub(crate) blueprints_path: PathBuf,
+    pub(crate) lib_path: Option<PathBuf>,
+    pub(crate) registry: Option<String>,
+    pub(crate) on_conflict: Option<Conflict>,
+    pub(crate) list: bool
+}
+
+impl FromArgMatches for InstallConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let on_conflict = matches.get_one::<String>("on-conflict").map(|c| match c.as_str() {
+            "overwrite" => Conflict::Overwrite,
+            "skip" => Conflict::Skip,
+            _ => Conflict::Rename,
+        });
+
+        Ok(InstallConfig {
+            source: matches.get_one::<String>("SOURCE").cloned(),
+            blueprints_path: blueprints_path(matches)?,
+            lib_path: lib_path(matches),
+            registry: matches.get_one::<String>("registry").cloned(),
+            on_conflict,
+            list: *matches.get_one::<bool>("list").unwrap_or(&false)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = InstallConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+pub struct UninstallConfig {
+    pub(crate) name: String,
+    pub(crate) blueprints_path: PathBuf,
+    pub(crate) lib_path: Option<PathBuf>,
+    pub(crate) force: bool
+}
+
+impl FromArgMatches for UninstallConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        Ok(UninstallConfig {
+            name: matches.get_one::<String>("NAME").cloned().unwrap_or_default(),
+            blueprints_path: blueprints_path(matches)?,
+            lib_path: lib_path(matches),
+            force: *matches.get_one::<bool>("force").unwrap_or(&false)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = UninstallConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledPackages {
+    pub(crate) packages: Vec<InstalledPackage>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledPackage {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    /// where the blueprint, or a library's folder, was written
+    pub(crate) blueprint: PathBuf,
+    /// SHA-256 of what was installed, to notice local edits before removing it
+    pub(crate) sha256: String,
+    pub(crate) source: String,
+    /// seconds since the Unix epoch
+    pub(crate) installed_at: u64,
+}
+
+impl InstalledPackages {
+    fn path() -> Option<PathBuf> {
+        command::data_dir().map(|dir| dir.join(INSTALL_RECORD))
+    }
+
+    pub fn load() -> InstalledPackages {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = Self::path().ok_or_else(|| std::io::Error::other("no data folder; set XDG_DATA_HOME or HOME"))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self).unwrap())
+    }
+
+    /// Packages installed into the blueprints folder `dir`.
+    pub fn in_folder<'a>(&'a self, dir: &'a Path) -> impl Iterator<Item = &'a InstalledPackage> {
+        self.packages.iter().filter(move |p| p.blueprint.parent() == Some(dir))
+    }
+}
+
+/// Load a package from an `.archbelt` file or a folder it was unpacked to, and check it.
+pub(crate) fn load_package(source: &Path) -> Result<Archive, Vec<String>> {
+    let archive = if source.is_dir() {
+        Archive::from_dir(source)
+    } else {
+        Archive::read(source)
+    }.map_err(|e| vec![e.to_string()])?;
+
+    // a folder can be a package put together by hand; only check checksums if it has them
+    let mut problems = if source.is_dir() && archive.get(CHECKSUMS_FILE).is_none() {
+        archive.verify_layout()
+    } else {
+        archive.verify()
+    };
+    // library packages are installed to a folder named after the package
+    if let Some(name) = archive.metadata().and_then(|m| m.name).filter(|name| !package::is_valid_name(name)) {
+        problems.push(format!("package name {:?} may only use letters, digits, spaces, '-', '_' and '.', and not start with '.'", name));
+    }
+    if let Some((name, data)) = archive.blueprint() {
+        if Path::new(name).file_name().map(|n| n.to_string_lossy() == name) != Some(true) {
+            problems.push(format!("blueprint name {:?} is not a plain file name", name));
+        } else if serde_json::from_slice::<Blueprint>(data).is_err() {
+            problems.push(format!("{} is not a valid blueprint", name));
+        }
+    }
+    if problems.is_empty() {
+        Ok(archive)
+    } else {
+        Err(problems)
+    }
+}
+
+/// Hash of an installed blueprint, or of every file in an installed library's folder.
+fn installed_hash(path: &Path) -> Option<String> {
+    if path.is_dir() {
+        Archive::from_dir(path).ok().map(|library| tree_hash(&library))
+    } else {
+        std::fs::read(path).ok().map(|data| command::content_hash(&data))
+    }
+}
+
+/// Hash of an archive's files that doesn't depend on the order they are stored in.
+fn tree_hash(archive: &Archive) -> String {
+    let checksums = archive.checksums();
+    let mut lines: Vec<&str> = checksums.lines().collect();
+    lines.sort();
+    command::content_hash(lines.join("\n").as_bytes())
+}
+
+/// `Name (2).json`, `Name (3).json`, ... whichever is free first.
+fn free_name(dir: &Path, file_name: &str) -> PathBuf {
+    let path = Path::new(file_name);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = path.extension().unwrap_or_default().to_string_lossy().to_string();
+    (2..).map(|n| dir.join(format!("{} ({}).{}", stem, n, extension)))
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+fn list_installed(blueprints_path: &Path, lib_path: Option<&Path>) {
+    let installed = InstalledPackages::load();
+    let mut any = false;
+    let libraries = lib_path.into_iter().flat_map(|dir| installed.in_folder(dir));
+    for package in installed.in_folder(blueprints_path).chain(libraries) {
+        let state = match installed_hash(&package.blueprint) {
+            Some(hash) if hash == package.sha256 => "",
+            Some(_) => " (modified)",
+            None => " (missing)",
+        };
+        println!("{} {}  {}{}", package.name, package.version,
+                 package.blueprint.file_name().unwrap_or_default().to_string_lossy(), state);
+        any = true;
+    }
+    if !any {
+        println!("No packages installed in {:?}.", blueprints_path);
+    }
+}
+
+/// Pick versions for `roots` and everything they depend on, and fetch them from the registry.
+fn fetch_dependencies(config: &InstallConfig, roots: &[Dependency], lock: &mut Lockfile) -> Vec<(Archive, String)> {
+    if roots.is_empty() {
+        return vec![];
+    }
+    let resolved = Registry::open(config.registry.as_deref())
+        .and_then(|registry| {
+            let resolved = registry::resolve(&registry, roots, Some(lock))?;
+            let archives = resolved.iter()
+                .map(|r| registry.fetch(&r.release).map(|archive| (archive, format!("{}@{}", r.name, r.release.version))))
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok((resolved, archives))
+        });
+    let (resolved, archives) = match resolved {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            eprintln!("🚨 {}; nothing was installed 🚨", e);
+            std::process::exit(1);
+        }
+    };
+
+    for package in resolved {
+        lock.packages.retain(|p| p.name != package.name);
+        lock.packages.push(LockedPackage {
+            name: package.name,
+            version: package.release.version,
+            sha256: package.release.sha256,
+            dependencies: package.release.dependencies,
+        });
+    }
+    lock.packages.sort_by(|a, b| a.name.cmp(&b.name));
+    archives
+}
+
+/// What a package puts on disk.
+enum Payload<'a> {
+    Blueprint(&'a [u8]),
+    /// the library's files, relative to its folder
+    Library(Archive),
+}
+
+/// A package that was checked against what is already installed, ready to be written.
+struct Install<'a> {
+    name: String,
+    version: String,
+    destination: PathBuf,
+    hash: String,
+    source: &'a str,
+    payload: Payload<'a>,
+}
+
+/// Work out where one package goes and check it for conflicts, without writing anything; `None`
+/// if it is skipped.
+fn plan_install<'a>(config: &InstallConfig, installed: &InstalledPackages, archive: &'a Archive, source: &'a str) -> Result<Option<Install<'a>>, String> {
+    let metadata = archive.metadata().unwrap_or_default();
+    let (mut destination, file_name, payload) = match archive.blueprint() {
+        Some((file_name, data)) => (config.blueprints_path.join(file_name), file_name.to_string(), Payload::Blueprint(data)),
+        None => {
+            let (Some(name), Some(lib_path)) = (&metadata.name, &config.lib_path) else {
+                return Err("Library packages need a name and a --lib folder to go to".to_string());
+            };
+            let mut library = Archive::default();
+            archive.library_files().for_each(|(path, data)| library.add(path, data));
+            if !sanitize::is_contained(Path::new(name)) {
+                return Err(format!("Library name {:?} points outside {:?}", name, lib_path));
+            }
+            (lib_path.join(name), name.clone(), Payload::Library(library))
+        }
+    };
+    let name = metadata.name.clone()
+        .unwrap_or_else(|| Path::new(&file_name).file_stem().unwrap_or_default().to_string_lossy().to_string());
+    let hash = match &payload {
+        Payload::Blueprint(data) => command::content_hash(data),
+        Payload::Library(library) => tree_hash(library),
+    };
+
+    if let Some(existing) = installed_hash(&destination) {
+        // an unmodified earlier install of the same package is simply upgraded
+        let upgrade = installed.packages.iter().any(|p| p.blueprint == destination && p.name == name && p.sha256 == existing);
+        match config.on_conflict {
+            // the same package again; just (re)record it
+            _ if existing == hash || upgrade => {}
+            None => return Err(format!("{:?} already exists; pass --on-conflict rename, overwrite or skip", destination)),
+            Some(Conflict::Skip) => {
+                println!("Skipped {}; {:?} already exists", name, destination);
+                return Ok(None);
+            }
+            Some(Conflict::Rename) if matches!(payload, Payload::Library(_)) => {
+                return Err(format!("{:?} already exists, and libraries can't be renamed; pass --on-conflict overwrite or skip", destination));
+            }
+            Some(Conflict::Rename) => destination = free_name(&config.blueprints_path, &file_name),
+            Some(Conflict::Overwrite) => {}
+        }
+    }
+
+    Ok(Some(Install { name, version: metadata.version, destination, hash, source, payload }))
+}
+
+/// Write a planned package's blueprint, or its code if it is a library, and record it.
+fn install_archive(install: Install, installed: &mut InstalledPackages) -> Result<(), String> {
+    let destination = &install.destination;
+    match &install.payload {
+        Payload::Blueprint(data) => std::fs::write(destination, data).map_err(|e| format!("Error writing {:?}: {:?}", destination, e))?,
+        Payload::Library(library) => {
+            if destination.exists() {
+                std::fs::remove_dir_all(destination).map_err(|e| format!("Error replacing library {:?}: {:?}", destination, e))?;
+            }
+            library.extract(destination).map_err(|e| format!("Error writing {:?}: {}", destination, e))?;
+        }
+    }
+
+    installed.packages.retain(|p| p.blueprint != *destination);
+    installed.packages.push(InstalledPackage {
+        name: install.name.clone(),
+        version: install.version.clone(),
+        blueprint: destination.clone(),
+        sha256: install.hash,
+        source: install.source.to_string(),
+        installed_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+    });
+    println!("Installed {} {} as {:?}", install.name, install.version, destination);
+    Ok(())
+}
+
+pub fn install_package(args: &ArgMatches) {
+    let config = match InstallConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("🚨 Could not get blueprints path; is Archean installed? did you provide an invalid path to --blueprint-path? 🚨");
+            std::process::exit(1);
+        }
+    };
+    if config.list {
+        list_installed(&config.blueprints_path, config.lib_path.as_deref());
+        return;
+    }
+    let Some(source) = config.source.clone() else {
+        eprintln!("🚨 No package given! 🚨");
+        std::process::exit(1);
+    };
+
+    // a package on disk brings its own dependencies; anything else is looked up in the registry
+    let path = PathBuf::from(&source);
+    let (local, roots) = if path.exists() {
+        let archive = match load_package(&path) {
+            Ok(archive) => archive,
+            Err(problems) => {
+                problems.iter().for_each(|p| eprintln!("✘ {}", p));
+                eprintln!("🚨 {:?} is not a valid package; nothing was installed 🚨", path);
+                std::process::exit(1);
+            }
+        };
+        let dependencies = archive.metadata().unwrap_or_default().dependencies.iter()
+            .map(|d| Dependency::parse(d))
+            .collect::<Result<Vec<_>, String>>();
+        let source = path.canonicalize().unwrap_or(path).to_string_lossy().to_string();
+        (Some((archive, source)), dependencies)
+    } else {
+        (None, Dependency::parse(&source).map(|d| vec![d]))
+    };
+    let roots = match roots {
+        Ok(roots) => roots,
+        Err(e) => {
+            eprintln!("🚨 {}; nothing was installed 🚨", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut lock = Lockfile::load(".").unwrap_or_default();
+    let mut packages = fetch_dependencies(&config, &roots, &mut lock);
+    packages.extend(local);
+
+    // every package is checked before the first one is written, so a conflict leaves nothing behind
+    let mut installed = InstalledPackages::load();
+    let mut planned: Vec<Install> = vec![];
+    let mut problems = vec![];
+    for (archive, source) in &packages {
+        match plan_install(&config, &installed, archive, source) {
+            Ok(Some(install)) if planned.iter().any(|p| p.destination == install.destination) => {
+                problems.push(format!("more than one package would be installed as {:?}", install.destination));
+            }
+            Ok(Some(install)) => planned.push(install),
+            Ok(None) => {}
+            Err(e) => problems.push(e),
+        }
+    }
+    if !problems.is_empty() {
+        problems.iter().for_each(|p| eprintln!("✘ {}", p));
+        eprintln!("🚨 Nothing was installed 🚨");
+        std::process::exit(1);
+    }
+
+    let mut failed = None;
+    for install in planned {
+        if let Err(e) = install_archive(install, &mut installed) {
+            failed = Some(e);
+            break;
+        }
+    }
+    // record whatever was written, so it can be uninstalled even if a later package failed
+    if let Err(e) = installed.save() {
+        eprintln!("Warning: packages were installed but could not be recorded: {}", e);
+    }
+    if let Some(e) = failed {
+        eprintln!("🚨 {}; the packages installed before it are recorded and can be uninstalled 🚨", e);
+        std::process::exit(1);
+    }
+    if !roots.is_empty() {
+        if let Err(e) = lock.save(".") {
+            eprintln!("Warning: could not write {}: {}", LOCKFILE, e);
+        }
+    }
+}
+
+pub fn uninstall_package(args: &ArgMatches) {
+    let config = match UninstallConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("🚨 Could not get blueprints path; is Archean installed? did you provide an invalid path to --blueprint-path? 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let mut installed = InstalledPackages::load();
+    let libraries = config.lib_path.iter().flat_map(|dir| installed.in_folder(dir));
+    let matching: Vec<InstalledPackage> = installed.in_folder(&config.blueprints_path).chain(libraries)
+        .filter(|p| p.name == config.name || p.blueprint.file_stem().is_some_and(|s| s.to_string_lossy() == config.name))
+        .cloned()
+        .collect();
+    if matching.is_empty() {
+        eprintln!("🚨 {} is not installed in {:?}! 🚨", config.name, config.blueprints_path);
+        std::process::exit(1);
+    }
+
+    for package in &matching {
+        match installed_hash(&package.blueprint) {
+            Some(hash) if hash != package.sha256 && !config.force => {
+                eprintln!("🚨 {:?} was changed since it was installed; pass --force to remove it anyway 🚨", package.blueprint);
+                std::process::exit(1);
+            }
+            Some(_) => {
+                let removed = if package.blueprint.is_dir() {
+                    std::fs::remove_dir_all(&package.blueprint)
+                } else {
+                    std::fs::remove_file(&package.blueprint)
+                };
+                if let Err(e) = removed {
+                    eprintln!("Error removing {:?}: {:?}", package.blueprint, e);
+                    std::process::exit(1);
+                }
+            }
+            // already gone; only the record is left to clean up
+            None => {}
+        }
+        installed.packages.retain(|p| p.blueprint != package.blueprint);
+        println!("Uninstalled {} {} ({:?})", package.name, package.version, package.blueprint);
+    }
+    if let Err(e) = installed.save() {
+        eprintln!("Error saving {}: {}", INSTALL_RECORD, e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,183 @@
+use std::path::PathBuf;
+use clap::{ArgMatches, Error, FromArgMatches};
+use serde_derive::Serialize;
+use crate::archean::json::Blueprint;
+use crate::command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Author,
+    Date,
+    Mass,
+    Components,
+    Files,
+}
+
+pub struct ListConfig {
+    pub(crate) blueprints_path: PathBuf,
+    pub(crate) sort: SortKey,
+    pub(crate) reverse: bool,
+    /// part of the author's name, any case
+    pub(crate) author: Option<String>,
+    /// dates as `datetime` starts, e.g. `2024-07` or `2024-07-20`
+    pub(crate) since: Option<String>,
+    pub(crate) until: Option<String>,
+    pub(crate) has_code: bool,
+    /// module types of which a blueprint needs at least one, any case
+    pub(crate) modules: Vec<String>,
+    pub(crate) json: bool
+}
+
+/// A date prefix: `YYYY`, `YYYY-MM`, `YYYY-MM-DD`, optionally followed by a time.
+fn is_date(date: &str) -> bool {
+    date.len() >= 4 && date.chars().all(|c| c.is_ascii_digit() || matches!(c, '-' | ':' | ' ' | 'T'))
+}
+
+impl FromArgMatches for ListConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let sort = match matches.get_one::<String>("sort").map(String::as_str) {
+            Some("author") => SortKey::Author,
+            Some("date") => SortKey::Date,
+            Some("mass") => SortKey::Mass,
+            Some("components") => SortKey::Components,
+            Some("files") => SortKey::Files,
+            _ => SortKey::Name,
+        };
+        let date = |name: &str| -> Result<Option<String>, Error> {
+            match matches.get_one::<String>(name) {
+                Some(date) if !is_date(date) => Err(Error::raw(clap::error::ErrorKind::InvalidValue,
+                    format!("--{} {:?} is not a date like 2024-07-20\n", name, date))),
+                date => Ok(date.cloned()),
+            }
+        };
+
+        Ok(ListConfig {
+            blueprints_path: command::get_blueprints_path(matches)?,
+            sort,
+            reverse: *matches.get_one::<bool>("reverse").unwrap_or(&false),
+            author: matches.get_one::<String>("author").map(|a| a.to_lowercase()),
+            since: date("since")?,
+            until: date("until")?,
+            has_code: *matches.get_one::<bool>("has-code").unwrap_or(&false),
+            modules: matches.get_many::<String>("module")
+                .map(|modules| modules.map(|m| m.to_lowercase()).collect())
+                .unwrap_or_default(),
+            json: *matches.get_one::<bool>("json").unwrap_or(&false)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = ListConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlueprintSummary {
+    name: String,
+    file: PathBuf,
+    author: String,
+    datetime: String,
+    mass: f64,
+    /// x, y, z
+    size: [f64; 3],
+    components: usize,
+    code_files: usize,
+    #[serde(skip)]
+    modules: Vec<String>,
+}
+
+impl BlueprintSummary {
+    fn new(file: PathBuf, blueprint: &Blueprint) -> Self {
+        BlueprintSummary {
+            name: file.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            author: blueprint.author.clone(),
+            datetime: blueprint.datetime.clone(),
+            mass: blueprint.mass,
+            size: [blueprint.box_size.x, blueprint.box_size.y, blueprint.box_size.z],
+            components: blueprint.data.components.len(),
+            code_files: blueprint.xc_files().len(),
+            modules: blueprint.data.components.iter().map(|c| c.module.to_lowercase()).collect(),
+            file,
+        }
+    }
+
+    fn matches(&self, config: &ListConfig) -> bool {
+        let until = |until: &String| self.datetime.get(..until.len()).unwrap_or(&self.datetime) <= until.as_str();
+        config.author.as_ref().is_none_or(|author| self.author.to_lowercase().contains(author))
+            && config.since.as_ref().is_none_or(|since| self.datetime.as_str() >= since.as_str())
+            && config.until.as_ref().is_none_or(until)
+            && (!config.has_code || self.code_files > 0)
+            && (config.modules.is_empty() || config.modules.iter().any(|m| self.modules.contains(m)))
+    }
+}
+
+fn sort(summaries: &mut [BlueprintSummary], key: SortKey) {
+    summaries.sort_by(|a, b| {
+        let order = match key {
+            SortKey::Name => std::cmp::Ordering::Equal,
+            SortKey::Author => a.author.to_lowercase().cmp(&b.author.to_lowercase()),
+            SortKey::Date => a.datetime.cmp(&b.datetime),
+            SortKey::Mass => a.mass.total_cmp(&b.mass),
+            SortKey::Components => a.components.cmp(&b.components),
+            SortKey::Files => a.code_files.cmp(&b.code_files),
+        };
+        order.then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+}
+
+fn print_table(summaries: &[BlueprintSummary]) {
+    let rows: Vec<Vec<String>> = summaries.iter().map(|s| vec![
+        s.name.clone(),
+        s.author.clone(),
+        s.datetime.clone(),
+        format!("{:.1}", s.mass),
+        format!("{}×{}×{}", s.size[0], s.size[1], s.size[2]),
+        s.components.to_string(),
+        s.code_files.to_string(),
+    ]).collect();
+    print!("{}", command::text_table(&["NAME", "AUTHOR", "DATE", "MASS", "SIZE", "COMPONENTS", "FILES"], &rows, 3));
+}
+
+pub fn list_blueprints(args: &ArgMatches) {
+    let config = match ListConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(e) if e.kind() == clap::error::ErrorKind::InvalidValue => {
+            eprint!("{}", e);
+            std::process::exit(1);
+        }
+        Err(_) => {
+            eprintln!("🚨 Could not get blueprints path; is Archean installed? did you provide an invalid path to --blueprint-path? 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&config.blueprints_path)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    files.retain(|p| p.extension().is_some_and(|e| e == "json"));
+
+    let mut summaries: Vec<BlueprintSummary> = vec![];
+    for file in files {
+        let blueprint = command::get_blueprint_object(file.clone()).ok()
+            .and_then(|source| serde_json::from_str::<Blueprint>(&source).ok());
+        match blueprint {
+            Some(blueprint) => summaries.push(BlueprintSummary::new(file, &blueprint)),
+            None => eprintln!("Skipping {:?}: not a blueprint", file),
+        }
+    }
+    summaries.retain(|s| s.matches(&config));
+    sort(&mut summaries, config.sort);
+    if config.reverse {
+        summaries.reverse();
+    }
+
+    if config.json {
+        println!("{}", serde_json::to_string_pretty(&summaries).unwrap());
+    } else if summaries.is_empty() {
+        println!("No matching blueprints in {:?}.", config.blueprints_path);
+    } else {
+        print_table(&summaries);
+    }
+}
@@ -0,0 +1,15 @@
+use std::path::PathBuf;
+use clap::ArgMatches;
+use crate::lsp::Server;
+use crate::xenon::bundle::Library;
+
+pub fn run_language_server(args: &ArgMatches) {
+    let lib_paths = args.get_many::<String>("lib")
+        .map(|paths| paths.map(PathBuf::from).collect())
+        .unwrap_or_default();
+
+    if let Err(e) = Server::new(Library::new(lib_paths)).run() {
+        eprintln!("🚨 Language server stopped: {} 🚨", e);
+        std::process::exit(1);
+    }
+}
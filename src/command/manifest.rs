@@ -0,0 +1,68 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use serde_derive::{Deserialize, Serialize};
+
+/// Name of the manifest written next to yanked files.
+pub const YANK_MANIFEST: &str = "archbelt.yank.json";
+
+/// Folder that code shared by several components is yanked into.
+pub const SHARED_FOLDER: &str = "_shared";
+
+/// Records where each yanked file came from, so it can be written back to the blueprint.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct YankManifest {
+    pub(crate) blueprint: String,
+    pub(crate) files: Vec<ManifestEntry>,
+}
+
+/// A file on disk and every HDD file (or inline program) it was yanked from (more than one when deduplicated).
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub(crate) path: String,
+    pub(crate) targets: Vec<FileTarget>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileTarget {
+    pub(crate) component: usize,
+    pub(crate) name: String,
+    /// the component's inline `program` rather than an HDD file
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) program: bool,
+}
+
+impl YankManifest {
+    pub fn load<P: AsRef<Path>>(dir: P) -> Option<YankManifest> {
+        let manifest = std::fs::read_to_string(dir.as_ref().join(YANK_MANIFEST)).ok()?;
+        serde_json::from_str(&manifest).ok()
+    }
+
+    /// The manifest in `folder` or the nearest folder above it, with the folder it is in.
+    pub fn find(folder: &Path) -> Option<(PathBuf, YankManifest)> {
+        folder.ancestors().find_map(|dir| YankManifest::load(dir).map(|m| (dir.to_path_buf(), m)))
+    }
+
+    /// Components that any of `files` was yanked from; `root` is the folder of the manifest.
+    pub fn components_of<'a>(&self, root: &Path, files: impl IntoIterator<Item = &'a Path>) -> BTreeSet<usize> {
+        let files: Vec<&Path> = files.into_iter().collect();
+        self.files.iter()
+            .filter(|entry| files.contains(&root.join(&entry.path).as_path()))
+            .flat_map(|entry| entry.targets.iter().map(|t| t.component))
+            .collect()
+    }
+
+    /// Where the file called `name` on the HDD of one of `components` was yanked to.
+    pub fn hdd_file(&self, root: &Path, components: &BTreeSet<usize>, name: &str) -> Option<PathBuf> {
+        self.files.iter()
+            .find(|entry| entry.targets.iter().any(|t| !t.program && t.name == name && components.contains(&t.component)))
+            .map(|entry| root.join(&entry.path))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> std::io::Result<()> {
+        std::fs::write(dir.as_ref().join(YANK_MANIFEST), self.to_json())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
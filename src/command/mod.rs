@@ -2,6 +2,30 @@ mod yank;
 mod descriptors;
 mod watch;
 mod package;
+mod bundle;
+pub(crate) mod manifest;
+mod push;
+mod lsp;
+mod test;
+mod search;
+mod refactor;
+mod doc;
+mod archive;
+mod install;
+mod registry;
+mod version;
+pub(crate) mod sanitize;
+mod list;
+mod info;
+mod bom;
+mod graph;
+mod validate;
+mod export;
+mod vox;
+mod preview;
+mod transform;
+mod combine;
+mod extract;
 
 use clap::{ArgMatches, Command, Error};
 use clap_complete::{generate, Generator, Shell};
@@ -10,6 +34,7 @@ use std::path::{Path, PathBuf};
 use steamlocate::SteamDir;
 use std::fs;
 use std::ops::Deref;
+use sha2::{Digest, Sha256};
 use crate::statics::{ARCHEAN_STEAM_ID, COMMAND, DESCRIPTION, VERSION};
 
 pub mod prelude {
@@ -20,9 +45,31 @@ pub mod prelude {
             .version(VERSION)
             .about(DESCRIPTION)
             .subcommand(descriptors::yank_command())
+            .subcommand(descriptors::push_command())
             .subcommand(descriptors::watch_command())
             .subcommand(descriptors::package_command())
+            .subcommand(descriptors::unpack_command())
+            .subcommand(descriptors::verify_command())
+            .subcommand(descriptors::install_command())
+            .subcommand(descriptors::uninstall_command())
+            .subcommand(descriptors::publish_command())
+            .subcommand(descriptors::version_command())
+            .subcommand(descriptors::bundle_command())
+            .subcommand(descriptors::doc_command())
             .subcommand(descriptors::info_command())
+            .subcommand(descriptors::list_command())
+            .subcommand(descriptors::bom_command())
+            .subcommand(descriptors::graph_command())
+            .subcommand(descriptors::validate_command())
+            .subcommand(descriptors::export_command())
+            .subcommand(descriptors::preview_command())
+            .subcommand(descriptors::transform_command())
+            .subcommand(descriptors::combine_command())
+            .subcommand(descriptors::extract_command())
+            .subcommand(descriptors::search_command())
+            .subcommand(descriptors::refactor_command())
+            .subcommand(descriptors::lsp_command())
+            .subcommand(descriptors::test_command())
             .subcommand(descriptors::complete_command())
     }
 
@@ -31,15 +78,81 @@ pub mod prelude {
             "complete" => {
                 generate_shell_completion(args);
             }
+            "doc" => {
+                doc::generate_docs(args);
+            }
+            "export" => {
+                export::export_blueprint(args);
+            }
+            "graph" => {
+                graph::export_graph(args);
+            }
             "info" => {
-                show_info(args);
+                info::show_info(args);
+            }
+            "list" => {
+                list::list_blueprints(args);
+            }
+            "lsp" => {
+                lsp::run_language_server(args);
             }
             "package" => {
                 package::package_from_blueprint(args);
             }
+            "unpack" => {
+                archive::unpack_archive(args);
+            }
+            "validate" => {
+                validate::validate_blueprint(args);
+            }
+            "verify" => {
+                archive::verify_archive(args);
+            }
+            "install" => {
+                install::install_package(args);
+            }
+            "uninstall" => {
+                install::uninstall_package(args);
+            }
+            "preview" => {
+                preview::preview_blueprint(args);
+            }
+            "transform" => {
+                transform::transform_blueprint(args);
+            }
+            "combine" => {
+                combine::combine_blueprints(args);
+            }
+            "extract" => {
+                extract::extract_blueprint(args);
+            }
+            "publish" => {
+                registry::publish_package(args);
+            }
+            "version" => {
+                version::package_version(args);
+            }
+            "bom" => {
+                bom::print_bom(args);
+            }
+            "bundle" => {
+                bundle::bundle_libraries(args);
+            }
+            "refactor" => {
+                refactor::refactor_blueprints(args);
+            }
+            "search" => {
+                search::search_blueprints(args);
+            }
+            "test" => {
+                test::run_tests(args);
+            }
             "yank" => {
                 yank::yank_xenon_code(args);
             }
+            "push" => {
+                push::push_xenon_code(args);
+            }
             "watch" => {
                 watch::watch_blueprints(args);
             }
@@ -103,21 +216,6 @@ fn generate_shell_completion(args: &ArgMatches) {
     }
 }
 
-fn show_info(args: &ArgMatches) {
-    let archean_path = prelude::get_archean_path().expect("Could not get Archean path; is it installed?");
-    println!("Archean path: {:?} (exists: {})", archean_path, archean_path.exists());
-
-    let blueprints_path = get_blueprints_path(args);
-    match blueprints_path {
-        Ok(path) => {
-            println!("Blueprints path: {:?} (exists: {})", path, path.exists());
-        }
-        Err(_) => {
-            eprintln!("Could not get blueprints path; is Archean installed? did you provide an invalid path to --blueprint-path?");
-        }
-    }
-}
-
 // MARK: - Helper functions
 fn get_blueprint_object(path: PathBuf) -> Result<String, CommandError> {
     let bp_string = fs::read_to_string(path);
@@ -127,6 +225,67 @@ fn get_blueprint_object(path: PathBuf) -> Result<String, CommandError> {
     }
 }
 
+/// Every file below `dir`, skipping hidden files and folders.
+fn walk_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let Ok(entries) = fs::read_dir(dir) else {
+        return files;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            files.extend(walk_files(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Hex SHA-256 of `data`, used to identify file contents.
+fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Lay out rows as aligned columns under `header`; columns from `numeric` on are right-aligned.
+fn text_table(header: &[&str], rows: &[Vec<String>], numeric: usize) -> String {
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| rows.iter().map(|r| r[i].chars().count()).chain([header[i].chars().count()]).max().unwrap_or(0))
+        .collect();
+    let line = |cells: &[&str]| {
+        let padded: Vec<String> = cells.iter().enumerate().map(|(i, cell)| match i >= numeric {
+            true => format!("{:>width$}", cell, width = widths[i]),
+            false => format!("{:<width$}", cell, width = widths[i]),
+        }).collect();
+        format!("{}\n", padded.join("  ").trim_end())
+    };
+
+    let mut table = line(header);
+    for row in rows {
+        table.push_str(&line(&row.iter().map(String::as_str).collect::<Vec<_>>()));
+    }
+    table
+}
+
+/// Folder for data archbelt can rebuild at any time, e.g. the search index.
+fn cache_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join(COMMAND))
+}
+
+/// Folder for state archbelt keeps between runs, e.g. the record of installed packages.
+fn data_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))?;
+    Some(base.join(COMMAND))
+}
+
 fn generate_completions<G: Generator>(gen: G, cmd: &mut Command) {
     generate(gen, cmd, COMMAND, &mut std::io::stdout());
 }
@@ -202,5 +361,5 @@ fn extract_filename(for_id: String, matches: &ArgMatches) -> Result<PathBuf, Com
             return Err(CommandError);
         }
     };
-    Ok(file_name?)
+    file_name
 }
\ No newline at end of file
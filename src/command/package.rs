@@ -1,23 +1,76 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use clap::{ArgMatches, Error, FromArgMatches};
+use clap::error::ErrorKind;
+use semver::Version;
 use serde_derive::{Deserialize, Serialize};
 use crate::archean::json::Blueprint;
 use crate::command;
+use crate::command::archive::{Archive, ARCHIVE_EXTENSION, BLUEPRINT_FOLDER, METADATA_FILE, SOURCE_FOLDER};
+use crate::command::manifest::{ManifestEntry, YANK_MANIFEST, YankManifest};
 use crate::command::prelude::{WatchState, yank_from_config, YankConfig};
+use crate::command::registry::Dependency;
+use crate::command::yank::{output_options, plan_files, Layout};
 
 #[derive(Clone)]
 pub struct PackageConfig {
     pub(crate) file_name: PathBuf,
-    pub(crate) watch: WatchState
+    pub(crate) watch: WatchState,
+    pub(crate) archive: bool,
+    /// the project folder, or with `archive` the `.archbelt` file to write
+    pub(crate) out: Option<PathBuf>,
+    pub(crate) layout: Layout,
+    pub(crate) readme: Option<PathBuf>
 }
 
+impl PackageConfig {
+    fn out_is_archive(&self) -> bool {
+        self.archive && self.out.as_ref().is_some_and(|out| out.extension().is_some_and(|e| e == ARCHIVE_EXTENSION))
+    }
+
+    /// Where the project manifest is read from and loose files are written to.
+    fn project_dir(&self) -> PathBuf {
+        match &self.out {
+            Some(out) if !self.out_is_archive() => out.clone(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    fn archive_path(&self) -> PathBuf {
+        match &self.out {
+            Some(out) if self.out_is_archive() => out.clone(),
+            _ => {
+                let stem = self.file_name.file_stem().unwrap().to_string_lossy().to_string();
+                self.project_dir().join(format!("{}.{}", stem, ARCHIVE_EXTENSION))
+            }
+        }
+    }
+}
+
+/// Version of a package that doesn't set one in its project manifest yet.
+pub const INITIAL_VERSION: &str = "0.1.0";
+
+/// `archbelt.json`: what a package is, as written next to its files and into archives.
+///
+/// The package fields come from the project manifest, the `archbelt.json` in the folder a
+/// blueprint is packaged in; `format_version`, `mass` and `author` always come from the blueprint.
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BlueprintMetadata {
     pub(crate) name: Option<String>,
+    /// the package's semver version
     pub(crate) version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+    /// the blueprint format version Archean wrote
+    pub(crate) format_version: i64,
     pub(crate) mass: f64,
+    /// who built the blueprint in game
     pub(crate) author: String,
+    pub(crate) authors: Vec<String>,
+    /// an SPDX license expression, e.g. `MIT OR Apache-2.0`
     pub(crate) license: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) repository: Option<String>,
     pub(crate) keywords: Vec<String>,
     pub(crate) dependencies: Vec<String>,
 }
@@ -26,16 +79,149 @@ impl BlueprintMetadata {
     pub fn set_name(&mut self, name: String) {
         self.name = Some(name);
     }
+
+    /// Read the project manifest in `dir`, if there is one.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Option<BlueprintMetadata>, String> {
+        let path = dir.as_ref().join(METADATA_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(manifest) => serde_json::from_str(&manifest).map(Some).map_err(|e| format!("{:?}: {}", path, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("{:?}: {}", path, e)),
+        }
+    }
+
+    /// Whether `version` holds a blueprint format version (e.g. `"3"`), which releases before
+    /// package versions wrote there, rather than a package version. That is a plain number that
+    /// matches the format version, or sits in a manifest without one.
+    pub fn has_format_version(&self, blueprint_format: Option<i64>) -> bool {
+        self.version.parse::<i64>().is_ok_and(|v| self.format_version == 0 || v == self.format_version || Some(v) == blueprint_format)
+    }
+
+    /// Take the package fields from a project manifest, keeping what came from the blueprint.
+    pub fn merge(&mut self, manifest: BlueprintMetadata) {
+        self.name = manifest.name.or(self.name.take());
+        if !manifest.version.is_empty() {
+            self.version = manifest.version;
+        }
+        self.description = manifest.description;
+        if !manifest.authors.is_empty() {
+            self.authors = manifest.authors;
+        }
+        self.license = manifest.license;
+        self.repository = manifest.repository;
+        self.keywords = manifest.keywords;
+        self.dependencies = manifest.dependencies;
+    }
+
+    /// Everything that would keep the package from being installed or published; empty if valid.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+        match &self.name {
+            Some(name) if !is_valid_name(name) => problems.push(format!("name {:?} may only use letters, digits, spaces, '-', '_' and '.', and not start with '.'", name)),
+            Some(_) => {}
+            None => problems.push("name is missing".to_string()),
+        }
+        if let Err(e) = Version::parse(&self.version) {
+            problems.push(format!("version {:?} is not a semver version like 1.0.0: {}", self.version, e));
+        }
+        if self.description.as_ref().is_some_and(|d| d.trim().is_empty()) {
+            problems.push("description is empty".to_string());
+        }
+        if self.authors.iter().any(|a| a.trim().is_empty()) {
+            problems.push("authors has an empty entry".to_string());
+        }
+        if let Some(license) = &self.license {
+            if let Err(e) = check_license(license) {
+                problems.push(format!("license {:?} is not an SPDX expression: {}", license, e));
+            }
+        }
+        if let Some(repository) = &self.repository {
+            if !repository.contains("://") && !repository.starts_with("git@") {
+                problems.push(format!("repository {:?} is not a URL", repository));
+            }
+        }
+        for (i, keyword) in self.keywords.iter().enumerate() {
+            if keyword.is_empty() || keyword.len() > MAX_KEYWORD_LENGTH || keyword.contains(char::is_whitespace) {
+                problems.push(format!("keyword {:?} must be 1 to {} characters without spaces", keyword, MAX_KEYWORD_LENGTH));
+            } else if self.keywords[..i].iter().any(|k| k.eq_ignore_ascii_case(keyword)) {
+                problems.push(format!("keyword {:?} is listed twice", keyword));
+            }
+        }
+        for dependency in &self.dependencies {
+            match Dependency::parse(dependency) {
+                Ok(dependency) if Some(&dependency.name) == self.name.as_ref() => problems.push(format!("the package depends on itself ({:?})", dependency.name)),
+                Ok(_) => {}
+                Err(e) => problems.push(e),
+            }
+        }
+        problems
+    }
+}
+
+const MAX_KEYWORD_LENGTH: usize = 32;
+
+/// Package names end up in file names and `name@version` dependencies.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    !name.trim().is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'))
+}
+
+/// Check the syntax of an SPDX license expression: identifiers joined by `AND`/`OR`, an optional
+/// `WITH <exception>`, and parentheses.
+fn check_license(license: &str) -> Result<(), String> {
+    let spaced = license.replace('(', " ( ").replace(')', " ) ");
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+    let is_id = |t: &str| !matches!(t, "AND" | "OR" | "WITH" | "(" | ")")
+        && t.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '+' | ':'));
+
+    // alternate between expecting a license (or an opening parenthesis) and an operator
+    let mut depth = 0;
+    let mut expect_license = true;
+    // `WITH` only follows a license and is followed by an exception, never a parenthesis
+    let mut can_take_with = false;
+    let mut in_exception = false;
+    for token in &tokens {
+        match (*token, expect_license) {
+            ("(", true) if !in_exception => depth += 1,
+            (")", false) if depth > 0 => {
+                depth -= 1;
+                can_take_with = false;
+            }
+            ("AND" | "OR", false) => expect_license = true,
+            ("WITH", false) if can_take_with => {
+                expect_license = true;
+                in_exception = true;
+            }
+            (t, true) if is_id(t) => {
+                expect_license = false;
+                can_take_with = !in_exception;
+                in_exception = false;
+            }
+            (t, _) => return Err(format!("unexpected {:?}", t)),
+        }
+    }
+    if tokens.is_empty() || expect_license {
+        return Err("incomplete expression".to_string());
+    }
+    if depth > 0 {
+        return Err("unclosed parenthesis".to_string());
+    }
+    Ok(())
 }
 
 impl From<Blueprint> for BlueprintMetadata {
     fn from(value: Blueprint) -> Self {
         BlueprintMetadata {
             name: None,
-            version: value.version.to_string(),
+            version: INITIAL_VERSION.to_string(),
+            description: None,
+            format_version: value.version,
             mass: value.mass,
+            authors: vec![value.author.clone()],
             author: value.author,
-            license: None, // TODO: implement license output in .json file
+            license: None,
+            repository: None,
             keywords: vec![],
             dependencies: vec![]
         }
@@ -45,10 +231,12 @@ impl From<Blueprint> for BlueprintMetadata {
 impl From<PackageConfig> for YankConfig {
     fn from(config: PackageConfig) -> Self {
         YankConfig {
+            out: config.project_dir(),
             file_name: config.file_name,
             folder: true,
             watch: config.watch, // TODO: handle watch state ... maybe 0.5.0 ish?
-            disable_collate: false
+            layout: config.layout,
+            dedup: true
         }
     }
 }
@@ -56,7 +244,8 @@ impl From<PackageConfig> for YankConfig {
 impl FromArgMatches for PackageConfig {
     fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
         let file_name = command::extract_filename("BLUEPRINT".to_string(), matches)?;
-        let watch_switch = matches.get_one::<bool>("watch").unwrap_or(&false);
+        // `package` has no --watch flag of its own, but the config is shared with `watch`
+        let watch_switch = matches.try_get_one::<bool>("watch").ok().flatten().unwrap_or(&false);
 
         let watch_state = if *watch_switch {
             WatchState::Requested
@@ -66,22 +255,26 @@ impl FromArgMatches for PackageConfig {
 
         Ok(PackageConfig {
             file_name,
-            watch: watch_state
+            watch: watch_state,
+            archive: *matches.get_one::<bool>("archive").unwrap_or(&false),
+            out: matches.get_one::<String>("out").map(PathBuf::from),
+            layout: output_options(matches)?.1,
+            readme: matches.get_one::<String>("readme").map(PathBuf::from)
         })
     }
 
     fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
         let file_name = command::extract_filename("BLUEPRINT".to_string(), matches)?;
-        let watch_switch = matches.get_one::<bool>("watch").unwrap_or(&false);
+        let watch_switch = matches.try_get_one::<bool>("watch").ok().flatten().unwrap_or(&false);
 
         self.file_name = file_name;
-        match self.watch {
-            _ => {
-                if !watch_switch {
-                    self.watch = WatchState::Disabled;
-                }
-            }
+        if !watch_switch {
+            self.watch = WatchState::Disabled;
         }
+        self.archive = *matches.get_one::<bool>("archive").unwrap_or(&false);
+        self.out = matches.get_one::<String>("out").map(PathBuf::from);
+        self.layout = output_options(matches)?.1;
+        self.readme = matches.get_one::<String>("readme").map(PathBuf::from);
         Ok(())
     }
 }
@@ -97,7 +290,28 @@ fn get_blueprint_metadata(config: PackageConfig) -> BlueprintMetadata {
                 Ok(blueprint) => {
                     let mut metadata = BlueprintMetadata::from(blueprint);
                     metadata.set_name(blueprint_name);
-                    return metadata;
+                    match BlueprintMetadata::load(config.project_dir()) {
+                        Ok(Some(mut manifest)) => {
+                            if manifest.has_format_version(Some(metadata.format_version)) {
+                                println!("Note: version {:?} in {} is the blueprint format version older releases wrote; packaging as {}",
+                                         manifest.version, METADATA_FILE, metadata.version);
+                                manifest.version.clear();
+                            }
+                            metadata.merge(manifest)
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            eprintln!("🚨 {} 🚨", e);
+                            std::process::exit(1);
+                        }
+                    }
+                    let problems = metadata.validate();
+                    if !problems.is_empty() {
+                        problems.iter().for_each(|p| eprintln!("✘ {}", p));
+                        eprintln!("🚨 Fix the package metadata in {} before packaging 🚨", METADATA_FILE);
+                        std::process::exit(1);
+                    }
+                    metadata
                 }
                 _ => {
                     eprintln!("🚨 Blueprint could not be parsed! 🚨");
@@ -112,32 +326,86 @@ fn get_blueprint_metadata(config: PackageConfig) -> BlueprintMetadata {
     }
 }
 
+/// Build a single `.archbelt` file instead of writing the package loosely into the cwd.
+fn package_archive(config: PackageConfig) {
+    let metadata = get_blueprint_metadata(config.clone());
+    let source = command::get_blueprint_object(config.file_name.clone()).unwrap();
+    let blueprint: Blueprint = serde_json::from_str(&source).unwrap();
+    let blueprint_file = config.file_name.file_name().unwrap().to_string_lossy().to_string();
+
+    let mut archive = Archive::default();
+    archive.add(METADATA_FILE, serde_json::to_string_pretty(&metadata).unwrap());
+    archive.add(format!("{}/{}", BLUEPRINT_FOLDER, blueprint_file), source.clone());
+
+    // the same layout `yank -f` produces, so the sources can be pushed back after unpacking
+    let stem = config.file_name.file_stem().unwrap().to_string_lossy().to_string();
+    let planned = match plan_files(&blueprint.xc_files(), &stem, &config.layout, true) {
+        Ok(planned) => planned,
+        Err(e) => {
+            eprintln!("🚨 Refusing to package {:?}: {} 🚨", config.file_name, e);
+            std::process::exit(1);
+        }
+    };
+    for file in &planned {
+        archive.add(format!("{}/{}", SOURCE_FOLDER, file.path), file.content.clone());
+    }
+    let manifest = YankManifest {
+        blueprint: blueprint_file,
+        files: planned.into_iter().map(|f| ManifestEntry { path: f.path, targets: f.targets }).collect(),
+    };
+    archive.add(format!("{}/{}", SOURCE_FOLDER, YANK_MANIFEST), manifest.to_json());
+
+    if let Some(readme) = &config.readme {
+        match std::fs::read(readme) {
+            Ok(readme) => archive.add("README.md", readme),
+            Err(e) => {
+                eprintln!("Error reading {:?}: {:?}", readme, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let out = config.archive_path();
+    if let Some(parent) = out.parent() {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            eprintln!("Error creating {:?}: {:?}", parent, e);
+            std::process::exit(1);
+        }
+    }
+    if let Err(e) = archive.write(&out) {
+        eprintln!("🚨 Error writing archive: {} 🚨", e);
+        std::process::exit(1);
+    }
+    println!("Wrote {:?} ({} entries)", out, archive.entries().len() + 1);
+}
+
 pub fn package_from_blueprint(args: &ArgMatches) {
     let config = PackageConfig::from_arg_matches(args);
     match config {
+        Ok(config) if config.archive => package_archive(config),
         Ok(config) => {
+            // fetch metadata from file and the project manifest
+            let metadata = get_blueprint_metadata(config.clone());
             // copy blueprint file to destination folder
-            let copied = std::fs::copy(config.file_name.clone(), config.file_name.file_name().unwrap());
-            match copied {
-                Err(e) => {
-                    eprintln!("Error copying blueprint file: {:?}", e);
-                    std::process::exit(1);
-                }
-                _ => {}
+            let project_dir = config.project_dir();
+            let copied = std::fs::create_dir_all(&project_dir)
+                .and_then(|_| std::fs::copy(config.file_name.clone(), project_dir.join(config.file_name.file_name().unwrap())));
+            if let Err(e) = copied {
+                eprintln!("Error copying blueprint file: {:?}", e);
+                std::process::exit(1);
             }
-            // fetch metadata from file
-            let metadata = get_blueprint_metadata(config.clone());
             let metadata_json = serde_json::to_string_pretty(&metadata).unwrap();
-            let metadata_saved = std::fs::write("archbelt.json", metadata_json);
-            match metadata_saved {
-                Err(e) => {
-                    eprintln!("Error saving metadata: {:?}", e);
-                    std::process::exit(1);
-                }
-                _ => {}
+            let metadata_saved = std::fs::write(project_dir.join(METADATA_FILE), metadata_json);
+            if let Err(e) = metadata_saved {
+                eprintln!("Error saving metadata: {:?}", e);
+                std::process::exit(1);
             }
             yank_from_config(config.into());
         }
+        Err(e) if e.kind() == ErrorKind::InvalidValue => {
+            eprint!("{}", e);
+            std::process::exit(1);
+        }
         Err(e) => {
             eprintln!("Error: {:?}", e);
         }
@@ -0,0 +1,262 @@
+use std::path::PathBuf;
+use clap::{ArgMatches, Error, FromArgMatches};
+use crate::archean::geometry::{self, GridBox, Rgba, GRID};
+use crate::archean::json::Blueprint;
+use crate::command;
+
+/// Size of a grid cell in SVG previews, in pixels.
+const SVG_CELL: usize = 8;
+/// Brightness of the farthest cells; the nearest are drawn at full brightness.
+const FAR_SHADE: f32 = 0.55;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    /// looking down
+    Top,
+    /// looking along -z
+    Front,
+    /// looking along -x
+    Side,
+    /// the top view of the cells at one height
+    Slice(i64),
+}
+
+impl View {
+    fn name(&self) -> String {
+        match self {
+            View::Top => "top".to_string(),
+            View::Front => "front".to_string(),
+            View::Side => "side".to_string(),
+            View::Slice(level) => format!("slice at {}m", *level as f64 * GRID),
+        }
+    }
+
+    /// Column, row (upwards) and nearness of a cell in this view.
+    fn map(&self, [x, y, z]: [i64; 3]) -> (i64, i64, i64) {
+        match self {
+            View::Top | View::Slice(_) => (x, -z, y),
+            View::Front => (x, y, z),
+            View::Side => (-z, y, x),
+        }
+    }
+}
+
+pub struct PreviewConfig {
+    pub(crate) file_name: PathBuf,
+    pub(crate) views: Vec<View>,
+    /// folder to write SVG files to, instead of printing to the terminal
+    pub(crate) svg: Option<PathBuf>,
+    /// most terminal columns to use
+    pub(crate) width: usize,
+    pub(crate) components: bool
+}
+
+impl FromArgMatches for PreviewConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let mut views: Vec<View> = match matches.get_many::<String>("view") {
+            Some(views) => views.map(|v| match v.as_str() {
+                "front" => View::Front,
+                "side" => View::Side,
+                _ => View::Top,
+            }).collect(),
+            None if matches.contains_id("slice") => vec![],
+            None => vec![View::Top, View::Front, View::Side],
+        };
+        if let Some(height) = matches.get_one::<f64>("slice") {
+            views.push(View::Slice((height / GRID).floor() as i64));
+        }
+        let width = matches.get_one::<usize>("width").copied()
+            .or_else(|| std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()))
+            .unwrap_or(80);
+
+        Ok(PreviewConfig {
+            file_name: command::extract_filename("BLUEPRINT".to_string(), matches)?,
+            views,
+            svg: matches.get_one::<String>("svg").map(PathBuf::from),
+            width: width.max(1),
+            components: !*matches.get_one::<bool>("no-components").unwrap_or(&false)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = PreviewConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+/// A box in a view: its column range, row range, nearest face and color.
+type Footprint = ((i64, i64), (i64, i64), i64, Rgba);
+
+/// A flat picture of a view: the color and nearness of the closest cell behind each pixel.
+struct Projection {
+    width: usize,
+    height: usize,
+    /// row by row, from the top
+    pixels: Vec<Option<(Rgba, i64)>>,
+}
+
+impl Projection {
+    fn new(boxes: &[GridBox], view: View) -> Projection {
+        let boxes: Vec<&GridBox> = match view {
+            View::Slice(level) => boxes.iter().filter(|b| b.min[1] <= level && level < b.max()[1]).collect(),
+            _ => boxes.iter().collect(),
+        };
+        let footprints: Vec<Footprint> = boxes.iter().map(|b| {
+            let (u0, v0, d0) = view.map(b.min);
+            let (u1, v1, d1) = view.map(b.max().map(|c| c - 1));
+            ((u0.min(u1), u0.max(u1)), (v0.min(v1), v0.max(v1)), d0.max(d1), b.color)
+        }).collect();
+
+        let u_min = footprints.iter().map(|f| f.0.0).min().unwrap_or(0);
+        let u_max = footprints.iter().map(|f| f.0.1).max().unwrap_or(-1);
+        let v_min = footprints.iter().map(|f| f.1.0).min().unwrap_or(0);
+        let v_max = footprints.iter().map(|f| f.1.1).max().unwrap_or(-1);
+        let width = (u_max - u_min + 1).max(0) as usize;
+        let height = (v_max - v_min + 1).max(0) as usize;
+
+        let mut pixels = vec![None; width * height];
+        for ((u0, u1), (v0, v1), depth, color) in footprints {
+            for v in v0..=v1 {
+                for u in u0..=u1 {
+                    let pixel = &mut pixels[(v_max - v) as usize * width + (u - u_min) as usize];
+                    if pixel.is_none_or(|(_, nearest)| depth >= nearest) {
+                        *pixel = Some((color, depth));
+                    }
+                }
+            }
+        }
+        Projection { width, height, pixels }
+    }
+
+    fn get(&self, column: usize, row: usize) -> Option<(Rgba, i64)> {
+        if column < self.width && row < self.height { self.pixels[row * self.width + column] } else { None }
+    }
+
+    /// Shrink by `factor` in both directions, keeping the nearest cell of each square.
+    fn downsample(self, factor: usize) -> Projection {
+        if factor <= 1 {
+            return self;
+        }
+        let width = self.width.div_ceil(factor);
+        let height = self.height.div_ceil(factor);
+        let mut pixels = vec![None; width * height];
+        for row in 0..height {
+            for column in 0..width {
+                pixels[row * width + column] = (0..factor * factor)
+                    .filter_map(|i| self.get(column * factor + i % factor, row * factor + i / factor))
+                    .max_by_key(|(_, depth)| *depth);
+            }
+        }
+        Projection { width, height, pixels }
+    }
+
+    /// Colors with farther cells darkened, so shapes read in a flat picture.
+    fn shaded(&self) -> Vec<Option<Rgba>> {
+        let depths = self.pixels.iter().flatten().map(|(_, d)| *d);
+        let (near, far) = (depths.clone().max().unwrap_or(0), depths.min().unwrap_or(0));
+        self.pixels.iter().map(|pixel| pixel.map(|(color, depth)| {
+            let nearness = if near == far { 1.0 } else { (depth - far) as f32 / (near - far) as f32 };
+            let shade = |c: u8| (c as f32 * (FAR_SHADE + (1.0 - FAR_SHADE) * nearness)).round() as u8;
+            let [r, g, b, a] = color.0;
+            Rgba([shade(r), shade(g), shade(b), a])
+        })).collect()
+    }
+}
+
+/// Two rows per line with half blocks, so cells come out about square.
+fn ansi(projection: &Projection) -> String {
+    let colors = projection.shaded();
+    let color = |column: usize, row: usize| if row < projection.height { colors[row * projection.width + column] } else { None };
+    let mut out = String::new();
+    for row in (0..projection.height).step_by(2) {
+        for column in 0..projection.width {
+            match (color(column, row), color(column, row + 1)) {
+                (None, None) => out.push(' '),
+                (Some(Rgba([r, g, b, _])), None) => out.push_str(&format!("\x1b[38;2;{};{};{}m▀\x1b[0m", r, g, b)),
+                (None, Some(Rgba([r, g, b, _]))) => out.push_str(&format!("\x1b[38;2;{};{};{}m▄\x1b[0m", r, g, b)),
+                (Some(Rgba([r, g, b, _])), Some(Rgba([r2, g2, b2, _]))) => {
+                    out.push_str(&format!("\x1b[38;2;{};{};{};48;2;{};{};{}m▀\x1b[0m", r, g, b, r2, g2, b2))
+                }
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn svg(title: &str, projection: &Projection) -> String {
+    let colors = projection.shaded();
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" shape-rendering=\"crispEdges\">\n<title>{}</title>\n",
+        projection.width * SVG_CELL, projection.height * SVG_CELL,
+        title.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;"));
+    for row in 0..projection.height {
+        // one rectangle per run of the same color
+        let mut column = 0;
+        while column < projection.width {
+            let Some(color) = colors[row * projection.width + column] else {
+                column += 1;
+                continue;
+            };
+            let start = column;
+            while column < projection.width && colors[row * projection.width + column] == Some(color) {
+                column += 1;
+            }
+            let opacity = if color.0[3] < 255 { format!(" fill-opacity=\"{:.2}\"", color.0[3] as f32 / 255.0) } else { String::new() };
+            out.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#{}\"{}/>\n",
+                                  start * SVG_CELL, row * SVG_CELL, (column - start) * SVG_CELL, SVG_CELL, color.hex(), opacity));
+        }
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+pub fn preview_blueprint(args: &ArgMatches) {
+    let config = match PreviewConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let blueprint = command::get_blueprint_object(config.file_name.clone()).ok()
+        .and_then(|source| serde_json::from_str::<Blueprint>(&source).ok());
+    let Some(blueprint) = blueprint else {
+        eprintln!("🚨 Blueprint could not be parsed! 🚨");
+        std::process::exit(1);
+    };
+
+    let mut boxes = geometry::blocks(&blueprint);
+    if config.components {
+        boxes.extend(geometry::occupancies(&blueprint));
+    }
+    let name = config.file_name.file_stem().unwrap_or_default().to_string_lossy().to_string();
+
+    for view in &config.views {
+        let projection = Projection::new(&boxes, *view);
+        match &config.svg {
+            Some(dir) => {
+                let path = dir.join(format!("{}-{}.svg", name, view.name().replace(' ', "-")));
+                let written = std::fs::create_dir_all(dir)
+                    .and_then(|_| std::fs::write(&path, svg(&format!("{} ({})", name, view.name()), &projection)));
+                match written {
+                    Ok(_) => println!("Wrote {:?}", path),
+                    Err(e) => {
+                        eprintln!("Error saving {:?}: {:?}", path, e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            None => {
+                println!("{} ({})", name, view.name());
+                if projection.width == 0 {
+                    println!("(empty)\n");
+                    continue;
+                }
+                let factor = projection.width.div_ceil(config.width);
+                println!("{}", ansi(&projection.downsample(factor)));
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+use clap::{ArgMatches, Error, FromArgMatches};
+use crate::archean::json::Blueprint;
+use crate::archean::raw::RawBlueprint;
+use crate::command;
+use crate::command::manifest::{YANK_MANIFEST, YankManifest};
+
+pub struct PushConfig {
+    pub(crate) file_name: PathBuf,
+    pub(crate) from: PathBuf,
+    pub(crate) dry_run: bool
+}
+
+impl FromArgMatches for PushConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let file_name = command::extract_filename("BLUEPRINT".to_string(), matches)?;
+        let from = match matches.get_one::<String>("from") {
+            Some(dir) => PathBuf::from(dir),
+            None => default_source_dir(&file_name),
+        };
+        let dry_run = matches.get_one::<bool>("dry-run").unwrap_or(&false);
+
+        Ok(PushConfig {
+            file_name,
+            from,
+            dry_run: *dry_run
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = PushConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+/// `yank -f` writes into a folder named after the blueprint; prefer that over the cwd.
+fn default_source_dir(file_name: &Path) -> PathBuf {
+    let folder = PathBuf::from(file_name.file_stem().unwrap_or_default());
+    if folder.join(YANK_MANIFEST).exists() {
+        folder
+    } else {
+        PathBuf::from(".")
+    }
+}
+
+pub fn push_xenon_code(args: &ArgMatches) {
+    let config = match PushConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let Some(manifest) = YankManifest::load(&config.from) else {
+        eprintln!("🚨 No {} found in {:?}; yank the blueprint first! 🚨", YANK_MANIFEST, config.from);
+        std::process::exit(1);
+    };
+    let blueprint_file = config.file_name.file_name().unwrap().to_string_lossy().to_string();
+    if manifest.blueprint != blueprint_file {
+        eprintln!("Warning: files were yanked from {:?}, pushing to {:?}", manifest.blueprint, blueprint_file);
+    }
+
+    let source = match command::get_blueprint_object(config.file_name.clone()) {
+        Ok(source) => source,
+        Err(_) => {
+            eprintln!("🚨 Blueprint could not be found or opened! 🚨");
+            std::process::exit(1);
+        }
+    };
+    let (blueprint, mut raw) = match (serde_json::from_str::<Blueprint>(&source), RawBlueprint::parse(&source)) {
+        (Ok(blueprint), Ok(raw)) => (blueprint, raw),
+        _ => {
+            eprintln!("🚨 Blueprint could not be parsed! 🚨");
+            std::process::exit(1);
+        }
+    };
+    let current = blueprint.xc_files();
+
+    let mut changed = 0;
+    for entry in &manifest.files {
+        let code = match std::fs::read_to_string(config.from.join(&entry.path)) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("Skipping {}: {}", entry.path, e);
+                continue;
+            }
+        };
+        // a shared file fans out to every component it was yanked from
+        for target in &entry.targets {
+            let unchanged = current.iter().any(|f| {
+                f.component_index() == target.component
+                    && f.is_program() == target.program
+                    && f.file_name() == target.name
+                    && f.file_content() == code
+            });
+            if unchanged {
+                continue;
+            }
+            let component = blueprint.data.components.get(target.component)
+                .map(|c| c.name())
+                .unwrap_or_default();
+            let written = if target.program {
+                raw.set_program(target.component, &code)
+            } else {
+                raw.set_xc_file(target.component, &target.name, &code)
+            };
+            if written {
+                println!("{}: ~ {} (from {})", component, target.name, entry.path);
+                changed += 1;
+            } else if target.program {
+                eprintln!("Skipping {}: component #{} does not exist", entry.path, target.component);
+            } else {
+                eprintln!("Skipping {}: component #{} has no HDD", entry.path, target.component);
+            }
+        }
+    }
+
+    if changed == 0 {
+        println!("Blueprint is up to date.");
+        return;
+    }
+    if config.dry_run {
+        println!("Dry run; blueprint not modified.");
+        return;
+    }
+    if let Err(e) = std::fs::write(&config.file_name, raw.to_json()) {
+        eprintln!("Error saving blueprint: {:?}", e);
+        std::process::exit(1);
+    }
+    println!("Wrote {} file(s) to {:?}", changed, config.file_name);
+}
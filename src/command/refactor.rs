@@ -0,0 +1,260 @@
+use std::path::PathBuf;
+use clap::{ArgMatches, Error, FromArgMatches};
+use clap::error::ErrorKind;
+use similar::TextDiff;
+use crate::archean::json::Blueprint;
+use crate::archean::raw::RawBlueprint;
+use crate::command;
+use crate::xenon::rename::{is_valid_name, occurrences, rename};
+
+/// Number of hash characters shown when listing candidates for `replace`.
+const SHORT_HASH: usize = 12;
+
+pub enum Operation {
+    Rename { from: String, to: String, function: bool },
+    Replace { name: String, hash: String, code: String },
+}
+
+pub struct RefactorConfig {
+    pub(crate) operation: Operation,
+    pub(crate) blueprints: Vec<PathBuf>,
+    pub(crate) dry_run: bool
+}
+
+fn sigil(function: bool) -> char {
+    if function { '@' } else { '$' }
+}
+
+fn parse_rename(matches: &ArgMatches) -> Result<Operation, Error> {
+    let from = matches.get_one::<String>("FROM").cloned().unwrap_or_default();
+    let to = matches.get_one::<String>("TO").cloned().unwrap_or_default();
+    let function = match from.chars().next() {
+        Some('$') => false,
+        Some('@') => true,
+        _ => return Err(Error::raw(ErrorKind::InvalidValue, "FROM must start with $ or @\n")),
+    };
+    let from = from[1..].to_string();
+    let to = match to.strip_prefix(sigil(function)) {
+        Some(to) => to.to_string(),
+        None if to.starts_with(['$', '@']) => {
+            return Err(Error::raw(ErrorKind::InvalidValue, "a variable can't be renamed to a function or the other way around\n"));
+        }
+        None => to,
+    };
+    if !is_valid_name(&from) || !is_valid_name(&to) {
+        return Err(Error::raw(ErrorKind::InvalidValue, "names may only contain letters, digits and _\n"));
+    }
+    Ok(Operation::Rename { from, to, function })
+}
+
+fn parse_replace(matches: &ArgMatches) -> Result<Operation, Error> {
+    let with = matches.get_one::<String>("with").cloned().unwrap_or_default();
+    let code = std::fs::read_to_string(&with)
+        .map_err(|e| Error::raw(ErrorKind::Io, format!("could not read {}: {}\n", with, e)))?;
+    let hash = matches.get_one::<String>("HASH").cloned().unwrap_or_default().to_lowercase();
+    if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::raw(ErrorKind::InvalidValue, "HASH must be (a prefix of) a hex SHA-256\n"));
+    }
+
+    Ok(Operation::Replace {
+        name: matches.get_one::<String>("FILE").cloned().unwrap_or_default(),
+        hash,
+        code
+    })
+}
+
+impl FromArgMatches for RefactorConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let (operation, matches) = match matches.subcommand() {
+            Some(("rename", sub)) => (parse_rename(sub)?, sub),
+            Some(("replace", sub)) => (parse_replace(sub)?, sub),
+            _ => return Err(Error::new(ErrorKind::MissingSubcommand)),
+        };
+
+        let blueprints = match matches.get_many::<String>("blueprint") {
+            Some(names) => names
+                .map(|name| command::get_blueprint_path(format!("{}.json", name), matches))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => {
+                let mut all: Vec<PathBuf> = std::fs::read_dir(command::get_blueprints_path(matches)?)
+                    .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+                    .unwrap_or_default();
+                all.retain(|p| p.extension().is_some_and(|e| e == "json"));
+                all.sort();
+                all
+            }
+        };
+
+        Ok(RefactorConfig {
+            operation,
+            blueprints,
+            dry_run: *matches.get_one::<bool>("dry-run").unwrap_or(&false)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = RefactorConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+struct FileChange {
+    component_index: usize,
+    program: bool,
+    label: String,
+    name: String,
+    before: String,
+    after: String,
+    summary: String,
+}
+
+struct BlueprintChange {
+    path: PathBuf,
+    raw: RawBlueprint,
+    files: Vec<FileChange>,
+}
+
+fn blueprint_name(path: &std::path::Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// Work out the new code of every affected file without touching any blueprint, so a conflict
+/// in one blueprint leaves all of them unmodified.
+fn plan(config: &RefactorConfig) -> Result<Vec<BlueprintChange>, String> {
+    let mut planned = vec![];
+    let mut candidates: Vec<String> = vec![];
+
+    for path in &config.blueprints {
+        let source = command::get_blueprint_object(path.clone())
+            .map_err(|_| format!("{:?} could not be opened", path))?;
+        let (blueprint, raw) = match (serde_json::from_str::<Blueprint>(&source), RawBlueprint::parse(&source)) {
+            (Ok(blueprint), Ok(raw)) => (blueprint, raw),
+            _ => {
+                eprintln!("Skipping {:?}: not a blueprint", path);
+                continue;
+            }
+        };
+        let files = blueprint.xc_files();
+        let label = |component: &str, file: &str| format!("{} › {} › {}", blueprint_name(path), component, file);
+
+        let mut changes = vec![];
+        match &config.operation {
+            Operation::Rename { from, to, function } => {
+                // globals are shared by every file of an HDD, so the new name must be free in all of them
+                for file in &files {
+                    let uses_from = files.iter()
+                        .filter(|f| f.component_index() == file.component_index() && f.is_program() == file.is_program())
+                        .any(|f| !occurrences(f.file_content(), from, *function).is_empty());
+                    if !uses_from {
+                        continue;
+                    }
+                    if let Some(span) = occurrences(file.file_content(), to, *function).first() {
+                        return Err(format!("{}{} is already used in {}:{}",
+                            sigil(*function), to, label(file.component(), file.file_name()), span.line + 1));
+                    }
+                }
+                for file in &files {
+                    let (after, count) = rename(file.file_content(), from, to, *function);
+                    if count > 0 {
+                        changes.push(FileChange {
+                            component_index: file.component_index(),
+                            program: file.is_program(),
+                            label: label(file.component(), file.file_name()),
+                            name: file.file_name().to_string(),
+                            before: file.file_content().to_string(),
+                            after,
+                            summary: format!("{} occurrence(s)", count),
+                        });
+                    }
+                }
+            }
+            Operation::Replace { name, hash, code } => {
+                for file in files.iter().filter(|f| f.file_name() == name) {
+                    let current = command::content_hash(file.file_content().as_bytes());
+                    if !current.starts_with(hash.as_str()) {
+                        candidates.push(format!("{}  {}", &current[..SHORT_HASH], label(file.component(), file.file_name())));
+                        continue;
+                    }
+                    if file.file_content() == code {
+                        continue;
+                    }
+                    changes.push(FileChange {
+                        component_index: file.component_index(),
+                        program: file.is_program(),
+                        label: label(file.component(), file.file_name()),
+                        name: file.file_name().to_string(),
+                        before: file.file_content().to_string(),
+                        after: code.clone(),
+                        summary: format!("{} → {}", &current[..SHORT_HASH], &command::content_hash(code.as_bytes())[..SHORT_HASH]),
+                    });
+                }
+            }
+        }
+
+        if !changes.is_empty() {
+            planned.push(BlueprintChange { path: path.clone(), raw, files: changes });
+        }
+    }
+
+    if planned.is_empty() && !candidates.is_empty() {
+        println!("No file matches that hash; files with that name:");
+        candidates.iter().for_each(|c| println!("  {}", c));
+    }
+    Ok(planned)
+}
+
+pub fn refactor_blueprints(args: &ArgMatches) {
+    let config = match RefactorConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(e) if e.kind() == ErrorKind::ValueValidation => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprint!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let planned = match plan(&config) {
+        Ok(planned) => planned,
+        Err(e) => {
+            eprintln!("🚨 {}; nothing was changed 🚨", e);
+            std::process::exit(1);
+        }
+    };
+    if planned.is_empty() {
+        println!("Nothing to change.");
+        return;
+    }
+
+    let mut changed = 0;
+    for mut blueprint in planned {
+        for file in &blueprint.files {
+            println!("{}: {}", file.label, file.summary);
+            if config.dry_run {
+                let diff = TextDiff::from_lines(&file.before, &file.after);
+                print!("{}", diff.unified_diff().context_radius(2).header(&file.label, &file.label));
+            }
+            if file.program {
+                blueprint.raw.set_program(file.component_index, &file.after);
+            } else {
+                blueprint.raw.set_xc_file(file.component_index, &file.name, &file.after);
+            }
+            changed += 1;
+        }
+        if config.dry_run {
+            continue;
+        }
+        if let Err(e) = std::fs::write(&blueprint.path, blueprint.raw.to_json()) {
+            eprintln!("Error saving blueprint {:?}: {:?}", blueprint.path, e);
+            std::process::exit(1);
+        }
+    }
+
+    if config.dry_run {
+        println!("Dry run; {} file(s) would change, no blueprint was modified.", changed);
+    } else {
+        println!("Changed {} file(s).", changed);
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use clap::{ArgMatches, Error, FromArgMatches};
+use semver::{Version, VersionReq};
+use serde_derive::{Deserialize, Serialize};
+use crate::command;
+use crate::command::archive::{Archive, ARCHIVE_EXTENSION};
+use crate::command::install::load_package;
+
+/// Registry used when `--registry` isn't given.
+pub const REGISTRY_ENV: &str = "ARCHBELT_REGISTRY";
+/// Index of a registry, at its root.
+pub const REGISTRY_INDEX: &str = "index.json";
+/// Versions picked by the last install, written to the cwd.
+pub const LOCKFILE: &str = "archbelt.lock";
+
+pub fn parse_version(version: &str) -> Option<Version> {
+    Version::parse(version.trim()).ok()
+}
+
+/// A dependency as written in `archbelt.json`: `name` or `name@<requirement>`, e.g. `pid@^1.2`.
+#[derive(Debug, Clone)]
+pub struct Dependency {
+    pub name: String,
+    pub requirement: VersionReq,
+}
+
+impl Dependency {
+    pub fn parse(dependency: &str) -> Result<Dependency, String> {
+        let (name, requirement) = match dependency.split_once('@') {
+            Some((name, requirement)) => (name.trim(), requirement.trim()),
+            None => (dependency.trim(), "*"),
+        };
+        if name.is_empty() {
+            return Err(format!("dependency {:?} has no name", dependency));
+        }
+        let requirement = VersionReq::parse(requirement)
+            .map_err(|e| format!("dependency {:?}: {}", dependency, e))?;
+        Ok(Dependency { name: name.to_string(), requirement })
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryIndex {
+    /// published versions by package name, oldest first
+    pub(crate) packages: BTreeMap<String, Vec<Release>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Release {
+    pub(crate) version: String,
+    /// archive path, relative to the registry
+    pub(crate) file: String,
+    pub(crate) sha256: String,
+    pub(crate) dependencies: Vec<String>,
+}
+
+pub struct Registry {
+    root: PathBuf,
+    index: RegistryIndex,
+}
+
+impl Registry {
+    /// Open the registry at a folder or `file://` URL, or the one from `ARCHBELT_REGISTRY`.
+    pub fn open(location: Option<&str>) -> Result<Registry, String> {
+        let location = match location {
+            Some(location) => location.to_string(),
+            None => std::env::var(REGISTRY_ENV)
+                .map_err(|_| format!("no registry given; pass --registry or set {}", REGISTRY_ENV))?,
+        };
+        let root = match location.strip_prefix("file://") {
+            Some(path) => PathBuf::from(path),
+            None if location.contains("://") => return Err(format!("{}: only folders and file:// URLs are supported", location)),
+            None => PathBuf::from(location),
+        };
+        let index = match std::fs::read_to_string(root.join(REGISTRY_INDEX)) {
+            Ok(index) => serde_json::from_str(&index).map_err(|e| format!("{:?}: {}", root.join(REGISTRY_INDEX), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => RegistryIndex::default(),
+            Err(e) => return Err(format!("{:?}: {}", root.join(REGISTRY_INDEX), e)),
+        };
+        Ok(Registry { root, index })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn releases(&self, name: &str) -> &[Release] {
+        self.index.packages.get(name).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// The newest release of `name` that satisfies `requirement`.
+    pub fn best_match(&self, name: &str, requirement: &VersionReq) -> Option<&Release> {
+        self.releases(name).iter()
+            .filter_map(|r| parse_version(&r.version).map(|v| (v, r)))
+            .filter(|(v, _)| requirement.matches(v))
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, r)| r)
+    }
+
+    /// Read a release's archive, checking it against the hash in the index.
+    pub fn fetch(&self, release: &Release) -> Result<Archive, String> {
+        let path = self.root.join(&release.file);
+        let data = std::fs::read(&path).map_err(|e| format!("{:?}: {}", path, e))?;
+        if command::content_hash(&data) != release.sha256 {
+            return Err(format!("{:?} does not match the hash in the registry index", path));
+        }
+        Archive::read(&path).map_err(|e| e.to_string())
+    }
+
+    /// Add a package to the registry, as `<name>/<name>-<version>.archbelt`.
+    ///
+    /// Publishing a version again is fine as long as its contents are the same; published
+    /// versions never change, so lockfiles pointing at them stay valid.
+    pub fn publish(&mut self, archive: &Archive) -> Result<Release, String> {
+        let metadata = archive.metadata().ok_or("the package has no metadata")?;
+        let problems = metadata.validate();
+        if !problems.is_empty() {
+            return Err(problems.join("; "));
+        }
+        let name = metadata.name.clone().unwrap_or_default();
+        let version = parse_version(&metadata.version).unwrap_or_else(|| Version::new(0, 0, 0));
+
+        let file = format!("{}/{}-{}.{}", name, name, version, ARCHIVE_EXTENSION);
+        let destination = self.root.join(&file);
+        if let Some(existing) = self.releases(&name).iter().find(|r| parse_version(&r.version).as_ref() == Some(&version)) {
+            let published = self.fetch(existing)?;
+            if published.checksums() == archive.checksums() {
+                return Ok(existing.clone());
+            }
+            return Err(format!("{} {} is already published with different contents", name, version));
+        }
+
+        std::fs::create_dir_all(destination.parent().unwrap()).map_err(|e| format!("{:?}: {}", destination, e))?;
+        archive.write(&destination).map_err(|e| e.to_string())?;
+        let data = std::fs::read(&destination).map_err(|e| format!("{:?}: {}", destination, e))?;
+        let release = Release {
+            version: version.to_string(),
+            file,
+            sha256: command::content_hash(&data),
+            dependencies: metadata.dependencies.clone(),
+        };
+        self.index.packages.entry(name).or_default().push(release.clone());
+        std::fs::write(self.root.join(REGISTRY_INDEX), serde_json::to_string_pretty(&self.index).unwrap())
+            .map_err(|e| format!("{:?}: {}", self.root.join(REGISTRY_INDEX), e))?;
+        Ok(release)
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub(crate) packages: Vec<LockedPackage>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) sha256: String,
+    pub(crate) dependencies: Vec<String>,
+}
+
+impl Lockfile {
+    pub fn load<P: AsRef<Path>>(dir: P) -> Option<Lockfile> {
+        let lockfile = std::fs::read_to_string(dir.as_ref().join(LOCKFILE)).ok()?;
+        serde_json::from_str(&lockfile).ok()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> std::io::Result<()> {
+        std::fs::write(dir.as_ref().join(LOCKFILE), serde_json::to_string_pretty(self).unwrap())
+    }
+
+    fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|p| p.name == name)
+    }
+}
+
+/// A package picked from the registry.
+#[derive(Debug, Clone)]
+pub struct Resolved {
+    pub name: String,
+    pub release: Release,
+}
+
+/// Pick a release for every package `roots` need, directly or through their dependencies.
+///
+/// Locked versions are kept as long as they still satisfy every requirement; otherwise the newest
+/// matching release is used. Each package is installed in one version only, so two requirements
+/// that no single release satisfies are an error. Dependencies come before the packages needing
+/// them in the result.
+pub fn resolve(registry: &Registry, roots: &[Dependency], lock: Option<&Lockfile>) -> Result<Vec<Resolved>, String> {
+    // each package with what asked for it; `None` for the roots
+    let mut selected: Vec<(Resolved, Option<String>)> = vec![];
+    let mut pending: Vec<(Dependency, Option<String>)> = roots.iter().rev().map(|d| (d.clone(), None)).collect();
+
+    while let Some((dependency, required_by)) = pending.pop() {
+        let needs = match &required_by {
+            Some(package) => format!("{} needs {}@{}", package, dependency.name, dependency.requirement),
+            None => format!("{}@{} was asked for", dependency.name, dependency.requirement),
+        };
+        if let Some((resolved, picked_by)) = selected.iter().find(|(r, _)| r.name == dependency.name) {
+            let version = parse_version(&resolved.release.version).unwrap();
+            if !dependency.requirement.matches(&version) {
+                let picked_by = picked_by.as_deref().unwrap_or("the command line");
+                return Err(format!("{}, but {} picked {}", needs, picked_by, version));
+            }
+            continue;
+        }
+
+        let locked = lock.and_then(|l| l.get(&dependency.name))
+            .filter(|l| parse_version(&l.version).is_some_and(|v| dependency.requirement.matches(&v)))
+            .and_then(|l| registry.releases(&dependency.name).iter().find(|r| r.version == l.version && r.sha256 == l.sha256));
+        let release = locked.or_else(|| registry.best_match(&dependency.name, &dependency.requirement))
+            .ok_or_else(|| match registry.releases(&dependency.name) {
+                [] => format!("{}, but {} is not in the registry", needs, dependency.name),
+                _ => format!("{}, but no published version matches", needs),
+            })?
+            .clone();
+
+        let picked_by = format!("{} {}", dependency.name, release.version);
+        for nested in release.dependencies.iter().rev() {
+            pending.push((Dependency::parse(nested)?, Some(picked_by.clone())));
+        }
+        selected.push((Resolved { name: dependency.name.clone(), release }, required_by));
+    }
+
+    let selected: Vec<Resolved> = selected.into_iter().map(|(r, _)| r).collect();
+    let mut ordered = vec![];
+    for root in roots {
+        post_order(&root.name, &selected, &mut vec![], &mut ordered);
+    }
+    Ok(ordered)
+}
+
+/// Append `name` to `ordered` after everything it depends on, each package once.
+fn post_order(name: &str, selected: &[Resolved], visiting: &mut Vec<String>, ordered: &mut Vec<Resolved>) {
+    // a dependency cycle is cut where it closes
+    if visiting.iter().any(|v| v == name) || ordered.iter().any(|r| r.name == name) {
+        return;
+    }
+    let Some(package) = selected.iter().find(|r| r.name == name) else {
+        return;
+    };
+    visiting.push(name.to_string());
+    for dependency in package.release.dependencies.iter().filter_map(|d| Dependency::parse(d).ok()) {
+        post_order(&dependency.name, selected, visiting, ordered);
+    }
+    visiting.pop();
+    ordered.push(package.clone());
+}
+
+pub struct PublishConfig {
+    pub(crate) source: PathBuf,
+    pub(crate) registry: Option<String>
+}
+
+impl FromArgMatches for PublishConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        Ok(PublishConfig {
+            source: matches.get_one::<String>("SOURCE").map(PathBuf::from).unwrap_or_default(),
+            registry: matches.get_one::<String>("registry").cloned()
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = PublishConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+pub fn publish_package(args: &ArgMatches) {
+    let config = PublishConfig::from_arg_matches(args).unwrap();
+    let mut registry = match Registry::open(config.registry.as_deref()) {
+        Ok(registry) => registry,
+        Err(e) => {
+            eprintln!("🚨 {} 🚨", e);
+            std::process::exit(1);
+        }
+    };
+
+    let archive = match load_package(&config.source) {
+        Ok(archive) => archive,
+        Err(problems) => {
+            problems.iter().for_each(|p| eprintln!("✘ {}", p));
+            eprintln!("🚨 {:?} is not a valid package; nothing was published 🚨", config.source);
+            std::process::exit(1);
+        }
+    };
+
+    match registry.publish(&archive) {
+        Ok(release) => println!("Published {} to {:?}", release.file, registry.root()),
+        Err(e) => {
+            eprintln!("🚨 {} 🚨", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(version: &str, dependencies: &[&str]) -> Release {
+        Release {
+            version: version.to_string(),
+            file: String::new(),
+            sha256: format!("hash of {}", version),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    fn registry(packages: &[(&str, Vec<Release>)]) -> Registry {
+        Registry {
+            root: PathBuf::new(),
+            index: RegistryIndex {
+                packages: packages.iter().map(|(name, releases)| (name.to_string(), releases.clone())).collect(),
+            },
+        }
+    }
+
+    fn roots(dependencies: &[&str]) -> Vec<Dependency> {
+        dependencies.iter().map(|d| Dependency::parse(d).unwrap()).collect()
+    }
+
+    fn picked(resolved: &[Resolved]) -> Vec<String> {
+        resolved.iter().map(|r| format!("{}@{}", r.name, r.release.version)).collect()
+    }
+
+    #[test]
+    fn dependencies_come_before_the_packages_needing_them() {
+        let registry = registry(&[
+            ("a", vec![release("1.0.0", &["c", "b"])]),
+            ("b", vec![release("1.0.0", &["c"])]),
+            ("c", vec![release("1.0.0", &[])]),
+        ]);
+        let resolved = resolve(&registry, &roots(&["a"]), None).unwrap();
+        assert_eq!(picked(&resolved), ["c@1.0.0", "b@1.0.0", "a@1.0.0"]);
+    }
+
+    #[test]
+    fn a_diamond_shares_one_version() {
+        let registry = registry(&[
+            ("app", vec![release("1.0.0", &["left@^1", "right@^1"])]),
+            ("left", vec![release("1.0.0", &["base@^1.0"])]),
+            ("right", vec![release("1.0.0", &["base@>=1.1"])]),
+            ("base", vec![release("1.0.0", &[]), release("1.1.0", &[]), release("1.2.0", &[]), release("2.0.0", &[])]),
+        ]);
+        let resolved = resolve(&registry, &roots(&["app"]), None).unwrap();
+        assert_eq!(picked(&resolved), ["base@1.2.0", "left@1.0.0", "right@1.0.0", "app@1.0.0"]);
+    }
+
+    #[test]
+    fn requirements_no_release_satisfies_are_an_error() {
+        let registry = registry(&[
+            ("app", vec![release("1.0.0", &["left", "right"])]),
+            ("left", vec![release("1.0.0", &["base@^2"])]),
+            ("right", vec![release("1.0.0", &["base@~1.1"])]),
+            ("base", vec![release("1.1.0", &[]), release("2.0.0", &[])]),
+        ]);
+        let error = resolve(&registry, &roots(&["app"]), None).unwrap_err();
+        assert_eq!(error, "right 1.0.0 needs base@~1.1, but left 1.0.0 picked 2.0.0");
+    }
+
+    #[test]
+    fn missing_packages_and_versions_are_reported() {
+        let registry = registry(&[("app", vec![release("1.0.0", &["gone"])]), ("old", vec![release("0.1.0", &[])])]);
+        assert_eq!(resolve(&registry, &roots(&["app"]), None).unwrap_err(), "app 1.0.0 needs gone@*, but gone is not in the registry");
+        assert_eq!(resolve(&registry, &roots(&["old@^1"]), None).unwrap_err(), "old@^1 was asked for, but no published version matches");
+    }
+
+    #[test]
+    fn the_newest_matching_release_is_picked() {
+        let registry = registry(&[("pid", vec![release("1.2.0", &[]), release("1.10.0", &[]), release("2.0.0-beta.1", &[]), release("1.3.0", &[])])]);
+        assert_eq!(registry.best_match("pid", &VersionReq::parse("^1.2").unwrap()).unwrap().version, "1.10.0");
+        assert_eq!(registry.best_match("pid", &VersionReq::parse("~1.2").unwrap()).unwrap().version, "1.2.0");
+        assert!(registry.best_match("pid", &VersionReq::parse("^2").unwrap()).is_none());
+    }
+
+    fn locked(name: &str, version: &str) -> Lockfile {
+        Lockfile {
+            packages: vec![LockedPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+                sha256: format!("hash of {}", version),
+                dependencies: vec![],
+            }],
+        }
+    }
+
+    #[test]
+    fn locked_versions_are_reused_while_they_match() {
+        let registry = registry(&[("pid", vec![release("1.2.0", &[]), release("1.3.0", &[])])]);
+        let resolved = resolve(&registry, &roots(&["pid@^1"]), Some(&locked("pid", "1.2.0"))).unwrap();
+        assert_eq!(picked(&resolved), ["pid@1.2.0"]);
+
+        // a lock the requirement has moved past is ignored
+        let resolved = resolve(&registry, &roots(&["pid@>=1.3"]), Some(&locked("pid", "1.2.0"))).unwrap();
+        assert_eq!(picked(&resolved), ["pid@1.3.0"]);
+    }
+
+    #[test]
+    fn locked_versions_with_a_different_hash_are_not_reused() {
+        let registry = registry(&[("pid", vec![release("1.2.0", &[]), release("1.3.0", &[])])]);
+        let mut lock = locked("pid", "1.2.0");
+        lock.packages[0].sha256 = "republished".to_string();
+        let resolved = resolve(&registry, &roots(&["pid"]), Some(&lock)).unwrap();
+        assert_eq!(picked(&resolved), ["pid@1.3.0"]);
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::{Component, Path};
+
+/// Characters that aren't allowed in a file name on at least one common filesystem, plus `%`,
+/// which starts an escape.
+const ILLEGAL: [char; 10] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*', '%'];
+
+/// Device names Windows reserves, with or without an extension.
+const RESERVED: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+
+fn is_reserved(segment: &str) -> bool {
+    let stem = segment.split('.').next().unwrap_or_default().to_ascii_uppercase();
+    RESERVED.contains(&stem.as_str())
+        || ((stem.starts_with("COM") || stem.starts_with("LPT")) && stem.len() == 4 && stem.as_bytes()[3].is_ascii_digit())
+}
+
+fn escape_char(c: char, out: &mut String) {
+    let mut buffer = [0; 4];
+    for byte in c.encode_utf8(&mut buffer).bytes() {
+        out.push_str(&format!("%{:02X}", byte));
+    }
+}
+
+/// A name from a blueprint that would leave the folder it is written to: absolute, or with a
+/// `..` part. (Drive letters need a `:`, which is always escaped.)
+pub fn escapes_root(name: &str) -> bool {
+    name.starts_with(['/', '\\']) || name.split(['/', '\\']).any(|part| part == "..")
+}
+
+/// Make `name` safe to use as one file or folder name anywhere.
+///
+/// Illegal and control characters, a trailing `.` or space, and the first character of reserved
+/// names are percent-encoded, so [`unescape`] gives back the original name.
+pub fn escape_segment(name: &str) -> String {
+    let mut out = String::new();
+    let count = name.chars().count();
+    for (i, c) in name.chars().enumerate() {
+        let trailing = i + 1 == count && matches!(c, '.' | ' ');
+        if c.is_control() || ILLEGAL.contains(&c) || trailing {
+            escape_char(c, &mut out);
+        } else {
+            out.push(c);
+        }
+    }
+    if is_reserved(&out) {
+        let first = out.remove(0);
+        let mut escaped = String::new();
+        escape_char(first, &mut escaped);
+        out.insert_str(0, &escaped);
+    }
+    out
+}
+
+/// Make a file name that may contain folders (`lib/pid.xc`, as `bundle` writes them) safe, keeping
+/// its `/`s as folders.
+pub fn escape_path(name: &str) -> Result<String, String> {
+    if escapes_root(name) {
+        return Err(format!("{:?} points outside the output folder", name));
+    }
+    let segments: Vec<&str> = name.split('/').collect();
+    if segments.iter().any(|s| s.is_empty()) {
+        return Err(format!("{:?} is not a valid file name", name));
+    }
+    Ok(segments.into_iter().map(escape_segment).collect::<Vec<_>>().join("/"))
+}
+
+/// Undo [`escape_segment`] or [`escape_path`].
+pub fn unescape(escaped: &str) -> String {
+    let mut bytes = vec![];
+    let mut rest = escaped.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let hex = tail.get(..2).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (byte, hex) {
+            (b'%', Some(decoded)) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+/// Whether a relative path stays below the folder it is joined to.
+pub fn is_contained(relative: &Path) -> bool {
+    relative.components().all(|c| matches!(c, Component::Normal(_)))
+}
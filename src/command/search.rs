@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use clap::{ArgMatches, Error, FromArgMatches};
+use serde_derive::{Deserialize, Serialize};
+use crate::archean::json::Blueprint;
+use crate::command;
+use crate::xenon::parser::parse;
+use crate::xenon::symbols::analyze;
+
+const INDEX_FILE: &str = "search-index.json";
+/// bump when the layout of the cached index changes
+const INDEX_VERSION: u32 = 2;
+
+pub struct SearchConfig {
+    pub(crate) blueprints_path: PathBuf,
+    pub(crate) query: String,
+    pub(crate) symbol: bool,
+    pub(crate) definitions: bool,
+    pub(crate) ignore_case: bool,
+    pub(crate) rebuild: bool
+}
+
+impl FromArgMatches for SearchConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let blueprints_path = command::get_blueprints_path(matches)?;
+        let query = matches.get_one::<String>("QUERY").cloned().unwrap_or_default();
+        let definitions = *matches.get_one::<bool>("definitions").unwrap_or(&false);
+        // `$name` and `@name` only make sense as identifiers
+        let symbol = *matches.get_one::<bool>("symbol").unwrap_or(&false)
+            || definitions
+            || query.starts_with('$')
+            || query.starts_with('@');
+
+        Ok(SearchConfig {
+            blueprints_path,
+            query,
+            symbol,
+            definitions,
+            ignore_case: *matches.get_one::<bool>("ignore-case").unwrap_or(&false),
+            rebuild: *matches.get_one::<bool>("rebuild").unwrap_or(&false)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = SearchConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    /// keyed by the blueprint's path
+    blueprints: HashMap<String, IndexedBlueprint>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedBlueprint {
+    modified: u128,
+    files: Vec<IndexedFile>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedFile {
+    component: String,
+    file: String,
+    code: String,
+    symbols: Vec<IndexedSymbol>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedSymbol {
+    name: String,
+    function: bool,
+    definition: bool,
+    line: usize,
+}
+
+impl SearchIndex {
+    fn load() -> SearchIndex {
+        let index = command::cache_dir()
+            .and_then(|dir| std::fs::read_to_string(dir.join(INDEX_FILE)).ok())
+            .and_then(|s| serde_json::from_str::<SearchIndex>(&s).ok());
+        match index {
+            Some(index) if index.version == INDEX_VERSION => index,
+            _ => SearchIndex { version: INDEX_VERSION, ..Default::default() },
+        }
+    }
+
+    fn save(&self) {
+        let Some(dir) = command::cache_dir() else {
+            return;
+        };
+        if std::fs::create_dir_all(&dir).is_ok() {
+            // the index is only a cache; failing to write it just makes the next search slower
+            let _ = std::fs::write(dir.join(INDEX_FILE), serde_json::to_string(self).unwrap());
+        }
+    }
+}
+
+fn index_blueprint(blueprint: &Blueprint) -> Vec<IndexedFile> {
+    blueprint.xc_files().iter().map(|f| {
+        let parsed = parse(f.file_content());
+        let symbols = analyze(&parsed.program);
+        let mut indexed: Vec<IndexedSymbol> = symbols.definitions.iter().map(|s| IndexedSymbol {
+            name: s.name.clone(),
+            function: s.is_function(),
+            definition: true,
+            line: s.span.line,
+        }).collect();
+        indexed.extend(symbols.references.iter().map(|r| IndexedSymbol {
+            name: r.name.clone(),
+            function: r.function,
+            definition: false,
+            line: r.span.line,
+        }));
+        indexed.sort_by_key(|s| s.line);
+        IndexedFile {
+            component: f.component().to_string(),
+            file: f.file_name().to_string(),
+            code: f.file_content().to_string(),
+            symbols: indexed,
+        }
+    }).collect()
+}
+
+/// Bring the cached index up to date with the blueprints folder, re-reading changed files only.
+fn refresh(index: &mut SearchIndex, config: &SearchConfig) -> Vec<String> {
+    let mut blueprints: Vec<PathBuf> = std::fs::read_dir(&config.blueprints_path)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    blueprints.retain(|p| p.extension().is_some_and(|e| e == "json"));
+    blueprints.sort();
+
+    let present: Vec<String> = blueprints.iter().map(|p| p.to_string_lossy().to_string()).collect();
+    // entries of other blueprint folders stay cached; deleted blueprints of this one are dropped
+    index.blueprints.retain(|key, _| {
+        Path::new(key).parent() != Some(config.blueprints_path.as_path()) || present.contains(key)
+    });
+
+    for (path, key) in blueprints.iter().zip(&present) {
+        let modified = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .map(|t| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos())
+            .unwrap_or_default();
+        if !config.rebuild && index.blueprints.get(key).is_some_and(|b| b.modified == modified) {
+            continue;
+        }
+        let files = command::get_blueprint_object(path.clone()).ok()
+            .and_then(|source| serde_json::from_str::<Blueprint>(&source).ok())
+            .map(|blueprint| index_blueprint(&blueprint))
+            .unwrap_or_default();
+        index.blueprints.insert(key.clone(), IndexedBlueprint { modified, files });
+    }
+    present
+}
+
+pub fn search_blueprints(args: &ArgMatches) {
+    let config = match SearchConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("🚨 Could not get blueprints path; is Archean installed? did you provide an invalid path to --blueprint-path? 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let mut index = SearchIndex::load();
+    let blueprints = refresh(&mut index, &config);
+    index.save();
+
+    let name = config.query.trim_start_matches(['$', '@']);
+    let sigil = config.query.chars().next().filter(|c| *c == '$' || *c == '@');
+    let needle = if config.ignore_case { config.query.to_lowercase() } else { config.query.clone() };
+
+    let mut found = 0;
+    for key in &blueprints {
+        let blueprint_name = PathBuf::from(key).file_stem().unwrap_or_default().to_string_lossy().to_string();
+        for file in &index.blueprints[key].files {
+            let lines: Vec<&str> = file.code.lines().collect();
+            let mut hits: Vec<(usize, bool)> = vec![];
+            if config.symbol {
+                for symbol in &file.symbols {
+                    let same_name = if config.ignore_case { symbol.name.eq_ignore_ascii_case(name) } else { symbol.name == name };
+                    let same_kind = match sigil {
+                        Some('@') => symbol.function,
+                        Some(_) => !symbol.function,
+                        None => true,
+                    };
+                    if same_name && same_kind && (symbol.definition || !config.definitions) {
+                        hits.push((symbol.line, symbol.definition));
+                    }
+                }
+                hits.dedup();
+            } else {
+                for (line, text) in lines.iter().enumerate() {
+                    let haystack = if config.ignore_case { text.to_lowercase() } else { text.to_string() };
+                    if haystack.contains(&needle) {
+                        hits.push((line, false));
+                    }
+                }
+            }
+
+            for (line, definition) in hits {
+                let text = lines.get(line).map(|l| l.trim()).unwrap_or_default();
+                let marker = if definition { " (definition)" } else { "" };
+                println!("{} › {} › {}:{}{}  {}", blueprint_name, file.component, file.file, line + 1, marker, text);
+                found += 1;
+            }
+        }
+    }
+
+    if found == 0 {
+        println!("No matches for {:?} in {} blueprint(s).", config.query, blueprints.len());
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use clap::{ArgMatches, Error, FromArgMatches};
+use serde_derive::Deserialize;
+use serde_json::Value as JsonValue;
+use crate::command;
+use crate::command::manifest::YankManifest;
+use crate::xenon::ast::Program;
+use crate::xenon::bundle::Library;
+use crate::xenon::include::includes;
+use crate::xenon::interpreter::{Machine, Slot, Value};
+use crate::xenon::parser::parse;
+
+/// Test files are picked up by this suffix, next to the `.xc` files they exercise.
+pub const TEST_SUFFIX: &str = ".test.json";
+
+pub struct TestConfig {
+    pub(crate) paths: Vec<PathBuf>,
+    pub(crate) library: Library
+}
+
+impl FromArgMatches for TestConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let paths = matches.get_many::<String>("PATH")
+            .map(|paths| paths.map(PathBuf::from).collect())
+            .unwrap_or_else(|| vec![PathBuf::from(".")]);
+        let lib_paths = matches.get_many::<String>("lib")
+            .map(|paths| paths.map(PathBuf::from).collect())
+            .unwrap_or_default();
+
+        Ok(TestConfig {
+            paths,
+            library: Library::new(lib_paths)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = TestConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestFile {
+    /// file the computer runs; defaults to `main.xc`, or every `.xc` file in the folder
+    entry: Option<String>,
+    #[serde(default = "default_tick_rate")]
+    tick_rate: f64,
+    cases: Vec<TestCase>,
+}
+
+fn default_tick_rate() -> f64 {
+    25.0
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestCase {
+    name: String,
+    #[serde(default)]
+    ticks: u64,
+    /// values read by `input_number`/`input_text`, set before the given tick runs
+    #[serde(default)]
+    inputs: Vec<InputValue>,
+    /// messages delivered to `input.N` entry points before the given tick runs
+    #[serde(default)]
+    messages: Vec<InputMessage>,
+    #[serde(default)]
+    expect: Vec<Expectation>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InputValue {
+    #[serde(default)]
+    tick: u64,
+    port: JsonValue,
+    #[serde(default)]
+    channel: u32,
+    value: JsonValue,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InputMessage {
+    #[serde(default)]
+    tick: u64,
+    port: u32,
+    #[serde(default)]
+    args: Vec<JsonValue>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Expectation {
+    /// number of ticks run before checking; 0 checks right after `init`, the default is the end
+    after_tick: Option<u64>,
+    /// global variables (and arrays) by name, with or without `$`
+    #[serde(default)]
+    vars: HashMap<String, JsonValue>,
+    /// the arguments of the last message sent with `output.N`, by port
+    #[serde(default)]
+    outputs: HashMap<String, Vec<JsonValue>>,
+    /// values written with `output_number`/`output_text`
+    #[serde(default)]
+    channels: Vec<InputValue>,
+    /// lines that must have been printed
+    #[serde(default)]
+    prints: Vec<String>,
+    #[serde(default = "default_tolerance")]
+    tolerance: f64,
+}
+
+fn default_tolerance() -> f64 {
+    1e-9
+}
+
+fn to_value(json: &JsonValue) -> Value {
+    match json {
+        JsonValue::String(s) => Value::Text(s.clone()),
+        JsonValue::Bool(b) => Value::Number(if *b { 1.0 } else { 0.0 }),
+        other => Value::Number(other.as_f64().unwrap_or_default()),
+    }
+}
+
+fn port_key(json: &JsonValue) -> String {
+    to_value(json).as_text()
+}
+
+fn matches(actual: &Value, expected: &JsonValue, tolerance: f64) -> bool {
+    match (actual, to_value(expected)) {
+        (Value::Text(a), Value::Text(e)) => *a == e,
+        (actual, expected) => (actual.as_number() - expected.as_number()).abs() <= tolerance,
+    }
+}
+
+fn describe(values: &[Value]) -> String {
+    let values: Vec<String> = values.iter().map(|v| match v {
+        Value::Text(t) => format!("{:?}", t),
+        Value::Number(_) => v.as_text(),
+    }).collect();
+    format!("[{}]", values.join(", "))
+}
+
+/// The computer a yanked folder belongs to, from the yank manifest.
+struct YankedHdd {
+    root: PathBuf,
+    manifest: YankManifest,
+    components: BTreeSet<usize>,
+}
+
+/// Where the HDD file `name` of the computer in `folder` is: in the folder itself, or wherever
+/// the yank manifest says it was deduplicated to.
+fn hdd_file(folder: &Path, name: &str, yanked: &Option<YankedHdd>) -> Option<PathBuf> {
+    if folder.join(name).is_file() {
+        return Some(folder.join(name));
+    }
+    yanked.as_ref()
+        .and_then(|hdd| hdd.manifest.hdd_file(&hdd.root, &hdd.components, name))
+        .filter(|path| path.is_file())
+}
+
+/// Load the programs of the HDD in `folder`, following includes into the library.
+fn load_programs(folder: &Path, entry: &Option<String>, library: &Library) -> Result<Vec<Program>, String> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(folder).map_err(|e| e.to_string())?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "xc"))
+        .collect();
+    files.sort();
+
+    // a yanked computer's files may have been deduplicated into the shared folder
+    let absolute = folder.canonicalize().unwrap_or_else(|_| folder.to_path_buf());
+    let yanked = YankManifest::find(&absolute).map(|(root, manifest)| {
+        let local: Vec<PathBuf> = files.iter().filter_map(|f| f.file_name()).map(|name| absolute.join(name)).collect();
+        let components = manifest.components_of(&root, local.iter().map(PathBuf::as_path));
+        YankedHdd { root, manifest, components }
+    });
+
+    let mut pending: Vec<String> = match entry {
+        Some(entry) => vec![entry.clone()],
+        None if hdd_file(&absolute, "main.xc", &yanked).is_some() => vec!["main.xc".to_string()],
+        None => files.iter().rev().filter_map(|f| f.file_name()).map(|name| name.to_string_lossy().to_string()).collect(),
+    };
+
+    let mut loaded: Vec<String> = vec![];
+    let mut programs = vec![];
+    while let Some(name) = pending.pop() {
+        if loaded.contains(&name) {
+            continue;
+        }
+        let path = hdd_file(&absolute, &name, &yanked)
+            .or_else(|| library.find(&name))
+            .ok_or_else(|| format!("{} not found", name))?;
+        let source = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", name, e))?;
+        let parsed = parse(&source);
+        if let Some(diagnostic) = parsed.diagnostics.first() {
+            return Err(format!("{}:{}: {}", name, diagnostic.span.line + 1, diagnostic.message));
+        }
+        includes(&source).into_iter().rev().for_each(|i| pending.push(i.path));
+        loaded.push(name);
+        programs.push(parsed.program);
+    }
+    Ok(programs)
+}
+
+fn check(machine: &Machine, expectation: &Expectation) -> Vec<String> {
+    let mut failures = vec![];
+    let tolerance = expectation.tolerance;
+
+    let mut vars: Vec<_> = expectation.vars.iter().collect();
+    vars.sort_by_key(|(name, _)| name.to_string());
+    for (name, expected) in vars {
+        let name = name.trim_start_matches('$');
+        match (machine.global(name), expected) {
+            (Some(Slot::Scalar(actual)), expected) if !expected.is_array() => {
+                if !matches(actual, expected, tolerance) {
+                    failures.push(format!("${} is {}, expected {}", name, describe(std::slice::from_ref(actual)), expected));
+                }
+            }
+            (Some(Slot::Array(actual)), JsonValue::Array(expected)) => {
+                let equal = actual.len() == expected.len()
+                    && actual.iter().zip(expected).all(|(a, e)| matches(a, e, tolerance));
+                if !equal {
+                    failures.push(format!("${} is {}, expected {}", name, describe(actual), JsonValue::Array(expected.clone())));
+                }
+            }
+            (Some(_), expected) => failures.push(format!("${} does not have the shape of {}", name, expected)),
+            (None, _) => failures.push(format!("${} is not a global variable", name)),
+        }
+    }
+
+    let mut outputs: Vec<_> = expectation.outputs.iter().collect();
+    outputs.sort_by_key(|(port, _)| port.to_string());
+    for (port, expected) in outputs {
+        let last = machine.io.messages.iter().rev().find(|m| m.port.to_string() == *port);
+        match last {
+            Some(message) => {
+                let equal = message.args.len() == expected.len()
+                    && message.args.iter().zip(expected).all(|(a, e)| matches(a, e, tolerance));
+                if !equal {
+                    failures.push(format!("output.{} sent {}, expected {}", port, describe(&message.args), JsonValue::Array(expected.clone())));
+                }
+            }
+            None => failures.push(format!("nothing was sent on output.{}", port)),
+        }
+    }
+
+    for channel in &expectation.channels {
+        let key = (port_key(&channel.port), channel.channel);
+        match machine.io.outputs.get(&key) {
+            Some(actual) if matches(actual, &channel.value, tolerance) => {}
+            Some(actual) => failures.push(format!("port {} channel {} is {}, expected {}", key.0, key.1, describe(std::slice::from_ref(actual)), channel.value)),
+            None => failures.push(format!("nothing was written to port {} channel {}", key.0, key.1)),
+        }
+    }
+
+    for line in &expectation.prints {
+        if !machine.io.prints.contains(line) {
+            failures.push(format!("{:?} was not printed", line));
+        }
+    }
+
+    failures
+}
+
+fn run_case(programs: Vec<Program>, tick_rate: f64, case: &TestCase) -> Result<Vec<String>, String> {
+    let mut machine = Machine::new(programs, tick_rate).map_err(|e| e.to_string())?;
+    machine.run_init().map_err(|e| format!("init: {}", e))?;
+
+    let mut failures = vec![];
+    let end = case.ticks;
+    let mut check_at = |machine: &Machine, tick: u64| {
+        for expectation in case.expect.iter().filter(|e| e.after_tick.unwrap_or(end) == tick) {
+            failures.extend(check(machine, expectation).into_iter().map(|f| format!("after tick {}: {}", tick, f)));
+        }
+    };
+    check_at(&machine, 0);
+
+    for tick in 0..case.ticks {
+        for input in case.inputs.iter().filter(|i| i.tick == tick) {
+            machine.io.inputs.insert((port_key(&input.port), input.channel), to_value(&input.value));
+        }
+        for message in case.messages.iter().filter(|m| m.tick == tick) {
+            let args = message.args.iter().map(to_value).collect();
+            machine.send_input(message.port, args).map_err(|e| format!("input.{} at tick {}: {}", message.port, tick, e))?;
+        }
+        machine.run_tick().map_err(|e| format!("tick {}: {}", tick, e))?;
+        check_at(&machine, tick + 1);
+    }
+
+    for expectation in &case.expect {
+        if expectation.after_tick.is_some_and(|t| t > case.ticks) {
+            failures.push(format!("after_tick {} is past the last tick ({})", expectation.after_tick.unwrap(), case.ticks));
+        }
+    }
+    Ok(failures)
+}
+
+pub fn run_tests(args: &ArgMatches) {
+    let config = match TestConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut test_files: Vec<PathBuf> = config.paths.iter()
+        .flat_map(|p| if p.is_file() { vec![p.clone()] } else { command::walk_files(p) })
+        .filter(|p| p.to_string_lossy().ends_with(TEST_SUFFIX))
+        .collect();
+    test_files.sort();
+    if test_files.is_empty() {
+        eprintln!("🚨 No *{} files found! 🚨", TEST_SUFFIX);
+        std::process::exit(1);
+    }
+
+    let (mut passed, mut failed) = (0, 0);
+    for test_file in &test_files {
+        println!("{}", test_file.display());
+        let tests = std::fs::read_to_string(test_file)
+            .map_err(|e| e.to_string())
+            .and_then(|s| serde_json::from_str::<TestFile>(&s).map_err(|e| e.to_string()));
+        let tests = match tests {
+            Ok(tests) => tests,
+            Err(e) => {
+                println!("  ✘ could not read tests: {}", e);
+                failed += 1;
+                continue;
+            }
+        };
+        let folder = test_file.parent().unwrap_or(Path::new("."));
+        let programs = match load_programs(folder, &tests.entry, &config.library) {
+            Ok(programs) => programs,
+            Err(e) => {
+                println!("  ✘ could not load code: {}", e);
+                failed += tests.cases.len().max(1);
+                continue;
+            }
+        };
+
+        for case in &tests.cases {
+            match run_case(programs.clone(), tests.tick_rate, case) {
+                Ok(failures) if failures.is_empty() => {
+                    println!("  ✔ {}", case.name);
+                    passed += 1;
+                }
+                Ok(failures) => {
+                    println!("  ✘ {}", case.name);
+                    failures.iter().for_each(|f| println!("      {}", f));
+                    failed += 1;
+                }
+                Err(e) => {
+                    println!("  ✘ {}", case.name);
+                    println!("      runtime error in {}", e);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    println!("\n{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
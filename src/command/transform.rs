@@ -0,0 +1,348 @@
+use std::path::PathBuf;
+use clap::{ArgMatches, Error, FromArgMatches};
+use clap::error::ErrorKind;
+use serde_json::Value;
+use crate::archean::geometry::{direction, Point, GRID};
+use crate::archean::json::Blueprint;
+use crate::archean::raw::RawBlueprint;
+use crate::command;
+
+const AXES: [&str; 3] = ["x", "y", "z"];
+const POS: [&str; 3] = ["pos_x", "pos_y", "pos_z"];
+const SIZE: [&str; 3] = ["size_x", "size_y", "size_z"];
+const FRAME: [&str; 3] = ["frame_x", "frame_y", "frame_z"];
+const XYZ: [&str; 3] = ["x", "y", "z"];
+
+/// `w`, `x`, `y`, `z`, as blueprints store orientations.
+type Quaternion = [f64; 4];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirror {
+    /// the blueprint's `symmetry_axis`
+    Symmetry,
+    Axis(usize),
+}
+
+pub struct TransformConfig {
+    pub(crate) file_name: PathBuf,
+    pub(crate) mirror: Option<Mirror>,
+    /// axis and number of quarter turns, in the order given
+    pub(crate) turns: Vec<(usize, i64)>,
+    /// in grid units
+    pub(crate) offset: [i64; 3],
+    /// file to write instead of the blueprint
+    pub(crate) out: Option<PathBuf>,
+    pub(crate) dry_run: bool
+}
+
+fn axis(name: &str) -> Result<usize, Error> {
+    AXES.iter().position(|a| a.eq_ignore_ascii_case(name))
+        .ok_or_else(|| Error::raw(ErrorKind::InvalidValue, format!("{:?} is not an axis; use x, y or z\n", name)))
+}
+
+/// Parse `x,y,z` in meters into grid units; each must be a whole number of grid cells.
+pub(crate) fn grid_offset(text: &str) -> Result<[i64; 3], Error> {
+    let invalid = || Error::raw(ErrorKind::InvalidValue,
+                                format!("{:?} is not an offset like 1.5,0,-2 in steps of {}m\n", text, GRID));
+    let meters: Vec<f64> = text.split(',').map(|v| v.trim().parse::<f64>()).collect::<Result<_, _>>().map_err(|_| invalid())?;
+    let [x, y, z] = meters[..] else {
+        return Err(invalid());
+    };
+    let cells = [x, y, z].map(|v| v / GRID);
+    if cells.iter().any(|c| (c - c.round()).abs() > 1e-9) {
+        return Err(invalid());
+    }
+    Ok(cells.map(|c| c.round() as i64))
+}
+
+/// Parse `AXIS` or `AXIS:DEGREES` into an axis and a number of quarter turns.
+fn turn(text: &str) -> Result<(usize, i64), Error> {
+    let (name, degrees) = text.split_once(':').unwrap_or((text, "90"));
+    let degrees: i64 = degrees.trim().parse().ok().filter(|d| d % 90 == 0)
+        .ok_or_else(|| Error::raw(ErrorKind::InvalidValue, format!("{:?} is not a multiple of 90 degrees\n", degrees)))?;
+    Ok((axis(name.trim())?, (degrees / 90).rem_euclid(4)))
+}
+
+impl FromArgMatches for TransformConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let file_name = command::extract_filename("BLUEPRINT".to_string(), matches)?;
+        let mirror = match matches.get_one::<String>("mirror").map(String::as_str) {
+            None => None,
+            Some("symmetry") => Some(Mirror::Symmetry),
+            Some(name) => Some(Mirror::Axis(axis(name)?)),
+        };
+        let turns = matches.get_many::<String>("rotate")
+            .map(|turns| turns.map(|t| turn(t)).collect::<Result<Vec<_>, _>>())
+            .transpose()?
+            .unwrap_or_default();
+        let offset = matches.get_one::<String>("translate").map(|t| grid_offset(t)).transpose()?.unwrap_or([0; 3]);
+
+        Ok(TransformConfig {
+            file_name,
+            mirror,
+            turns,
+            offset,
+            out: matches.get_one::<String>("out").map(PathBuf::from),
+            dry_run: *matches.get_one::<bool>("dry-run").unwrap_or(&false)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = TransformConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+fn multiply([aw, ax, ay, az]: Quaternion, [bw, bx, by, bz]: Quaternion) -> Quaternion {
+    [
+        aw * bw - ax * bx - ay * by - az * bz,
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+    ]
+}
+
+/// Snap rounding errors from quarter turns back to the values they should be.
+fn tidy(value: f64) -> f64 {
+    [0.0, 0.5, std::f64::consts::FRAC_1_SQRT_2, 1.0].into_iter()
+        .flat_map(|v| [v, -v])
+        .find(|v| (value - v).abs() < 1e-9)
+        .map(|v| v + 0.0)
+        .unwrap_or(value)
+}
+
+/// A mirror, quarter turns about the origin and a move along the grid, in that order.
+#[derive(Debug, Clone)]
+pub struct Transform {
+    /// where the x, y and z unit vectors end up
+    columns: [[i64; 3]; 3],
+    /// the turns alone, to apply to orientations
+    rotation: Quaternion,
+    mirror: Option<usize>,
+    /// in grid units
+    offset: [i64; 3],
+}
+
+impl Transform {
+    pub fn new(mirror: Option<usize>, turns: &[(usize, i64)], offset: [i64; 3]) -> Transform {
+        let mut columns = [[1, 0, 0], [0, 1, 0], [0, 0, 1]];
+        if let Some(axis) = mirror {
+            columns[axis][axis] = -1;
+        }
+        let mut rotation = [1.0, 0.0, 0.0, 0.0];
+        for &(axis, quarters) in turns {
+            let half = std::f64::consts::FRAC_PI_4;
+            let mut quarter = [half.cos(), 0.0, 0.0, 0.0];
+            quarter[axis + 1] = half.sin();
+            for _ in 0..quarters {
+                // counter-clockwise, looking from the positive end of the axis
+                columns = columns.map(|[x, y, z]| match axis {
+                    0 => [x, -z, y],
+                    1 => [z, y, -x],
+                    _ => [-y, x, z],
+                });
+                rotation = multiply(quarter, rotation);
+            }
+        }
+        Transform { columns, rotation: rotation.map(tidy), mirror, offset }
+    }
+
+    pub fn translation(offset: [i64; 3]) -> Transform {
+        Transform::new(None, &[], offset)
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.columns == [[1, 0, 0], [0, 1, 0], [0, 0, 1]] && self.offset == [0; 3]
+    }
+
+    fn linear(&self, v: [i64; 3]) -> [i64; 3] {
+        [0, 1, 2].map(|k| (0..3).map(|i| v[i] * self.columns[i][k]).sum())
+    }
+
+    /// A point in meters.
+    fn point(&self, p: Point) -> Point {
+        [0, 1, 2].map(|k| (0..3).map(|i| p[i] * self.columns[i][k] as f64).sum::<f64>() + self.offset[k] as f64 * GRID)
+    }
+
+    /// A box of grid cells, as its lowest corner and size.
+    fn cells(&self, min: [i64; 3], size: [i64; 3]) -> ([i64; 3], [i64; 3]) {
+        let (a, b) = (self.linear(min), self.linear([0, 1, 2].map(|i| min[i] + size[i])));
+        ([0, 1, 2].map(|i| a[i].min(b[i]) + self.offset[i]), [0, 1, 2].map(|i| (a[i] - b[i]).abs()))
+    }
+
+    /// Which axis `axis` ends up along.
+    fn axis(&self, axis: usize) -> usize {
+        self.columns[axis].iter().position(|c| *c != 0).unwrap_or(axis)
+    }
+
+    /// A segment's `dir`, if it is known.
+    fn direction(&self, dir: i64) -> Option<i64> {
+        let turned = self.linear(direction(dir)?.map(|c| c as i64));
+        (0..6).find(|d| direction(*d).map(|v| v.map(|c| c as i64)) == Some(turned))
+    }
+
+    /// Mirrored orientations turn the other way around the mirrored axes. Components can't be
+    /// mirrored themselves, so they end up turned to match instead.
+    fn orientation(&self, [w, x, y, z]: Quaternion) -> Quaternion {
+        let mut q = [w, x, y, z];
+        if let Some(axis) = self.mirror {
+            (1..4).filter(|i| *i != axis + 1).for_each(|i| q[i] = -q[i]);
+        }
+        multiply(self.rotation, q).map(tidy)
+    }
+
+    /// Move everything in the blueprint: blocks and their frames, components with their
+    /// orientations and occupancies, pipe segments, the bounding box and the symmetry axis.
+    pub fn apply(&self, raw: &mut RawBlueprint) {
+        for block in raw.blocks_mut().into_iter().flatten() {
+            self.move_cells(block);
+        }
+        for component in raw.components_mut().into_iter().flatten() {
+            if let Some(position) = component.get_mut("position") {
+                set_point(position, &XYZ, self.point(point(position, &XYZ)));
+            }
+            if let Some(orientation) = component.get_mut("orientation") {
+                let keys = ["w", "x", "y", "z"];
+                let q = keys.map(|k| orientation.get(k).and_then(Value::as_f64).unwrap_or_default());
+                let turned = self.orientation(q);
+                for (key, value) in keys.iter().zip(turned) {
+                    orientation[*key] = Value::from(value);
+                }
+            }
+            let occupancies = component.get_mut("occupancies").and_then(Value::as_array_mut);
+            for occupancy in occupancies.into_iter().flatten() {
+                self.move_cells(occupancy);
+            }
+        }
+        for pipe in raw.pipes_mut().into_iter().flatten() {
+            let segments = pipe.get_mut("segments").and_then(Value::as_array_mut);
+            for segment in segments.into_iter().flatten() {
+                if let Some(start) = segment.get_mut("start") {
+                    set_point(start, &XYZ, self.point(point(start, &XYZ)));
+                }
+                let dir = segment.get("dir").and_then(Value::as_i64);
+                if let Some(dir) = dir.and_then(|d| self.direction(d)) {
+                    segment["dir"] = Value::from(dir);
+                }
+            }
+        }
+
+        let corners = ["box_min", "box_max"].map(|key| raw.entry_mut(key).map(|v| self.point(point(v, &XYZ))));
+        if let [Some(a), Some(b)] = corners {
+            raw.set_bounds([0, 1, 2].map(|i| a[i].min(b[i])), [0, 1, 2].map(|i| a[i].max(b[i])));
+        }
+        if let Some(symmetry) = raw.data_entry_mut("symmetry_axis") {
+            if let Some(axis) = symmetry.as_i64().and_then(symmetry_axis) {
+                *symmetry = Value::from(self.axis(axis) as i64 + 1);
+            }
+        }
+    }
+
+    fn move_cells(&self, value: &mut Value) {
+        let (min, size) = self.cells(triple(value, &POS), triple(value, &SIZE));
+        set_triple(value, &POS, min);
+        set_triple(value, &SIZE, size);
+        if FRAME.iter().all(|k| value.get(k).is_some()) {
+            let frame = self.linear(triple(value, &FRAME));
+            set_triple(value, &FRAME, frame);
+        }
+    }
+}
+
+/// The axis a blueprint's `symmetry_axis` stands for: 1, 2 and 3 are x, y and z, 0 is none.
+pub fn symmetry_axis(value: i64) -> Option<usize> {
+    (1..=3).contains(&value).then(|| value as usize - 1)
+}
+
+fn triple(value: &Value, keys: &[&str; 3]) -> [i64; 3] {
+    keys.map(|k| value.get(k).and_then(Value::as_i64).unwrap_or_default())
+}
+
+fn set_triple(value: &mut Value, keys: &[&str; 3], v: [i64; 3]) {
+    for (key, v) in keys.iter().zip(v) {
+        value[*key] = Value::from(v);
+    }
+}
+
+fn point(value: &Value, keys: &[&str; 3]) -> Point {
+    keys.map(|k| value.get(k).and_then(Value::as_f64).unwrap_or_default())
+}
+
+fn set_point(value: &mut Value, keys: &[&str; 3], p: Point) {
+    for (key, v) in keys.iter().zip(p) {
+        // `+ 0.0` turns -0.0 into 0.0
+        value[*key] = Value::from(v + 0.0);
+    }
+}
+
+pub fn transform_blueprint(args: &ArgMatches) {
+    let config = match TransformConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(e) if e.kind() == ErrorKind::InvalidValue => {
+            eprint!("{}", e);
+            std::process::exit(1);
+        }
+        Err(_) => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let source = match command::get_blueprint_object(config.file_name.clone()) {
+        Ok(source) => source,
+        Err(_) => {
+            eprintln!("🚨 Blueprint could not be found or opened! 🚨");
+            std::process::exit(1);
+        }
+    };
+    let (blueprint, mut raw) = match (serde_json::from_str::<Blueprint>(&source), RawBlueprint::parse(&source)) {
+        (Ok(blueprint), Ok(raw)) => (blueprint, raw),
+        _ => {
+            eprintln!("🚨 Blueprint could not be parsed! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let mirror = match config.mirror {
+        Some(Mirror::Symmetry) => match symmetry_axis(blueprint.data.symmetry_axis) {
+            Some(axis) => Some(axis),
+            None => {
+                eprintln!("🚨 Blueprint has no symmetry axis; pass --mirror x, y or z 🚨");
+                std::process::exit(1);
+            }
+        },
+        Some(Mirror::Axis(axis)) => Some(axis),
+        None => None,
+    };
+    let transform = Transform::new(mirror, &config.turns, config.offset);
+    if transform.is_identity() {
+        println!("Nothing to do; pass --translate, --rotate or --mirror.");
+        return;
+    }
+    transform.apply(&mut raw);
+
+    let data = &blueprint.data;
+    for (count, what) in [(data.doors.len(), "door(s)"), (data.labels.len(), "label(s)"), (data.frames.len(), "frame(s)")] {
+        if count > 0 {
+            eprintln!("Warning: {} {} were left where they were", count, what);
+        }
+    }
+    let Ok(moved) = serde_json::from_str::<Blueprint>(&raw.to_json()) else {
+        eprintln!("🚨 Transformed blueprint could not be parsed; nothing was written 🚨");
+        std::process::exit(1);
+    };
+    println!("Moved {} block(s), {} component(s) and {} pipe(s)", data.blocks.len(), data.components.len(), data.pipes.len());
+    println!("Bounding box is now ({}, {}, {}) to ({}, {}, {})",
+             moved.box_min.x, moved.box_min.y, moved.box_min.z, moved.box_max.x, moved.box_max.y, moved.box_max.z);
+
+    if config.dry_run {
+        println!("Dry run; blueprint not modified.");
+        return;
+    }
+    let path = config.out.unwrap_or(config.file_name);
+    if let Err(e) = std::fs::write(&path, raw.to_json()) {
+        eprintln!("Error saving {:?}: {:?}", path, e);
+        std::process::exit(1);
+    }
+    println!("Wrote {:?}", path);
+}
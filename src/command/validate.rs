@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use clap::{ArgMatches, Error, FromArgMatches};
+use crate::archean::geometry::{segment_end, segment_start, Point, GRID};
+use crate::archean::json::{Blueprint, Component, Occupancy, Pipe};
+use crate::command;
+use crate::command::graph::Graph;
+
+/// How far apart two points may be and still count as joined, in meters.
+const TOLERANCE: f64 = 0.01;
+
+pub struct ValidateConfig {
+    pub(crate) file_name: PathBuf,
+    /// modules that should have at least one pipe, any case
+    pub(crate) kinds: Vec<String>
+}
+
+impl FromArgMatches for ValidateConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        Ok(ValidateConfig {
+            file_name: command::extract_filename("BLUEPRINT".to_string(), matches)?,
+            kinds: matches.get_many::<String>("kind")
+                .map(|kinds| kinds.map(|k| k.to_lowercase()).collect())
+                .unwrap_or_default()
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = ValidateConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+fn format_point(p: Point) -> String {
+    format!("({}, {}, {})", p[0], p[1], p[2])
+}
+
+fn position(component: &Component) -> Point {
+    [component.position.x, component.position.y, component.position.z]
+}
+
+/// How far from its position a port of `component` can be: the diagonal of the cells it occupies,
+/// plus a cell for the pipe's end.
+fn reach(component: &Component) -> f64 {
+    let extent = |pos: fn(&Occupancy) -> i64, size: fn(&Occupancy) -> i64| {
+        let min = component.occupancies.iter().map(pos).min().unwrap_or(0);
+        let max = component.occupancies.iter().map(|o| pos(o) + size(o)).max().unwrap_or(0);
+        (max - min) as f64 * GRID
+    };
+    let x = extent(|o| o.pos_x, |o| o.size_x);
+    let y = extent(|o| o.pos_y, |o| o.size_y);
+    let z = extent(|o| o.pos_z, |o| o.size_z);
+    (x * x + y * y + z * z).sqrt() + GRID
+}
+
+fn describe(index: usize, component: &Component) -> String {
+    format!("{} #{} at {}", component.name(), index, format_point(position(component)))
+}
+
+/// Problems with how a pipe's segments are laid out; `ends` are the components it connects, if
+/// they exist.
+fn check_segments(index: usize, pipe: &Pipe, ends: [Option<(usize, &Component)>; 2]) -> Vec<String> {
+    let mut problems = vec![];
+    let Some(first) = pipe.segments.first() else {
+        return problems;
+    };
+
+    let mut end = segment_start(first);
+    for (i, segment) in pipe.segments.iter().enumerate() {
+        let start = segment_start(segment);
+        if i > 0 && distance(start, end) > TOLERANCE {
+            problems.push(format!("pipe #{} ({}): segment {} starts at {}, but segment {} ends at {}",
+                                  index, pipe.type_field, i, format_point(start), i - 1, format_point(end)));
+        }
+        match segment_end(segment) {
+            Some(segment_end) => end = segment_end,
+            None => {
+                problems.push(format!("pipe #{} ({}): segment {} has an unknown direction {}", index, pipe.type_field, i, segment.dir));
+                return problems;
+            }
+        }
+    }
+
+    // pipes may be laid out from either end
+    let start = segment_start(first);
+    let joins = |point: Point, end: Option<(usize, &Component)>| {
+        end.is_none_or(|(_, c)| distance(point, position(c)) <= reach(c))
+    };
+    let forward = joins(start, ends[0]) && joins(end, ends[1]);
+    let backward = joins(start, ends[1]) && joins(end, ends[0]);
+    if !forward && !backward {
+        let name = |end: Option<(usize, &Component)>| end.map(|(i, c)| describe(i, c)).unwrap_or_default();
+        problems.push(format!("pipe #{} ({}): runs from {} to {}, which does not join {} and {}",
+                              index, pipe.type_field, format_point(start), format_point(end), name(ends[0]), name(ends[1])));
+    }
+    problems
+}
+
+/// Check the pipes of a blueprint: that both ends exist, that no port has two pipes, that the
+/// segments actually join the two components, and that components of `kinds` have any pipes.
+pub fn check_pipes(blueprint: &Blueprint, kinds: &[String]) -> Vec<String> {
+    let components = &blueprint.data.components;
+    let component = |index: i64| usize::try_from(index).ok().and_then(|i| components.get(i).map(|c| (i, c)));
+    let mut problems = vec![];
+
+    let mut ports: BTreeMap<(usize, &str), Vec<usize>> = BTreeMap::new();
+    for (index, pipe) in blueprint.data.pipes.iter().enumerate() {
+        let ends = [component(pipe.a_component), component(pipe.b_component)];
+        for (end, (side, wanted)) in ends.iter().zip([("a", pipe.a_component), ("b", pipe.b_component)]) {
+            if end.is_none() {
+                let other = ends.iter().flatten().next().map(|(i, c)| format!(" (other end: {})", describe(*i, c))).unwrap_or_default();
+                problems.push(format!("pipe #{} ({}): {}_component {} does not exist{}", index, pipe.type_field, side, wanted, other));
+            }
+        }
+        for (end, port) in ends.iter().zip([&pipe.a_port, &pipe.b_port]) {
+            if let Some((i, _)) = end {
+                ports.entry((*i, port.as_str())).or_default().push(index);
+            }
+        }
+        problems.extend(check_segments(index, pipe, ends));
+    }
+
+    for ((index, port), pipes) in ports.iter().filter(|(_, pipes)| pipes.len() > 1) {
+        let pipes: Vec<String> = pipes.iter().map(|p| format!("#{}", p)).collect();
+        problems.push(format!("{}: port {:?} is used by pipes {}", describe(*index, &components[*index]), port, pipes.join(", ")));
+    }
+
+    let graph = Graph::new(blueprint, |_| true);
+    for (index, c) in components.iter().enumerate() {
+        if kinds.contains(&c.module.to_lowercase()) && !graph.is_connected(index) {
+            problems.push(format!("{}: {} has no pipes", describe(index, c), c.module));
+        }
+    }
+    problems
+}
+
+pub fn validate_blueprint(args: &ArgMatches) {
+    let config = match ValidateConfig::from_arg_matches(args) {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("🚨 Blueprint not found! 🚨");
+            std::process::exit(1);
+        }
+    };
+
+    let blueprint = command::get_blueprint_object(config.file_name.clone()).ok()
+        .and_then(|source| serde_json::from_str::<Blueprint>(&source).ok());
+    let Some(blueprint) = blueprint else {
+        eprintln!("🚨 Blueprint could not be parsed! 🚨");
+        std::process::exit(1);
+    };
+
+    let problems = check_pipes(&blueprint, &config.kinds);
+    if problems.is_empty() {
+        println!("✔ {} pipe(s) checked, no problems found", blueprint.data.pipes.len());
+    } else {
+        problems.iter().for_each(|p| eprintln!("✘ {}", p));
+        eprintln!("🚨 {} problem(s) found 🚨", problems.len());
+        std::process::exit(1);
+    }
+}
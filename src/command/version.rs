@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+use clap::{ArgMatches, Error, FromArgMatches};
+use semver::Version;
+use serde_json::{Map, Value};
+use crate::command::archive::METADATA_FILE;
+use crate::command::package::{BlueprintMetadata, INITIAL_VERSION};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Part {
+    Major,
+    Minor,
+    Patch,
+}
+
+pub enum VersionAction {
+    Show,
+    Bump(Part),
+    Set(Version),
+}
+
+pub struct VersionConfig {
+    pub(crate) action: VersionAction,
+    /// the project manifest
+    pub(crate) manifest: PathBuf
+}
+
+impl FromArgMatches for VersionConfig {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        let action = match matches.subcommand() {
+            Some(("bump", args)) => VersionAction::Bump(match args.get_one::<String>("PART").map(String::as_str) {
+                Some("major") => Part::Major,
+                Some("minor") => Part::Minor,
+                _ => Part::Patch,
+            }),
+            Some(("set", args)) => match args.get_one::<Version>("VERSION") {
+                Some(version) => VersionAction::Set(version.clone()),
+                None => VersionAction::Show,
+            },
+            _ => VersionAction::Show,
+        };
+
+        Ok(VersionConfig {
+            action,
+            manifest: PathBuf::from(METADATA_FILE)
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
+        *self = VersionConfig::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+pub fn bump(version: &Version, part: Part) -> Version {
+    // pre-release and build metadata are dropped
+    match part {
+        Part::Major => Version::new(version.major + 1, 0, 0),
+        Part::Minor => Version::new(version.major, version.minor + 1, 0),
+        Part::Patch => Version::new(version.major, version.minor, version.patch + 1),
+    }
+}
+
+/// Whether the manifest's `version` is a blueprint format version rather than a package version.
+fn has_format_version(manifest: &Map<String, Value>) -> bool {
+    serde_json::from_value::<BlueprintMetadata>(Value::Object(manifest.clone()))
+        .is_ok_and(|metadata| metadata.has_format_version(None))
+}
+
+pub fn package_version(args: &ArgMatches) {
+    let config = VersionConfig::from_arg_matches(args).unwrap();
+
+    // edited as plain JSON so fields and their order are kept as written
+    let mut manifest = match std::fs::read_to_string(&config.manifest) {
+        Ok(manifest) => match serde_json::from_str::<Map<String, Value>>(&manifest) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                eprintln!("🚨 {:?} could not be parsed: {} 🚨", config.manifest, e);
+                std::process::exit(1);
+            }
+        },
+        // `set` starts a project manifest, the rest need one
+        Err(_) if matches!(config.action, VersionAction::Set(_)) => Map::new(),
+        Err(_) => {
+            eprintln!("🚨 No {} here; create one with `archbelt version set <VERSION>` or `archbelt package` 🚨", METADATA_FILE);
+            std::process::exit(1);
+        }
+    };
+    let current = manifest.get("version").and_then(Value::as_str).unwrap_or_default().to_string();
+
+    let next = match config.action {
+        VersionAction::Show => {
+            println!("{}", current);
+            return;
+        }
+        VersionAction::Set(version) => version,
+        VersionAction::Bump(part) => match Version::parse(&current) {
+            Ok(version) => bump(&version, part),
+            Err(_) if has_format_version(&manifest) => {
+                println!("Note: version {:?} is the blueprint format version older releases wrote; starting at {}", current, INITIAL_VERSION);
+                Version::parse(INITIAL_VERSION).unwrap()
+            }
+            Err(_) => {
+                eprintln!("🚨 {:?} is not a semver version; use `archbelt version set <VERSION>` first 🚨", current);
+                std::process::exit(1);
+            }
+        },
+    };
+
+    manifest.insert("version".to_string(), Value::String(next.to_string()));
+    if let Err(e) = std::fs::write(&config.manifest, serde_json::to_string_pretty(&manifest).unwrap()) {
+        eprintln!("Error saving {:?}: {:?}", config.manifest, e);
+        std::process::exit(1);
+    }
+    if current.is_empty() {
+        println!("Version set to {}", next);
+    } else {
+        println!("{} → {}", current, next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bumping_resets_the_parts_after_it() {
+        let version = Version::parse("1.2.3-beta.1+build.5").unwrap();
+        assert_eq!(bump(&version, Part::Major).to_string(), "2.0.0");
+        assert_eq!(bump(&version, Part::Minor).to_string(), "1.3.0");
+        assert_eq!(bump(&version, Part::Patch).to_string(), "1.2.4");
+    }
+
+    fn manifest(json: &str) -> Map<String, Value> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn format_versions_written_by_older_releases_are_recognised() {
+        assert!(has_format_version(&manifest(r#"{ "version": "3" }"#)));
+        assert!(has_format_version(&manifest(r#"{ "version": "3", "format_version": 3 }"#)));
+        assert!(!has_format_version(&manifest(r#"{ "version": "2", "format_version": 3 }"#)));
+        assert!(!has_format_version(&manifest(r#"{ "version": "1.0" }"#)));
+        assert!(!has_format_version(&manifest(r#"{ "version": "1.0.0" }"#)));
+    }
+}
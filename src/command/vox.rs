@@ -0,0 +1,154 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::archean::geometry::{GridBox, Rgba};
+
+/// Largest model MagicaVoxel opens, per axis; bigger builds are split into several models.
+const MAX_MODEL_SIZE: i64 = 256;
+/// Colors a palette holds; index 0 means empty.
+const PALETTE_SIZE: usize = 255;
+
+/// A palette entry: a block color and material, or a component color.
+type Swatch = (Rgba, Option<i64>);
+
+fn chunk(id: &[u8; 4], content: &[u8], children: &[u8]) -> Vec<u8> {
+    let mut out = id.to_vec();
+    out.extend((content.len() as u32).to_le_bytes());
+    out.extend((children.len() as u32).to_le_bytes());
+    out.extend(content);
+    out.extend(children);
+    out
+}
+
+fn int(out: &mut Vec<u8>, value: i64) {
+    out.extend((value as i32).to_le_bytes());
+}
+
+fn dict(out: &mut Vec<u8>, entries: &[(&str, String)]) {
+    int(out, entries.len() as i64);
+    for (key, value) in entries {
+        for text in [*key, value.as_str()] {
+            int(out, text.len() as i64);
+            out.extend(text.as_bytes());
+        }
+    }
+}
+
+fn distance(a: Rgba, b: Rgba) -> i64 {
+    (0..4).map(|i| (a.0[i] as i64 - b.0[i] as i64).pow(2)).sum()
+}
+
+/// Pick up to [`PALETTE_SIZE`] swatches, the most used first, and map every swatch to a palette
+/// index; swatches that don't fit get the closest color.
+fn palette(voxels: &HashMap<[i64; 3], Swatch>) -> (Vec<Swatch>, HashMap<Swatch, u8>) {
+    let mut counts: BTreeMap<Swatch, usize> = BTreeMap::new();
+    for swatch in voxels.values() {
+        *counts.entry(*swatch).or_default() += 1;
+    }
+    let mut swatches: Vec<(Swatch, usize)> = counts.into_iter().collect();
+    swatches.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+
+    let palette: Vec<Swatch> = swatches.iter().take(PALETTE_SIZE).map(|(s, _)| *s).collect();
+    let index = swatches.iter().map(|(swatch, _)| {
+        let closest = palette.iter().position(|p| p == swatch).unwrap_or_else(|| {
+            (0..palette.len()).min_by_key(|&i| distance(palette[i].0, swatch.0)).unwrap_or(0)
+        });
+        (*swatch, closest as u8 + 1)
+    }).collect();
+    (palette, index)
+}
+
+/// A MagicaVoxel `.vox` file of the cells `boxes` fill, later boxes drawn over earlier ones.
+///
+/// The grid is turned so +z is up, as in MagicaVoxel. Transparent colors get a glass material.
+pub fn vox(boxes: &[GridBox]) -> Vec<u8> {
+    let mut voxels: HashMap<[i64; 3], Swatch> = HashMap::new();
+    for grid_box in boxes {
+        let max = grid_box.max();
+        for x in grid_box.min[0]..max[0] {
+            for y in grid_box.min[1]..max[1] {
+                for z in grid_box.min[2]..max[2] {
+                    voxels.insert([x, -z, y], (grid_box.color, grid_box.material));
+                }
+            }
+        }
+    }
+    let (palette, index) = palette(&voxels);
+
+    let min = [0, 1, 2].map(|i| voxels.keys().map(|v| v[i]).min().unwrap_or(0));
+    let max = [0, 1, 2].map(|i| voxels.keys().map(|v| v[i] + 1).max().unwrap_or(1));
+    let mut models: BTreeMap<[i64; 3], Vec<([i64; 3], u8)>> = BTreeMap::new();
+    for (voxel, swatch) in &voxels {
+        let offset = [0, 1, 2].map(|i| voxel[i] - min[i]);
+        let tile = offset.map(|v| v / MAX_MODEL_SIZE);
+        models.entry(tile).or_default().push((offset.map(|v| v % MAX_MODEL_SIZE), index[swatch]));
+    }
+
+    let mut children: Vec<u8> = vec![];
+    for (tile, cells) in &models {
+        let size = [0, 1, 2].map(|i| (max[i] - min[i] - tile[i] * MAX_MODEL_SIZE).min(MAX_MODEL_SIZE));
+        let mut content = vec![];
+        size.iter().for_each(|s| int(&mut content, *s));
+        children.extend(chunk(b"SIZE", &content, &[]));
+
+        let mut content = vec![];
+        int(&mut content, cells.len() as i64);
+        for ([x, y, z], color) in cells {
+            content.extend([*x as u8, *y as u8, *z as u8, *color]);
+        }
+        children.extend(chunk(b"XYZI", &content, &[]));
+    }
+
+    // scene: a transform over a group, holding a transform and shape per model
+    let mut scene = vec![];
+    let transform = |node: i64, child: i64, translation: Option<String>| {
+        let mut content = vec![];
+        int(&mut content, node);
+        dict(&mut content, &[]);
+        int(&mut content, child);
+        int(&mut content, -1);
+        int(&mut content, -1);
+        int(&mut content, 1);
+        dict(&mut content, &translation.map(|t| vec![("_t", t)]).unwrap_or_default());
+        chunk(b"nTRN", &content, &[])
+    };
+    scene.extend(transform(0, 1, None));
+    let mut group = vec![];
+    int(&mut group, 1);
+    dict(&mut group, &[]);
+    int(&mut group, models.len() as i64);
+    for i in 0..models.len() as i64 {
+        int(&mut group, 2 + i * 2);
+    }
+    scene.extend(chunk(b"nGRP", &group, &[]));
+    for (i, tile) in models.keys().enumerate() {
+        let i = i as i64;
+        let size = [0, 1, 2].map(|k| (max[k] - min[k] - tile[k] * MAX_MODEL_SIZE).min(MAX_MODEL_SIZE));
+        // models are placed by their center
+        let center = [0, 1, 2].map(|k| min[k] + tile[k] * MAX_MODEL_SIZE + size[k] / 2);
+        scene.extend(transform(2 + i * 2, 3 + i * 2, Some(format!("{} {} {}", center[0], center[1], center[2]))));
+        let mut shape = vec![];
+        int(&mut shape, 3 + i * 2);
+        dict(&mut shape, &[]);
+        int(&mut shape, 1);
+        int(&mut shape, i);
+        dict(&mut shape, &[]);
+        scene.extend(chunk(b"nSHP", &shape, &[]));
+    }
+    children.extend(scene);
+
+    let mut rgba = vec![0u8; 256 * 4];
+    for (i, (color, _)) in palette.iter().enumerate() {
+        rgba[i * 4..i * 4 + 4].copy_from_slice(&color.0);
+    }
+    children.extend(chunk(b"RGBA", &rgba, &[]));
+    for (i, (color, _)) in palette.iter().enumerate().filter(|(_, (c, _))| c.0[3] < 255) {
+        let mut content = vec![];
+        int(&mut content, i as i64 + 1);
+        dict(&mut content, &[("_type", "_glass".to_string()), ("_trans", format!("{:.3}", 1.0 - color.0[3] as f32 / 255.0))]);
+        children.extend(chunk(b"MATL", &content, &[]));
+    }
+
+    let mut out = b"VOX ".to_vec();
+    int(&mut out, 150);
+    out.extend(chunk(b"MAIN", &[], &children));
+    out
+}
@@ -5,13 +5,22 @@ use notify::{Watcher, RecursiveMode, Result as NotifyResult};
 use notify_debouncer_full::{DebouncedEvent, new_debouncer};
 use crate::command;
 use crate::command::get_blueprints_path;
+use crate::command::yank::{output_options, Layout};
 use super::prelude::*;
 
 pub fn watch_blueprints(matches: &ArgMatches) {
+    let (out, layout) = match output_options(matches) {
+        Ok(options) => options,
+        Err(e) => {
+            eprint!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let dedup = !*matches.get_one::<bool>("no-dedup").unwrap_or(&false);
     let file_name = command::extract_filename("TARGET".to_string(), matches);
     match file_name {
         Ok(file_path) => {
-            watch_event(&file_path).expect("Could not watch blueprint");
+            watch_event(&file_path, &out, &layout, dedup).expect("Could not watch blueprint");
             return
         }
         Err(_) => {
@@ -19,32 +28,35 @@ pub fn watch_blueprints(matches: &ArgMatches) {
         }
     }
     let archean_path = get_blueprints_path(matches).expect("Could not get Archean blueprints path");
-    watch_event(&archean_path).expect("Could not watch Archean blueprints path; is Archean installed via Steam?");
+    watch_event(&archean_path, &out, &layout, dedup).expect("Could not watch Archean blueprints path; is Archean installed via Steam?");
 }
 
-pub fn watch_event<P: AsRef<Path>>(path: P) -> NotifyResult<()> {
+/// Yank every blueprint below `path` into its own folder in `out` whenever it changes.
+pub fn watch_event<P: AsRef<Path>>(path: P, out: &Path, layout: &Layout, dedup: bool) -> NotifyResult<()> {
     let (tx, rx) = std::sync::mpsc::channel();
     let mut debouncer = new_debouncer(Duration::from_secs(2), None, tx)?;
     debouncer.watcher().watch(path.as_ref(), RecursiveMode::Recursive)?;
     println!("waiting for blueprint events");
     for result in rx {
         match result {
-            Ok(events) => events.iter().for_each(|event| handle_event(event)),
+            Ok(events) => events.iter().for_each(|event| handle_event(event, out, layout, dedup)),
             Err(errors) => errors.iter().for_each(|error| println!("{error:?}")),
         }
     }
     Ok(())
 }
 
-fn handle_event(event: &DebouncedEvent) {
+fn handle_event(event: &DebouncedEvent, out: &Path, layout: &Layout, dedup: bool) {
     if event.kind.is_modify() {
-        let blueprint_name = event.paths.get(0).unwrap();
+        let blueprint_name = event.paths.first().unwrap();
         println!("Blueprint file changed: {:?}, yanking..", blueprint_name);
         yank_from_config(YankConfig{
             file_name: blueprint_name.to_path_buf(),
+            out: out.to_path_buf(),
             folder: true,
             watch: WatchState::Watching, // we are already watching from the yank context
-            disable_collate: false
+            layout: layout.clone(),
+            dedup
         });
     }
 }
\ No newline at end of file
@@ -1,25 +1,86 @@
 use std::path::{Path, PathBuf};
 use clap::{ArgMatches, Error, FromArgMatches};
+use clap::error::ErrorKind;
 use std::fs;
 use std::io::Write;
 use crate::archean::json::{Blueprint, XcFileMeta};
 use crate::command;
+use crate::command::manifest::{FileTarget, ManifestEntry, SHARED_FOLDER, YankManifest};
+use crate::command::sanitize;
 use crate::command::prelude::WatchState;
 use crate::command::watch::watch_event;
 
+/// Where each yanked file goes, relative to the output folder, e.g. `{component}/{file}`.
+///
+/// `{blueprint}` is the blueprint's name, `{component}` the component's (or the shared folder for
+/// deduplicated files) and `{file}` the file's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout(String);
+
+impl Layout {
+    pub const PLACEHOLDERS: [&'static str; 3] = ["{blueprint}", "{component}", "{file}"];
+
+    pub fn parse(template: &str) -> Result<Layout, String> {
+        let mut rest = template.to_string();
+        for placeholder in Self::PLACEHOLDERS {
+            rest = rest.replace(placeholder, "");
+        }
+        if !template.contains("{file}") {
+            return Err(format!("layout {:?} has no {{file}}", template));
+        }
+        if rest.contains(['{', '}']) {
+            return Err(format!("layout {:?} has an unknown placeholder; use {}", template, Self::PLACEHOLDERS.join(", ")));
+        }
+        let escapes = Path::new(template).components().any(|c| !matches!(c, std::path::Component::Normal(_)));
+        if template.starts_with(['/', '\\']) || escapes {
+            return Err(format!("layout {:?} must stay inside the output folder", template));
+        }
+        Ok(Layout(template.to_string()))
+    }
+
+    /// `{component}/{file}`, or `{file}` without collating.
+    pub fn collated(collate: bool) -> Layout {
+        Layout(if collate { "{component}/{file}" } else { "{file}" }.to_string())
+    }
+
+    /// Whether components get their own folders, so identical files can be shared between them.
+    pub fn separates_components(&self) -> bool {
+        self.0.contains("{component}")
+    }
+
+    pub fn path(&self, blueprint: &str, component: &str, file: &str) -> String {
+        self.0.replace("{blueprint}", blueprint).replace("{component}", component).replace("{file}", file)
+    }
+}
+
 pub struct YankConfig {
     pub(crate) file_name: PathBuf,
+    /// folder to write into, relative paths being resolved against it rather than the cwd
+    pub(crate) out: PathBuf,
     pub(crate) folder: bool,
     pub(crate) watch: WatchState,
-    pub(crate) disable_collate: bool
+    pub(crate) layout: Layout,
+    pub(crate) dedup: bool
+}
+
+/// `--out` and `--layout` (or `--no-collate`), shared by every command that yanks.
+pub(crate) fn output_options(matches: &ArgMatches) -> Result<(PathBuf, Layout), Error> {
+    let out = matches.try_get_one::<String>("out").ok().flatten().map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."));
+    let collate_switch = matches.try_get_one::<bool>("no-collate").ok().flatten().unwrap_or(&false);
+    let layout = match matches.try_get_one::<String>("layout").ok().flatten() {
+        Some(template) => Layout::parse(template).map_err(|e| Error::raw(ErrorKind::InvalidValue, format!("{}\n", e)))?,
+        None => Layout::collated(!*collate_switch),
+    };
+    Ok((out, layout))
 }
 
 impl FromArgMatches for YankConfig {
     fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
         let file_name = command::extract_filename("BLUEPRINT".to_string(), matches)?;
+        let (out, layout) = output_options(matches)?;
         let folder_switch = matches.get_one::<bool>("folder").unwrap_or(&false);
         let watch_switch = matches.get_one::<bool>("watch").unwrap_or(&false);
-        let collate_switch = matches.get_one::<bool>("no-collate").unwrap_or(&false);
+        let dedup_switch = matches.get_one::<bool>("no-dedup").unwrap_or(&false);
 
         let watch_state = if *watch_switch {
             WatchState::Requested
@@ -29,25 +90,27 @@ impl FromArgMatches for YankConfig {
 
         Ok(YankConfig {
             file_name,
+            out,
             folder: *folder_switch,
             watch: watch_state,
-            disable_collate: *collate_switch
+            layout,
+            dedup: !*dedup_switch
         })
     }
 
     fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), Error> {
         let file_name = command::extract_filename("BLUEPRINT".to_string(), matches)?;
+        let (out, layout) = output_options(matches)?;
         let folder_switch = matches.get_one::<bool>("folder").unwrap_or(&false);
         let watch_switch = matches.get_one::<bool>("watch").unwrap_or(&false);
 
         self.file_name = file_name;
+        self.out = out;
+        self.layout = layout;
         self.folder = *folder_switch;
-        match self.watch {
-            _ => {
-                if !watch_switch {
-                    self.watch = WatchState::Disabled;
-                }
-            }
+        self.dedup = !*matches.get_one::<bool>("no-dedup").unwrap_or(&false);
+        if !watch_switch {
+            self.watch = WatchState::Disabled;
         }
 
         Ok(())
@@ -60,14 +123,17 @@ pub fn yank_xenon_code(args: &ArgMatches) {
         Ok(config) => {
             match config.watch {
                 WatchState::Requested | WatchState::Watching => {
-                    watch_event(config.file_name.clone()).expect("Could not watch blueprint path");
-                    return
+                    watch_event(config.file_name.clone(), &config.out, &config.layout, config.dedup).expect("Could not watch blueprint path");
                 }
                 _ => {
                     yank_from_config(config);
                 }
             }
         }
+        Err(e) if e.kind() == ErrorKind::InvalidValue => {
+            eprint!("{}", e);
+            std::process::exit(1);
+        }
         Err(_) => {
             eprintln!("🚨 Blueprint not found! 🚨");
             std::process::exit(1);
@@ -83,13 +149,7 @@ pub fn yank_from_config(config: YankConfig) {
             let blueprint: Option<Blueprint> = serde_json::from_str(bp.as_str()).ok();
             match blueprint {
                 Some(blueprint) => {
-                    let mut files: Vec<XcFileMeta> = vec![];
-
-                    blueprint.components_with_hdd().iter().for_each(|c| {
-                        c.xc_files().iter().for_each(|f| {
-                            files.push(f.clone());
-                        });
-                    });
+                    let files: Vec<XcFileMeta> = blueprint.xc_files();
 
                     if files.is_empty() {
                         match config.watch {
@@ -104,43 +164,53 @@ pub fn yank_from_config(config: YankConfig) {
                         }
                     }
 
-                    let folder_name: String = config.file_name.clone()
-                        .file_stem()
-                        .expect("could not convert blueprint name to OS string")
-                        .to_str()
-                        .expect("could not convert blueprint name to native string")
-                        .to_string();
-                    let current_dir = std::env::current_dir().expect("could not detect current path");
-
-                    if config.folder {
-                        fs::create_dir_all(folder_name.clone()).expect("Unable to create folder");
-                        std::env::set_current_dir(folder_name.clone()).expect("Unable to set current directory");
-                    }
+                    let blueprint_name = config.file_name.file_stem().unwrap_or_default().to_string_lossy().to_string();
+                    let root = if config.folder {
+                        config.out.join(&blueprint_name)
+                    } else {
+                        config.out.clone()
+                    };
+
+                    let planned = match plan_files(&files, &blueprint_name, &config.layout, config.dedup) {
+                        Ok(planned) => planned,
+                        Err(e) => {
+                            eprintln!("🚨 Refusing to yank {:?}: {}; nothing was written 🚨", config.file_name, e);
+                            match config.watch {
+                                WatchState::Requested | WatchState::Watching => return,
+                                _ => std::process::exit(1),
+                            }
+                        }
+                    };
+                    fs::create_dir_all(&root).expect("Unable to create folder");
 
-                    // For each XcFile, create the file on disk and write the plain_code to it
-                    files.iter().for_each(|f| {
-                        let file_name: String;
-                        if !config.disable_collate { // chose this name to make this logic easier to read
-                            file_name = format!("{}/{}", f.component(), f.file_name());
-                        } else {
-                            file_name = f.file_name().to_string();
+                    // For each planned file, create the file on disk and write the plain_code to it
+                    for f in &planned {
+                        let path = root.join(&f.path);
+                        // a symlink in the output folder could still lead elsewhere
+                        if let Err(e) = create_folders(&root, Path::new(&f.path).parent().unwrap_or(Path::new(""))) {
+                            eprintln!("🚨 Not writing {:?}: {} 🚨", path, e);
+                            continue;
+                        }
+                        if fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_symlink()) {
+                            eprintln!("🚨 Not writing {:?}: it is a symlink 🚨", path);
+                            continue;
                         }
-                        // create the folder if it doesn't exist
-                        let folder = Path::new(&file_name).parent().expect(format!("could not create parent folder for {}", file_name.to_string()).as_str());
-                        fs::create_dir_all(folder).expect("Unable to create folder");
                         // save the file
                         let mut fh = fs::OpenOptions::new()
                             .create(true)
                             .write(true)
                             .truncate(true)
-                            .open(file_name.clone())
-                            .expect(format!("Unable to create file {}", file_name.clone()).as_str());
+                            .open(&path)
+                            .unwrap_or_else(|_| panic!("Unable to create file {:?}", path));
 
-                        fh.write(f.file_content().as_bytes()).expect(format!("Unable to write to file: {}", file_name.clone()).as_str());
-                    });
+                        fh.write_all(f.content.as_bytes()).unwrap_or_else(|_| panic!("Unable to write to file: {:?}", path));
+                    }
 
-                    // pop back to current_dir
-                    std::env::set_current_dir(current_dir).expect("Unable to set current directory");
+                    let manifest = YankManifest {
+                        blueprint: config.file_name.file_name().unwrap().to_string_lossy().to_string(),
+                        files: planned.into_iter().map(|f| ManifestEntry { path: f.path, targets: f.targets }).collect(),
+                    };
+                    manifest.save(&root).expect("Unable to save yank manifest");
                 }
                 _ => {
                     eprintln!("🚨 Unable to parse blueprint! Please open an issue at https://github.com/archean-cookbook/archbelt/issues and attach your blueprint .json. 🚨");
@@ -153,4 +223,116 @@ pub fn yank_from_config(config: YankConfig) {
             std::process::exit(1);
         }
     }
-}
\ No newline at end of file
+}
+
+/// Create the folders of `relative` below `root` one at a time, checking each before going into
+/// it, so a symlink can't lead the yank out of `root`.
+fn create_folders(root: &Path, relative: &Path) -> Result<(), String> {
+    let mut folder = root.to_path_buf();
+    for component in relative.components() {
+        folder.push(component);
+        if !folder.exists() {
+            fs::create_dir(&folder).map_err(|e| format!("could not create {:?}: {}", folder, e))?;
+        }
+        if !is_inside(root, &folder) {
+            return Err(format!("{:?} resolves to a folder outside {:?}", folder, root));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `folder`, with symlinks resolved, is `root` or below it.
+fn is_inside(root: &Path, folder: &Path) -> bool {
+    match (root.canonicalize(), folder.canonicalize()) {
+        (Ok(root), Ok(folder)) => folder.starts_with(root),
+        _ => false,
+    }
+}
+
+pub(crate) struct PlannedFile {
+    pub(crate) path: String,
+    pub(crate) content: String,
+    pub(crate) targets: Vec<FileTarget>,
+    /// written once for several components, into the shared folder
+    pub(crate) shared: bool,
+}
+
+/// Decide where each code file goes on disk.
+///
+/// With `dedup`, a file whose name and content appear on more than one component is written once
+/// into the shared folder, and every component it came from is recorded as a target.
+///
+/// Names from the blueprint are escaped so they are valid everywhere (see [`sanitize`]); a
+/// blueprint with a component or file name that would leave the output folder is refused.
+/// Components that share a name get their index added to their folder.
+pub(crate) fn plan_files(files: &[XcFileMeta], blueprint: &str, layout: &Layout, dedup: bool) -> Result<Vec<PlannedFile>, String> {
+    let collate = layout.separates_components();
+    let mut planned: Vec<PlannedFile> = vec![];
+    let blueprint = sanitize::escape_segment(blueprint);
+
+    for f in files {
+        if sanitize::escapes_root(f.component()) {
+            return Err(format!("component {:?} points outside the output folder", f.component()));
+        }
+        // a component can't take the place of the shared folder
+        let mut component = match sanitize::escape_segment(f.component()) {
+            name if name == SHARED_FOLDER => format!("%{:02X}{}", b'_', &name[1..]),
+            name => name,
+        };
+        // components with the same name (two unaliased computers) each get their own folder
+        let clashes = files.iter().any(|other| {
+            other.component_index() < f.component_index() && other.component().to_lowercase() == f.component().to_lowercase()
+        });
+        if clashes {
+            component = format!("{} #{}", component, f.component_index());
+        }
+        let file_name = sanitize::escape_path(f.file_name())
+            .map_err(|e| format!("{} (in component {:?})", e, f.component()))?;
+
+        let target = FileTarget {
+            component: f.component_index(),
+            name: f.file_name().to_string(),
+            program: f.is_program(),
+        };
+        let shared = collate && dedup && files.iter().any(|other| {
+            other.component_index() != f.component_index()
+                && other.file_name() == f.file_name()
+                && other.file_content() == f.file_content()
+        });
+
+        if !shared {
+            let path = layout.path(&blueprint, &component, &file_name);
+            // without component folders, identical files land on the same path; keep one copy
+            if let Some(existing) = planned.iter_mut().find(|p| !p.shared && p.path == path && p.content == f.file_content()) {
+                existing.targets.push(target);
+                continue;
+            }
+            planned.push(PlannedFile { path, content: f.file_content().to_string(), targets: vec![target], shared: false });
+            continue;
+        }
+
+        if let Some(existing) = planned.iter_mut().find(|p| {
+            p.shared && p.targets[0].name == f.file_name() && p.content == f.file_content()
+        }) {
+            existing.targets.push(target);
+            continue;
+        }
+
+        // different shared groups can use the same file name; number the later ones
+        let mut path = layout.path(&blueprint, SHARED_FOLDER, &file_name);
+        let mut group = 1;
+        while planned.iter().any(|p| p.path == path) {
+            group += 1;
+            path = layout.path(&blueprint, &format!("{}/{}", SHARED_FOLDER, group), &file_name);
+        }
+        planned.push(PlannedFile { path, content: f.file_content().to_string(), targets: vec![target], shared: true });
+    }
+
+    // the manifest maps each path back to its components; a path planned twice would lose a file
+    for (i, file) in planned.iter().enumerate() {
+        if planned[..i].iter().any(|p| p.path.to_lowercase() == file.path.to_lowercase()) {
+            return Err(format!("more than one file would be written to {:?}; use a layout with {{component}}", file.path));
+        }
+    }
+    Ok(planned)
+}
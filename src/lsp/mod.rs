@@ -0,0 +1,469 @@
+mod transport;
+
+use std::collections::HashMap;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+use crate::command::manifest::YankManifest;
+use crate::xenon::ast::{ItemKind, Span};
+use crate::xenon::builtins;
+use crate::xenon::bundle::Library;
+use crate::xenon::lexer::TokenKind;
+use crate::xenon::parser::{parse, Parsed};
+use crate::xenon::symbols::{analyze, Occurrence, Symbol, SymbolKind, Symbols};
+use transport::{path_to_uri, read_message, uri_to_path, write_message};
+
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+
+struct Analysis {
+    parsed: Parsed,
+    symbols: Symbols,
+}
+
+/// A Language Server for XenonCode speaking JSON-RPC over stdio.
+///
+/// Files on one computer's HDD share their global names and can include each other. Which files
+/// that are is read from the yank manifest, so files deduplicated into the shared folder are
+/// analysed with the components they came from; without a manifest every folder is one HDD.
+pub struct Server {
+    /// unsaved contents of open documents
+    documents: HashMap<PathBuf, String>,
+    library: Library,
+    shutdown: bool,
+}
+
+impl Server {
+    pub fn new(library: Library) -> Self {
+        Server {
+            documents: HashMap::new(),
+            library,
+            shutdown: false,
+        }
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut input = BufReader::new(io::stdin().lock());
+        let mut output = io::stdout().lock();
+        while let Some(message) = read_message(&mut input)? {
+            let method = message.get("method").and_then(Value::as_str).unwrap_or_default().to_string();
+            let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+            if method == "exit" {
+                break;
+            }
+
+            let Some(id) = message.get("id").cloned() else {
+                for notification in self.notify(&method, &params) {
+                    write_message(&mut output, &notification)?;
+                }
+                continue;
+            };
+            if method.is_empty() {
+                // a response to something we never send
+                continue;
+            }
+            let response = match self.request(&method, &params) {
+                Some(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                None => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": -32601, "message": format!("unsupported method {}", method) }
+                }),
+            };
+            write_message(&mut output, &response)?;
+        }
+        if self.shutdown {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::UnexpectedEof, "client exited without shutdown"))
+        }
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Option<Value> {
+        match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "documentSymbolProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": ["$", "@", "\"", "."] }
+                },
+                "serverInfo": { "name": crate::statics::COMMAND, "version": crate::statics::VERSION }
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Value::Null)
+            }
+            "textDocument/definition" => Some(self.definition(params).unwrap_or(Value::Null)),
+            "textDocument/references" => Some(self.references(params).unwrap_or(json!([]))),
+            "textDocument/documentSymbol" => Some(self.document_symbols(params).unwrap_or(json!([]))),
+            "textDocument/hover" => Some(self.hover(params).unwrap_or(Value::Null)),
+            "textDocument/completion" => Some(self.completion(params).unwrap_or(json!([]))),
+            _ => None,
+        }
+    }
+
+    /// Handle a notification, returning the diagnostics to publish.
+    fn notify(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let Some(path) = params.pointer("/textDocument/uri").and_then(Value::as_str).and_then(uri_to_path) else {
+            return vec![];
+        };
+        match method {
+            "textDocument/didOpen" => {
+                let text = params.pointer("/textDocument/text").and_then(Value::as_str).unwrap_or_default();
+                self.documents.insert(path.clone(), text.to_string());
+            }
+            "textDocument/didChange" => {
+                // we only ask for full-document sync
+                let text = params.get("contentChanges")
+                    .and_then(Value::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Value::as_str);
+                if let Some(text) = text {
+                    self.documents.insert(path.clone(), text.to_string());
+                }
+            }
+            "textDocument/didSave" => {}
+            "textDocument/didClose" => {
+                self.documents.remove(&path);
+                return vec![publish(&path, vec![])];
+            }
+            _ => return vec![],
+        }
+
+        // a change can resolve or break names in every open file on the same HDD
+        let hdd = self.hdd_files(&path);
+        let mut open: Vec<PathBuf> = self.documents.keys()
+            .filter(|p| hdd.contains(p))
+            .cloned()
+            .collect();
+        open.sort();
+        open.iter().map(|p| publish(p, self.diagnostics(p))).collect()
+    }
+
+    fn source(&self, path: &Path) -> Option<String> {
+        match self.documents.get(path) {
+            Some(text) => Some(text.clone()),
+            None => std::fs::read_to_string(path).ok(),
+        }
+    }
+
+    /// Every `.xc` file on the same HDD as `path`, including `path` itself.
+    fn hdd_files(&self, path: &Path) -> Vec<PathBuf> {
+        let folder = path.parent().unwrap_or(Path::new("."));
+        let mut files: Vec<PathBuf> = std::fs::read_dir(folder)
+            .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+            .unwrap_or_default();
+        files.extend(self.documents.keys().cloned());
+        files.push(path.to_path_buf());
+        files.retain(|p| p.parent() == Some(folder) && p.extension().is_some_and(|e| e == "xc"));
+
+        if let Some((root, manifest)) = YankManifest::find(folder) {
+            // a file that isn't in the manifest yet goes with the files next to it
+            let mut components = manifest.components_of(&root, [path]);
+            if components.is_empty() {
+                components = manifest.components_of(&root, files.iter().map(PathBuf::as_path));
+            }
+            if !components.is_empty() {
+                files.retain(|f| {
+                    let owners = manifest.components_of(&root, [f.as_path()]);
+                    owners.is_empty() || !owners.is_disjoint(&components)
+                });
+                files.extend(manifest.files.iter()
+                    .filter(|entry| entry.targets.iter().any(|t| components.contains(&t.component)))
+                    .map(|entry| root.join(&entry.path))
+                    .filter(|p| p.extension().is_some_and(|e| e == "xc")));
+            }
+        }
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    fn analyze(&self, path: &Path) -> Option<Analysis> {
+        let parsed = parse(&self.source(path)?);
+        let symbols = analyze(&parsed.program);
+        Some(Analysis { parsed, symbols })
+    }
+
+    fn hdd_analyses(&self, path: &Path) -> Vec<(PathBuf, Analysis)> {
+        self.hdd_files(path).into_iter()
+            .filter_map(|p| self.analyze(&p).map(|a| (p, a)))
+            .collect()
+    }
+
+    fn diagnostics(&self, path: &Path) -> Vec<Value> {
+        let Some(analysis) = self.analyze(path) else {
+            return vec![];
+        };
+        let hdd = self.hdd_analyses(path);
+        let is_global = |name: &str, function: bool| {
+            hdd.iter().any(|(_, a)| a.symbols.globals().any(|s| s.name == name && s.is_function() == function))
+        };
+
+        let mut diagnostics: Vec<Value> = analysis.parsed.diagnostics.iter()
+            .map(|d| diagnostic(d.span, &d.message, SEVERITY_ERROR))
+            .collect();
+
+        for reference in analysis.symbols.references.iter().filter(|r| r.local.is_none()) {
+            if !is_global(&reference.name, reference.function) {
+                let message = if reference.function {
+                    format!("unknown function @{}", reference.name)
+                } else {
+                    format!("undefined variable ${}", reference.name)
+                };
+                diagnostics.push(diagnostic(reference.span, &message, SEVERITY_ERROR));
+            }
+        }
+        for name in &analysis.symbols.builtins {
+            if builtins::function(&name.name).is_none() {
+                diagnostics.push(diagnostic(name.span, &format!("unknown built-in '{}'", name.name), SEVERITY_WARNING));
+            }
+        }
+        // files deduplicated into the shared folder are on the HDD under their own name
+        let folder = path.parent().unwrap_or(Path::new("."));
+        let on_hdd: Vec<PathBuf> = self.hdd_files(path).into_iter().filter(|p| p.is_file() || self.documents.contains_key(p)).collect();
+        for item in &analysis.parsed.program.items {
+            if let ItemKind::Include { path: include, span } = &item.kind {
+                let found = folder.join(include).is_file()
+                    || on_hdd.iter().any(|p| p.file_name().is_some_and(|n| n.to_string_lossy() == *include))
+                    || self.library.find(include).is_some();
+                if !found {
+                    diagnostics.push(diagnostic(*span, &format!("\"{}\" not found on this HDD or in any library path", include), SEVERITY_WARNING));
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// The symbol under the cursor, with the file it is defined in.
+    fn resolve(&self, params: &Value) -> Option<(PathBuf, Symbol)> {
+        let (path, line, column) = text_position(params)?;
+        let analysis = self.analyze(&path)?;
+        let reference = match analysis.symbols.at(line, column)? {
+            Occurrence::Definition(symbol) => return Some((path, symbol.clone())),
+            Occurrence::Reference(reference) => reference,
+        };
+        if let Some(local) = reference.local {
+            return Some((path, analysis.symbols.definitions[local].clone()));
+        }
+        self.hdd_analyses(&path).into_iter().find_map(|(p, a)| {
+            a.symbols.globals()
+                .find(|s| s.name == reference.name && s.is_function() == reference.function)
+                .map(|s| (p, s.clone()))
+        })
+    }
+
+    fn definition(&self, params: &Value) -> Option<Value> {
+        let (path, symbol) = self.resolve(params)?;
+        Some(location(&path, symbol.span))
+    }
+
+    fn references(&self, params: &Value) -> Option<Value> {
+        let (path, symbol) = self.resolve(params)?;
+        let include_declaration = params.pointer("/context/includeDeclaration").and_then(Value::as_bool).unwrap_or(true);
+        let mut locations = vec![];
+        if include_declaration {
+            locations.push(location(&path, symbol.span));
+        }
+
+        if !symbol.is_global() {
+            let analysis = self.analyze(&path)?;
+            let index = analysis.symbols.definitions.iter().position(|s| *s == symbol);
+            for reference in analysis.symbols.references.iter().filter(|r| r.local.is_some() && r.local == index) {
+                locations.push(location(&path, reference.span));
+            }
+            return Some(Value::Array(locations));
+        }
+
+        for (p, analysis) in self.hdd_analyses(&path) {
+            for reference in &analysis.symbols.references {
+                if reference.local.is_none() && reference.name == symbol.name && reference.function == symbol.is_function() {
+                    locations.push(location(&p, reference.span));
+                }
+            }
+        }
+        Some(Value::Array(locations))
+    }
+
+    fn document_symbols(&self, params: &Value) -> Option<Value> {
+        let path = params.pointer("/textDocument/uri").and_then(Value::as_str).and_then(uri_to_path)?;
+        let analysis = self.analyze(&path)?;
+        let mut symbols: Vec<Value> = analysis.symbols.globals().map(|s| {
+            let kind = match s.kind {
+                SymbolKind::Function => 12,
+                SymbolKind::Const => 14,
+                SymbolKind::Array => 18,
+                _ => 13,
+            };
+            document_symbol(&s.name, &s.detail, kind, s.span)
+        }).collect();
+        for item in &analysis.parsed.program.items {
+            if let ItemKind::Entry { entry, .. } = &item.kind {
+                let width = analysis.parsed.lines[item.line].tokens.last().map(|t| t.span.end).unwrap_or_default();
+                symbols.push(document_symbol(&entry.label(), "entry point", 24, Span::new(item.line, 0, width)));
+            }
+        }
+        Some(Value::Array(symbols))
+    }
+
+    fn hover(&self, params: &Value) -> Option<Value> {
+        let (path, line, column) = text_position(params)?;
+        let analysis = self.analyze(&path)?;
+
+        if let Some(name) = analysis.symbols.builtins.iter().find(|b| b.span.contains(line, column)) {
+            let builtin = builtins::function(&name.name)?;
+            return Some(hover(&format!("```xenoncode\n{}\n```\n{}", builtin.signature, builtin.doc)));
+        }
+        let member = analysis.parsed.lines.get(line)?.tokens.iter().enumerate().find_map(|(i, t)| {
+            let after_dot = i > 0 && analysis.parsed.lines[line].tokens[i - 1].is_symbol(".");
+            match &t.kind {
+                TokenKind::Word(w) if after_dot && t.span.contains(line, column) => builtins::member(w),
+                _ => None,
+            }
+        });
+        if let Some(member) = member {
+            return Some(hover(&format!("```xenoncode\n{}\n```\n{}", member.signature, member.doc)));
+        }
+
+        let (defined_in, symbol) = self.resolve(params)?;
+        let doc = if defined_in == path {
+            analysis.parsed.doc_comment(symbol.span.line)
+        } else {
+            self.analyze(&defined_in).and_then(|a| a.parsed.doc_comment(symbol.span.line))
+        };
+        let mut contents = format!("```xenoncode\n{}\n```", symbol.detail);
+        if let Some(doc) = doc {
+            contents.push('\n');
+            contents.push_str(&doc);
+        }
+        Some(hover(&contents))
+    }
+
+    fn completion(&self, params: &Value) -> Option<Value> {
+        let (path, line, column) = text_position(params)?;
+        let source = self.source(&path)?;
+        let text: Vec<char> = source.lines().nth(line).unwrap_or_default().chars().collect();
+        let before: String = text[..column.min(text.len())].iter().collect();
+
+        // inside `include "...`
+        if let Some(partial) = before.trim_start().strip_prefix("include") {
+            if let Some(partial) = partial.trim_start().strip_prefix('"') {
+                let start = column - partial.chars().count();
+                return Some(Value::Array(self.include_candidates(&path).into_iter().map(|name| {
+                    completion_item(&name, 17, "file", Span::new(line, start, column))
+                }).collect()));
+            }
+        }
+
+        let chars: Vec<char> = before.chars().collect();
+        let column = chars.len();
+        let mut word_start = column;
+        while word_start > 0 && (chars[word_start - 1].is_ascii_alphanumeric() || chars[word_start - 1] == '_') {
+            word_start -= 1;
+        }
+        let sigil = if word_start > 0 { Some(chars[word_start - 1]) } else { None };
+        let mut items = vec![];
+
+        match sigil {
+            Some('$') | Some('@') => {
+                let function = sigil == Some('@');
+                let range = Span::new(line, word_start - 1, column);
+                let analysis = self.analyze(&path)?;
+                // locals of the item the cursor is in
+                let item = analysis.parsed.program.items.iter().rposition(|i| i.line <= line);
+                let mut seen = vec![];
+                for symbol in analysis.symbols.definitions.iter().filter(|s| s.item.is_some() && s.item == item && !function) {
+                    if !seen.contains(&symbol.name) {
+                        seen.push(symbol.name.clone());
+                        items.push(completion_item(&format!("${}", symbol.name), 6, &symbol.detail, range));
+                    }
+                }
+                for (_, a) in self.hdd_analyses(&path) {
+                    for symbol in a.symbols.globals().filter(|s| s.is_function() == function) {
+                        if !seen.contains(&symbol.name) {
+                            seen.push(symbol.name.clone());
+                            let sigil = if function { "@" } else { "$" };
+                            let kind = if function { 3 } else if symbol.kind == SymbolKind::Const { 21 } else { 6 };
+                            items.push(completion_item(&format!("{}{}", sigil, symbol.name), kind, &symbol.detail, range));
+                        }
+                    }
+                }
+            }
+            Some('.') => {
+                let range = Span::new(line, word_start, column);
+                items.extend(builtins::MEMBERS.iter().map(|m| completion_item(m.name, 2, m.signature, range)));
+            }
+            _ => {
+                let range = Span::new(line, word_start, column);
+                items.extend(builtins::FUNCTIONS.iter().map(|f| completion_item(f.name, 3, f.signature, range)));
+                items.extend(builtins::KEYWORDS.iter().map(|k| completion_item(k, 14, "keyword", range)));
+            }
+        }
+        Some(Value::Array(items))
+    }
+
+    fn include_candidates(&self, path: &Path) -> Vec<String> {
+        let mut names: Vec<String> = self.hdd_files(path).iter()
+            .filter(|p| p.as_path() != path)
+            .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+            .collect();
+        for dir in self.library.search_paths() {
+            if let Ok(entries) = std::fs::read_dir(dir) {
+                names.extend(entries.filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().is_some_and(|e| e == "xc"))
+                    .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().to_string())));
+            }
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+fn text_position(params: &Value) -> Option<(PathBuf, usize, usize)> {
+    let path = params.pointer("/textDocument/uri").and_then(Value::as_str).and_then(uri_to_path)?;
+    let line = params.pointer("/position/line").and_then(Value::as_u64)?;
+    let column = params.pointer("/position/character").and_then(Value::as_u64)?;
+    Some((path, line as usize, column as usize))
+}
+
+fn range(span: Span) -> Value {
+    json!({
+        "start": { "line": span.line, "character": span.start },
+        "end": { "line": span.line, "character": span.end }
+    })
+}
+
+fn location(path: &Path, span: Span) -> Value {
+    json!({ "uri": path_to_uri(path), "range": range(span) })
+}
+
+fn diagnostic(span: Span, message: &str, severity: u8) -> Value {
+    json!({ "range": range(span), "severity": severity, "source": crate::statics::COMMAND, "message": message })
+}
+
+fn publish(path: &Path, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": path_to_uri(path), "diagnostics": diagnostics }
+    })
+}
+
+fn document_symbol(name: &str, detail: &str, kind: u8, span: Span) -> Value {
+    json!({ "name": name, "detail": detail, "kind": kind, "range": range(span), "selectionRange": range(span) })
+}
+
+fn hover(markdown: &str) -> Value {
+    json!({ "contents": { "kind": "markdown", "value": markdown } })
+}
+
+fn completion_item(label: &str, kind: u8, detail: &str, span: Span) -> Value {
+    json!({ "label": label, "kind": kind, "detail": detail, "textEdit": { "range": range(span), "newText": label } })
+}
@@ -1,6 +1,8 @@
 mod archean;
 mod command;
+mod lsp;
 mod statics;
+mod xenon;
 
 use command::prelude::*;
 
@@ -0,0 +1,243 @@
+/// Location of a token: zero-based line and character columns, `end` exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Span {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, start: usize, end: usize) -> Self {
+        Span { line, start, end }
+    }
+
+    pub fn contains(&self, line: usize, column: usize) -> bool {
+        self.line == line && self.start <= column && column <= self.end
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Diagnostic { span, message: message.into() }
+    }
+}
+
+/// A `$variable` or `@function` name as written in the source, without its sigil.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Number,
+    Text,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: Ident,
+    pub ty: Option<Type>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Program {
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub kind: ItemKind,
+    /// line the item is declared on
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemKind {
+    Include { path: String, span: Span },
+    Const { name: Ident, value: Expr },
+    Var(VarDecl),
+    Function { name: Ident, params: Vec<Param>, ret: Option<Type>, body: Block },
+    Entry { entry: Entry, body: Block },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarDecl {
+    pub name: Ident,
+    pub storage: bool,
+    pub array: bool,
+    pub ty: Option<Type>,
+    pub value: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    Init,
+    Tick,
+    Shutdown,
+    TimerFrequency(f64),
+    TimerInterval(f64),
+    Input { port: u32, params: Vec<Param> },
+}
+
+impl Entry {
+    pub fn label(&self) -> String {
+        match self {
+            Entry::Init => "init".to_string(),
+            Entry::Tick => "tick".to_string(),
+            Entry::Shutdown => "shutdown".to_string(),
+            Entry::TimerFrequency(f) => format!("timer frequency {}", f),
+            Entry::TimerInterval(i) => format!("timer interval {}", i),
+            Entry::Input { port, .. } => format!("input.{}", port),
+        }
+    }
+}
+
+pub type Block = Vec<Stmt>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    Var(VarDecl),
+    Assign { target: Expr, op: AssignOp, value: Expr },
+    Increment { target: Expr, delta: f64 },
+    Expr(Expr),
+    Output { port: u32, args: Vec<Expr> },
+    If { branches: Vec<(Expr, Block)>, otherwise: Option<Block> },
+    While { cond: Expr, body: Block },
+    Foreach { array: Expr, index: Option<Ident>, item: Ident, body: Block },
+    Repeat { count: Expr, var: Option<Ident>, body: Block },
+    For { from: Expr, to: Expr, var: Option<Ident>, body: Block },
+    Break,
+    Continue,
+    Return(Option<Expr>),
+}
+
+impl Stmt {
+    /// Call `f` on this statement and every statement nested in it.
+    pub fn walk<'a>(&'a self, f: &mut impl FnMut(&'a Stmt)) {
+        f(self);
+        match &self.kind {
+            StmtKind::If { branches, otherwise } => {
+                branches.iter().flat_map(|(_, body)| body).for_each(|s| s.walk(f));
+                otherwise.iter().flatten().for_each(|s| s.walk(f));
+            }
+            StmtKind::While { body, .. }
+            | StmtKind::Foreach { body, .. }
+            | StmtKind::Repeat { body, .. }
+            | StmtKind::For { body, .. } => body.iter().for_each(|s| s.walk(f)),
+            _ => {}
+        }
+    }
+
+    /// Expressions used by this statement itself, not by the statements nested in it.
+    pub fn exprs(&self) -> Vec<&Expr> {
+        match &self.kind {
+            StmtKind::Var(decl) => decl.value.iter().collect(),
+            StmtKind::Assign { target, value, .. } => vec![target, value],
+            StmtKind::Increment { target, .. } => vec![target],
+            StmtKind::Expr(expr) => vec![expr],
+            StmtKind::Output { args, .. } => args.iter().collect(),
+            StmtKind::If { branches, .. } => branches.iter().map(|(cond, _)| cond).collect(),
+            StmtKind::While { cond, .. } => vec![cond],
+            StmtKind::Foreach { array, .. } => vec![array],
+            StmtKind::Repeat { count, .. } => vec![count],
+            StmtKind::For { from, to, .. } => vec![from, to],
+            StmtKind::Return(value) => value.iter().collect(),
+            StmtKind::Break | StmtKind::Continue => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignOp {
+    Set,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    Concat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    Xor,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Concat,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Text(String),
+    Var(Ident),
+    /// `@function(args)`
+    Call { name: Ident, args: Vec<Expr> },
+    /// `builtin(args)`
+    Builtin { name: Ident, args: Vec<Expr> },
+    /// `$array.0` / `$array.$i`
+    Index { base: Box<Expr>, index: Box<Expr> },
+    /// `$array.size` / `$array.append(...)`
+    Member { base: Box<Expr>, name: Ident, args: Option<Vec<Expr>> },
+    Unary { op: UnaryOp, expr: Box<Expr> },
+    Binary { op: BinaryOp, lhs: Box<Expr>, rhs: Box<Expr> },
+}
+
+impl Expr {
+    /// Call `f` on this expression and every expression nested in it.
+    pub fn walk<'a>(&'a self, f: &mut impl FnMut(&'a Expr)) {
+        f(self);
+        match self {
+            Expr::Call { args, .. } | Expr::Builtin { args, .. } => {
+                args.iter().for_each(|a| a.walk(f));
+            }
+            Expr::Index { base, index } => {
+                base.walk(f);
+                index.walk(f);
+            }
+            Expr::Member { base, args, .. } => {
+                base.walk(f);
+                args.iter().flatten().for_each(|a| a.walk(f));
+            }
+            Expr::Unary { expr, .. } => expr.walk(f),
+            Expr::Binary { lhs, rhs, .. } => {
+                lhs.walk(f);
+                rhs.walk(f);
+            }
+            Expr::Number(_) | Expr::Text(_) | Expr::Var(_) => {}
+        }
+    }
+}
@@ -0,0 +1,84 @@
+/// A built-in XenonCode function or array/text member.
+#[derive(Debug, Clone, Copy)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub signature: &'static str,
+    pub doc: &'static str,
+}
+
+const fn builtin(name: &'static str, signature: &'static str, doc: &'static str) -> Builtin {
+    Builtin { name, signature, doc }
+}
+
+pub const FUNCTIONS: &[Builtin] = &[
+    builtin("abs", "abs($x : number) : number", "Absolute value of `$x`."),
+    builtin("fract", "fract($x : number) : number", "Fractional part of `$x`."),
+    builtin("floor", "floor($x : number) : number", "Largest integer not greater than `$x`."),
+    builtin("ceil", "ceil($x : number) : number", "Smallest integer not less than `$x`."),
+    builtin("round", "round($x : number) : number", "`$x` rounded to the nearest integer."),
+    builtin("sign", "sign($x : number) : number", "-1, 0 or 1 depending on the sign of `$x`."),
+    builtin("sqrt", "sqrt($x : number) : number", "Square root of `$x`."),
+    builtin("pow", "pow($x : number, $y : number) : number", "`$x` raised to the power `$y`; same as `$x ^ $y`."),
+    builtin("log", "log($x : number, $base : number) : number", "Logarithm of `$x`; natural logarithm when `$base` is omitted."),
+    builtin("sin", "sin($x : number) : number", "Sine of `$x` radians."),
+    builtin("cos", "cos($x : number) : number", "Cosine of `$x` radians."),
+    builtin("tan", "tan($x : number) : number", "Tangent of `$x` radians."),
+    builtin("asin", "asin($x : number) : number", "Arc sine of `$x`, in radians."),
+    builtin("acos", "acos($x : number) : number", "Arc cosine of `$x`, in radians."),
+    builtin("atan", "atan($y : number, $x : number) : number", "Arc tangent of `$y / $x`, in radians; `$x` is optional."),
+    builtin("min", "min($a : number, $b : number) : number", "Smaller of the two values."),
+    builtin("max", "max($a : number, $b : number) : number", "Larger of the two values."),
+    builtin("avg", "avg($a : number, $b : number) : number", "Average of the two values."),
+    builtin("clamp", "clamp($x : number, $min : number, $max : number) : number", "`$x` limited to the range `$min`..`$max`."),
+    builtin("lerp", "lerp($a : number, $b : number, $t : number) : number", "Linear interpolation from `$a` to `$b` by `$t`."),
+    builtin("step", "step($edge : number, $x : number) : number", "0 if `$x` is below `$edge`, 1 otherwise."),
+    builtin("smoothstep", "smoothstep($a : number, $b : number, $x : number) : number", "Smooth Hermite interpolation between 0 and 1 as `$x` goes from `$a` to `$b`."),
+    builtin("mod", "mod($a : number, $b : number) : number", "Remainder of `$a / $b`; same as `$a % $b`."),
+    builtin("pi", "pi : number", "The constant π."),
+    builtin("text", "text($format : text, ...) : text", "Format a text; each `{}` in `$format` is replaced with the next argument."),
+    builtin("size", "size($t : text) : number", "Number of characters in `$t`."),
+    builtin("upper", "upper($t : text) : text", "`$t` in upper case."),
+    builtin("lower", "lower($t : text) : text", "`$t` in lower case."),
+    builtin("substring", "substring($t : text, $start : number, $length : number) : text", "Part of `$t` starting at `$start`."),
+    builtin("find", "find($t : text, $needle : text) : number", "Position of `$needle` in `$t`, or -1."),
+    builtin("isnumeric", "isnumeric($t : text) : number", "1 if `$t` is a number, 0 otherwise."),
+    builtin("print", "print(...)", "Write the arguments to the computer's console."),
+    builtin("input_number", "input_number($port : number, $channel : number) : number", "Read a number from an IO port channel."),
+    builtin("input_text", "input_text($port : number, $channel : number) : text", "Read a text from an IO port channel."),
+    builtin("output_number", "output_number($port : number, $channel : number, $value : number)", "Write a number to an IO port channel."),
+    builtin("output_text", "output_text($port : number, $channel : number, $value : text)", "Write a text to an IO port channel."),
+    builtin("time", "time : number", "Seconds since the computer was started."),
+    builtin("delta_time", "delta_time : number", "Seconds since the previous tick."),
+];
+
+pub const MEMBERS: &[Builtin] = &[
+    builtin("size", "$array.size : number", "Number of elements in the array."),
+    builtin("min", "$array.min : number", "Smallest element."),
+    builtin("max", "$array.max : number", "Largest element."),
+    builtin("avg", "$array.avg : number", "Average of the elements."),
+    builtin("sum", "$array.sum : number", "Sum of the elements."),
+    builtin("last", "$array.last : number", "Last element."),
+    builtin("append", "$array.append($value)", "Add `$value` to the end of the array."),
+    builtin("pop", "$array.pop()", "Remove the last element."),
+    builtin("insert", "$array.insert($index : number, $value)", "Insert `$value` before `$index`."),
+    builtin("erase", "$array.erase($index : number)", "Remove the element at `$index`."),
+    builtin("clear", "$array.clear()", "Remove every element."),
+    builtin("fill", "$array.fill($count : number, $value)", "Replace the contents with `$count` copies of `$value`."),
+    builtin("sort", "$array.sort()", "Sort the elements in ascending order."),
+    builtin("sortd", "$array.sortd()", "Sort the elements in descending order."),
+];
+
+pub const KEYWORDS: &[&str] = &[
+    "include", "const", "var", "array", "storage", "function", "return",
+    "init", "tick", "shutdown", "timer", "frequency", "interval", "input", "output",
+    "if", "elseif", "else", "while", "foreach", "repeat", "for", "break", "continue",
+    "and", "or", "xor", "not", "number", "text",
+];
+
+pub fn function(name: &str) -> Option<&'static Builtin> {
+    FUNCTIONS.iter().find(|b| b.name == name)
+}
+
+pub fn member(name: &str) -> Option<&'static Builtin> {
+    MEMBERS.iter().find(|b| b.name == name)
+}
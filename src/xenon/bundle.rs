@@ -0,0 +1,216 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use crate::archean::json::XcFile;
+use crate::command::sanitize;
+use super::include::includes;
+
+/// Environment variable holding extra library search paths, separated like `PATH`.
+pub const LIBRARY_PATH_ENV: &str = "ARCHBELT_LIB";
+
+/// Ordered set of directories that shared XenonCode libraries are resolved from.
+#[derive(Debug, Clone, Default)]
+pub struct Library {
+    search_paths: Vec<PathBuf>,
+}
+
+impl Library {
+    /// Search `paths` first, then anything listed in `ARCHBELT_LIB`.
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let mut search_paths = paths;
+        if let Some(env_paths) = std::env::var_os(LIBRARY_PATH_ENV) {
+            search_paths.extend(std::env::split_paths(&env_paths));
+        }
+        Library { search_paths }
+    }
+
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.search_paths
+    }
+
+    /// The first library file called `name`; names that would lead out of the library folders,
+    /// like `../x.xc` or absolute paths, are never found.
+    pub fn find(&self, name: &str) -> Option<PathBuf> {
+        if !sanitize::is_contained(Path::new(name)) {
+            return None;
+        }
+        self.search_paths.iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    }
+}
+
+#[derive(Debug)]
+pub enum BundleError {
+    Unresolved { file: String, include: String, line: usize },
+    Cycle(Vec<String>),
+    Io(PathBuf, std::io::Error),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::Unresolved { file, include, line } => {
+                write!(f, "{}:{}: include \"{}\" not found on the HDD or in any library path", file, line + 1, include)
+            }
+            BundleError::Cycle(chain) => {
+                write!(f, "include cycle: {}", chain.join(" -> "))
+            }
+            BundleError::Io(path, e) => {
+                write!(f, "could not read {:?}: {}", path, e)
+            }
+        }
+    }
+}
+
+/// Result of bundling the files of a single HDD.
+#[derive(Debug, Clone, Default)]
+pub struct Bundle {
+    pub files: Vec<XcFile>,
+    /// library files that were not on the HDD before
+    pub added: Vec<String>,
+    /// library files that were on the HDD with different content
+    pub updated: Vec<String>,
+}
+
+impl Bundle {
+    pub fn is_changed(&self) -> bool {
+        !self.added.is_empty() || !self.updated.is_empty()
+    }
+}
+
+#[derive(PartialEq)]
+enum Visit {
+    InProgress,
+    Done,
+}
+
+struct Resolver<'a> {
+    library: &'a Library,
+    local: HashMap<String, String>,
+    /// HDD files that no other HDD file includes; these are never replaced by library files
+    roots: HashSet<String>,
+    library_sources: HashMap<String, String>,
+    state: HashMap<String, Visit>,
+    stack: Vec<String>,
+    order: Vec<String>,
+}
+
+impl Resolver<'_> {
+    /// Load `name`, preferring the library copy so bundled files are always refreshed, unless it
+    /// is one of the HDD's roots.
+    fn source(&mut self, name: &str) -> Result<Option<String>, BundleError> {
+        if self.roots.contains(name) {
+            return Ok(self.local.get(name).cloned());
+        }
+        if let Some(code) = self.library_sources.get(name) {
+            return Ok(Some(code.clone()));
+        }
+        if let Some(path) = self.library.find(name) {
+            let code = std::fs::read_to_string(&path).map_err(|e| BundleError::Io(path, e))?;
+            self.library_sources.insert(name.to_string(), code.clone());
+            return Ok(Some(code));
+        }
+        Ok(self.local.get(name).cloned())
+    }
+
+    fn visit(&mut self, name: &str) -> Result<(), BundleError> {
+        match self.state.get(name) {
+            Some(Visit::Done) => return Ok(()),
+            Some(Visit::InProgress) => {
+                let start = self.stack.iter().position(|n| n == name).unwrap_or(0);
+                let mut chain = self.stack[start..].to_vec();
+                chain.push(name.to_string());
+                return Err(BundleError::Cycle(chain));
+            }
+            None => {}
+        }
+
+        self.state.insert(name.to_string(), Visit::InProgress);
+        self.stack.push(name.to_string());
+
+        let code = self.source(name)?.unwrap_or_default();
+        for include in includes(&code) {
+            if self.source(&include.path)?.is_none() {
+                return Err(BundleError::Unresolved {
+                    file: name.to_string(),
+                    include: include.path,
+                    line: include.line,
+                });
+            }
+            self.visit(&include.path)?;
+        }
+
+        self.stack.pop();
+        self.state.insert(name.to_string(), Visit::Done);
+        self.order.push(name.to_string());
+        Ok(())
+    }
+}
+
+/// Resolve the includes of an HDD's files against `library`.
+///
+/// The HDD's own files keep their order; library files follow them, dependencies first. Only files
+/// reached through an `include` are taken from the library, so a file of the HDD that nothing
+/// includes (like its `main.xc`) is kept even if a library has one with the same name.
+pub fn bundle(files: &[XcFile], library: &Library) -> Result<Bundle, BundleError> {
+    let included: HashSet<String> = files.iter()
+        .flat_map(|f| includes(&f.plain_code))
+        .map(|include| include.path)
+        .collect();
+    let mut resolver = Resolver {
+        library,
+        local: files.iter().map(|f| (f.name.clone(), f.plain_code.clone())).collect(),
+        roots: files.iter().map(|f| f.name.clone()).filter(|name| !included.contains(name)).collect(),
+        library_sources: HashMap::new(),
+        state: HashMap::new(),
+        stack: vec![],
+        order: vec![],
+    };
+
+    for file in files {
+        resolver.visit(&file.name)?;
+    }
+
+    let mut bundle = Bundle::default();
+    for file in files {
+        if !resolver.library_sources.contains_key(&file.name) {
+            bundle.files.push(file.clone());
+        }
+    }
+    for name in &resolver.order {
+        let Some(code) = resolver.library_sources.get(name) else {
+            continue;
+        };
+        match resolver.local.get(name) {
+            None => bundle.added.push(name.clone()),
+            Some(existing) if existing != code => bundle.updated.push(name.clone()),
+            _ => {}
+        }
+        bundle.files.push(XcFile {
+            name: name.clone(),
+            plain_code: code.clone(),
+        });
+    }
+
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn library_files_are_only_found_inside_the_library() {
+        let dir = std::env::temp_dir().join(format!("archbelt-library-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("lib").join("pid.xc"), "").unwrap();
+        std::fs::write(dir.join("secret.xc"), "").unwrap();
+        let library = Library { search_paths: vec![dir.join("lib")] };
+
+        assert_eq!(library.find("pid.xc"), Some(dir.join("lib").join("pid.xc")));
+        assert_eq!(library.find("../secret.xc"), None);
+        assert_eq!(library.find(&dir.join("secret.xc").to_string_lossy()), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+use super::ast::*;
+use super::interpreter::format_number;
+use super::parser::parse;
+use super::symbols::function_detail;
+
+/// What `archbelt doc` shows of one `.xc` file.
+#[derive(Debug, Clone, Default)]
+pub struct FileDoc {
+    pub name: String,
+    /// the comment block at the top of the file, if it isn't attached to the first item
+    pub summary: Option<String>,
+    pub consts: Vec<Declaration>,
+    pub functions: Vec<Declaration>,
+    pub entries: Vec<String>,
+    pub ports: Vec<PortUse>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    /// the declaration as it would be written, e.g. `const $MAX = 100`
+    pub signature: String,
+    pub doc: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Direction {
+    Read,
+    Write,
+}
+
+/// A place a program reads from or writes to an IO port.
+#[derive(Debug, Clone)]
+pub struct PortUse {
+    /// the port as written: a number, or the expression that computes it
+    pub port: String,
+    pub channel: Option<String>,
+    pub direction: Direction,
+    /// `output.N`, `input.N` or the built-in used
+    pub via: String,
+    pub line: usize,
+}
+
+fn binary_symbol(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Or => "or",
+        BinaryOp::Xor => "xor",
+        BinaryOp::And => "and",
+        BinaryOp::Eq => "==",
+        BinaryOp::Ne => "!=",
+        BinaryOp::Lt => "<",
+        BinaryOp::Le => "<=",
+        BinaryOp::Gt => ">",
+        BinaryOp::Ge => ">=",
+        BinaryOp::Concat => "&",
+        BinaryOp::Add => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::Mod => "%",
+        BinaryOp::Pow => "^",
+    }
+}
+
+fn list(args: &[Expr]) -> String {
+    args.iter().map(source).collect::<Vec<_>>().join(", ")
+}
+
+/// Write `expr` back as XenonCode; nested operations get parentheses.
+pub fn source(expr: &Expr) -> String {
+    let operand = |e: &Expr| match e {
+        Expr::Binary { .. } => format!("({})", source(e)),
+        _ => source(e),
+    };
+    match expr {
+        Expr::Number(n) => format_number(*n),
+        Expr::Text(t) => format!("{:?}", t),
+        Expr::Var(name) => format!("${}", name.name),
+        Expr::Call { name, args } => format!("@{}({})", name.name, list(args)),
+        Expr::Builtin { name, args } => format!("{}({})", name.name, list(args)),
+        Expr::Index { base, index } => format!("{}.{}", source(base), source(index)),
+        Expr::Member { base, name, args: None } => format!("{}.{}", source(base), name.name),
+        Expr::Member { base, name, args: Some(args) } => format!("{}.{}({})", source(base), name.name, list(args)),
+        Expr::Unary { op: UnaryOp::Neg, expr } => format!("-{}", operand(expr)),
+        Expr::Unary { op: UnaryOp::Not, expr } => format!("!{}", operand(expr)),
+        Expr::Binary { op, lhs, rhs } => format!("{} {} {}", operand(lhs), binary_symbol(*op), operand(rhs)),
+    }
+}
+
+fn port_uses(stmt: &Stmt, ports: &mut Vec<PortUse>) {
+    if let StmtKind::Output { port, .. } = &stmt.kind {
+        ports.push(PortUse {
+            port: port.to_string(),
+            channel: None,
+            direction: Direction::Write,
+            via: format!("output.{}", port),
+            line: stmt.line,
+        });
+    }
+    for expr in stmt.exprs() {
+        expr.walk(&mut |e| {
+            let Expr::Builtin { name, args } = e else {
+                return;
+            };
+            let direction = match name.name.as_str() {
+                "input_number" | "input_text" => Direction::Read,
+                "output_number" | "output_text" => Direction::Write,
+                _ => return,
+            };
+            ports.push(PortUse {
+                port: args.first().map(source).unwrap_or_default(),
+                channel: args.get(1).map(source),
+                direction,
+                via: name.name.clone(),
+                line: stmt.line,
+            });
+        });
+    }
+}
+
+/// Collect the documentation of one file; `name` is only used as its title.
+pub fn document(name: &str, code: &str) -> FileDoc {
+    let parsed = parse(code);
+    let mut doc = FileDoc { name: name.to_string(), ..Default::default() };
+
+    // a comment block separated from the first item by a blank line describes the file
+    let header: Vec<String> = parsed.lines.iter()
+        .take_while(|l| l.tokens.is_empty())
+        .map(|l| l.comment.clone().unwrap_or_default())
+        .collect();
+    let attached = match parsed.program.items.first() {
+        Some(item @ Item { kind: ItemKind::Const { .. } | ItemKind::Function { .. }, .. }) => {
+            parsed.doc_comment(item.line).map(|d| d.lines().count()).unwrap_or(0)
+        }
+        _ => 0,
+    };
+    let summary: Vec<String> = header[..header.len().saturating_sub(attached)].iter()
+        .filter(|l| !l.is_empty())
+        .cloned()
+        .collect();
+    if !summary.is_empty() {
+        doc.summary = Some(summary.join("\n"));
+    }
+
+    for item in &parsed.program.items {
+        match &item.kind {
+            ItemKind::Const { name, value } => doc.consts.push(Declaration {
+                signature: format!("const ${} = {}", name.name, source(value)),
+                doc: parsed.doc_comment(item.line),
+            }),
+            ItemKind::Function { name, params, ret, body } => {
+                doc.functions.push(Declaration {
+                    signature: function_detail(name, params, ret),
+                    doc: parsed.doc_comment(item.line),
+                });
+                body.iter().for_each(|s| s.walk(&mut |s| port_uses(s, &mut doc.ports)));
+            }
+            ItemKind::Entry { entry, body } => {
+                if let Entry::Input { port, .. } = entry {
+                    doc.ports.push(PortUse {
+                        port: port.to_string(),
+                        channel: None,
+                        direction: Direction::Read,
+                        via: entry.label(),
+                        line: item.line,
+                    });
+                }
+                doc.entries.push(entry.label());
+                body.iter().for_each(|s| s.walk(&mut |s| port_uses(s, &mut doc.ports)));
+            }
+            ItemKind::Include { .. } | ItemKind::Var(_) => {}
+        }
+    }
+    doc
+}
@@ -0,0 +1,27 @@
+/// An `include "file.xc"` directive found in a XenonCode source file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Include {
+    pub path: String,
+    /// zero-based line the directive sits on
+    pub line: usize,
+}
+
+/// Collect the `include` directives of a XenonCode source.
+///
+/// Includes are only valid at the top level of a file, so indented lines and anything after a
+/// `;` comment are ignored.
+pub fn includes(source: &str) -> Vec<Include> {
+    source.lines().enumerate().filter_map(|(line, text)| {
+        let rest = text.strip_prefix("include")?;
+        if !rest.starts_with([' ', '\t']) {
+            return None;
+        }
+        let rest = rest.trim_start().strip_prefix('"')?;
+        let end = rest.find('"')?;
+        let path = rest[..end].trim();
+        if path.is_empty() {
+            return None;
+        }
+        Some(Include { path: path.to_string(), line })
+    }).collect()
+}
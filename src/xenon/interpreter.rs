@@ -0,0 +1,736 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use super::ast::*;
+
+/// Statements an entry point may run before it is considered stuck in a loop.
+const STEP_LIMIT: u64 = 1_000_000;
+const MAX_CALL_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+}
+
+impl Value {
+    pub fn as_number(&self) -> f64 {
+        match self {
+            Value::Number(n) => *n,
+            Value::Text(t) => t.trim().parse().unwrap_or(0.0),
+        }
+    }
+
+    pub fn as_text(&self) -> String {
+        match self {
+            Value::Number(n) => format_number(*n),
+            Value::Text(t) => t.clone(),
+        }
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Number(n) => *n != 0.0,
+            Value::Text(t) => !t.is_empty(),
+        }
+    }
+
+    fn default_for(ty: Option<Type>) -> Value {
+        match ty {
+            Some(Type::Text) => Value::Text(String::new()),
+            _ => Value::Number(0.0),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_text())
+    }
+}
+
+fn bool_value(b: bool) -> Value {
+    Value::Number(if b { 1.0 } else { 0.0 })
+}
+
+/// Whole numbers print without decimals, like they do in game.
+pub fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        format!("{}", n)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Slot {
+    Scalar(Value),
+    Array(Vec<Value>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line + 1, self.message)
+    }
+}
+
+type RunResult<T> = Result<T, RuntimeError>;
+
+/// A value sent with `output.N (...)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub tick: u64,
+    pub port: u32,
+    pub args: Vec<Value>,
+}
+
+/// The mocked outside world of a computer.
+#[derive(Debug, Clone, Default)]
+pub struct Io {
+    /// values read by `input_number`/`input_text`, keyed by port and channel
+    pub inputs: HashMap<(String, u32), Value>,
+    /// values written by `output_number`/`output_text`, keyed by port and channel
+    pub outputs: HashMap<(String, u32), Value>,
+    /// everything sent with `output.N`
+    pub messages: Vec<Message>,
+    pub prints: Vec<String>,
+}
+
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+/// Runs XenonCode programs outside of the game.
+///
+/// All programs loaded together behave like the files of one HDD: they share their globals and
+/// functions, and their entry points run in load order.
+pub struct Machine {
+    programs: Rc<Vec<Program>>,
+    functions: HashMap<String, (usize, usize)>,
+    globals: HashMap<String, Slot>,
+    /// one stack of scopes per active function call or entry point
+    frames: Vec<Vec<HashMap<String, Slot>>>,
+    pub io: Io,
+    tick_rate: f64,
+    tick: u64,
+    time: f64,
+    timers: HashMap<(usize, usize), f64>,
+    steps: u64,
+    line: usize,
+}
+
+impl Machine {
+    /// Load `programs` and initialize their global variables.
+    pub fn new(programs: Vec<Program>, tick_rate: f64) -> RunResult<Self> {
+        let mut machine = Machine {
+            programs: Rc::new(programs),
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            frames: vec![],
+            io: Io::default(),
+            tick_rate,
+            tick: 0,
+            time: 0.0,
+            timers: HashMap::new(),
+            steps: 0,
+            line: 0,
+        };
+        let programs = machine.programs.clone();
+        for (p, program) in programs.iter().enumerate() {
+            for (i, item) in program.items.iter().enumerate() {
+                if let ItemKind::Function { name, .. } = &item.kind {
+                    machine.functions.insert(name.name.clone(), (p, i));
+                }
+            }
+        }
+        machine.frames.push(vec![HashMap::new()]);
+        for program in programs.iter() {
+            for item in &program.items {
+                machine.line = item.line;
+                match &item.kind {
+                    ItemKind::Const { name, value } => {
+                        let value = machine.eval(value)?;
+                        machine.globals.insert(name.name.clone(), Slot::Scalar(value));
+                    }
+                    ItemKind::Var(decl) => {
+                        let slot = machine.new_slot(decl)?;
+                        machine.globals.insert(decl.name.name.clone(), slot);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        machine.frames.clear();
+        Ok(machine)
+    }
+
+    pub fn global(&self, name: &str) -> Option<&Slot> {
+        self.globals.get(name)
+    }
+
+    pub fn run_init(&mut self) -> RunResult<()> {
+        self.run_entries(|e| matches!(e, Entry::Init), vec![])
+    }
+
+    /// Deliver a message to the `input.N` entry points for `port`.
+    pub fn send_input(&mut self, port: u32, args: Vec<Value>) -> RunResult<()> {
+        self.run_entries(|e| matches!(e, Entry::Input { port: p, .. } if *p == port), args)
+    }
+
+    /// Run one tick: `tick` entry points, then any timers that are due.
+    pub fn run_tick(&mut self) -> RunResult<()> {
+        self.run_entries(|e| matches!(e, Entry::Tick), vec![])?;
+
+        let delta = 1.0 / self.tick_rate;
+        let programs = self.programs.clone();
+        for (p, program) in programs.iter().enumerate() {
+            for (i, item) in program.items.iter().enumerate() {
+                let ItemKind::Entry { entry, body } = &item.kind else {
+                    continue;
+                };
+                let period = match entry {
+                    Entry::TimerFrequency(f) if *f > 0.0 => 1.0 / f,
+                    Entry::TimerInterval(s) if *s > 0.0 => *s,
+                    _ => continue,
+                };
+                let elapsed = self.timers.entry((p, i)).or_insert(0.0);
+                *elapsed += delta;
+                if *elapsed + 1e-9 >= period {
+                    *elapsed -= period;
+                    self.run_body(body, vec![], &[])?;
+                }
+            }
+        }
+
+        self.tick += 1;
+        self.time += delta;
+        Ok(())
+    }
+
+    fn run_entries(&mut self, matches: impl Fn(&Entry) -> bool, args: Vec<Value>) -> RunResult<()> {
+        let programs = self.programs.clone();
+        for program in programs.iter() {
+            for item in &program.items {
+                if let ItemKind::Entry { entry, body } = &item.kind {
+                    if matches(entry) {
+                        let params = match entry {
+                            Entry::Input { params, .. } => params.as_slice(),
+                            _ => &[],
+                        };
+                        self.line = item.line;
+                        self.run_body(body, args.clone(), params)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn run_body(&mut self, body: &Block, args: Vec<Value>, params: &[Param]) -> RunResult<Value> {
+        self.steps = 0;
+        let mut scope = HashMap::new();
+        for (i, param) in params.iter().enumerate() {
+            let value = args.get(i).cloned().unwrap_or_else(|| Value::default_for(param.ty));
+            scope.insert(param.name.name.clone(), Slot::Scalar(value));
+        }
+        self.frames.push(vec![scope]);
+        let flow = self.block(body);
+        self.frames.pop();
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::Number(0.0)),
+        }
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> RunResult<T> {
+        Err(RuntimeError { line: self.line, message: message.into() })
+    }
+
+    fn new_slot(&mut self, decl: &VarDecl) -> RunResult<Slot> {
+        if decl.array {
+            return Ok(Slot::Array(vec![]));
+        }
+        let value = match &decl.value {
+            Some(value) => self.eval(value)?,
+            None => Value::default_for(decl.ty),
+        };
+        Ok(Slot::Scalar(value))
+    }
+
+    fn slot(&self, name: &str) -> Option<&Slot> {
+        self.frames.last()
+            .and_then(|frame| frame.iter().rev().find_map(|scope| scope.get(name)))
+            .or_else(|| self.globals.get(name))
+    }
+
+    fn slot_mut(&mut self, name: &str) -> Option<&mut Slot> {
+        if let Some(frame) = self.frames.last_mut() {
+            if let Some(scope) = frame.iter_mut().rev().find(|scope| scope.contains_key(name)) {
+                return scope.get_mut(name);
+            }
+        }
+        self.globals.get_mut(name)
+    }
+
+    fn declare(&mut self, name: &str, slot: Slot) {
+        self.frames.last_mut().unwrap().last_mut().unwrap().insert(name.to_string(), slot);
+    }
+
+    fn block(&mut self, block: &Block) -> RunResult<Flow> {
+        self.frames.last_mut().unwrap().push(HashMap::new());
+        let mut flow = Flow::Normal;
+        for stmt in block {
+            flow = self.stmt(stmt)?;
+            if !matches!(flow, Flow::Normal) {
+                break;
+            }
+        }
+        self.frames.last_mut().unwrap().pop();
+        Ok(flow)
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> RunResult<Flow> {
+        self.line = stmt.line;
+        self.steps += 1;
+        if self.steps > STEP_LIMIT {
+            return self.error("too many steps; is there an endless loop?");
+        }
+
+        match &stmt.kind {
+            StmtKind::Var(decl) => {
+                let slot = self.new_slot(decl)?;
+                self.declare(&decl.name.name, slot);
+            }
+            StmtKind::Assign { target, op, value } => {
+                let value = self.eval(value)?;
+                let value = match op {
+                    AssignOp::Set => value,
+                    _ => {
+                        let current = self.eval(target)?;
+                        let op = match op {
+                            AssignOp::Add => BinaryOp::Add,
+                            AssignOp::Sub => BinaryOp::Sub,
+                            AssignOp::Mul => BinaryOp::Mul,
+                            AssignOp::Div => BinaryOp::Div,
+                            AssignOp::Mod => BinaryOp::Mod,
+                            AssignOp::Pow => BinaryOp::Pow,
+                            _ => BinaryOp::Concat,
+                        };
+                        binary(op, current, value)
+                    }
+                };
+                self.assign(target, value)?;
+            }
+            StmtKind::Increment { target, delta } => {
+                let value = self.eval(target)?.as_number() + delta;
+                self.assign(target, Value::Number(value))?;
+            }
+            StmtKind::Expr(expr) => {
+                self.eval(expr)?;
+            }
+            StmtKind::Output { port, args } => {
+                let args = args.iter().map(|a| self.eval(a)).collect::<RunResult<Vec<Value>>>()?;
+                self.io.messages.push(Message { tick: self.tick, port: *port, args });
+            }
+            StmtKind::If { branches, otherwise } => {
+                for (cond, body) in branches {
+                    if self.eval(cond)?.is_truthy() {
+                        return self.block(body);
+                    }
+                }
+                if let Some(body) = otherwise {
+                    return self.block(body);
+                }
+            }
+            StmtKind::While { cond, body } => {
+                while self.eval(cond)?.is_truthy() {
+                    match self.block(body)? {
+                        Flow::Break => break,
+                        Flow::Return(v) => return Ok(Flow::Return(v)),
+                        _ => {}
+                    }
+                    self.line = stmt.line;
+                }
+            }
+            StmtKind::Foreach { array, index, item, body } => {
+                let Expr::Var(name) = array else {
+                    return self.error("foreach needs an array variable");
+                };
+                let items = match self.slot(&name.name) {
+                    Some(Slot::Array(items)) => items.clone(),
+                    _ => return self.error(format!("${} is not an array", name.name)),
+                };
+                for (i, value) in items.into_iter().enumerate() {
+                    let mut scope = HashMap::new();
+                    if let Some(index) = index {
+                        scope.insert(index.name.clone(), Slot::Scalar(Value::Number(i as f64)));
+                    }
+                    scope.insert(item.name.clone(), Slot::Scalar(value));
+                    match self.loop_body(scope, body)? {
+                        Flow::Break => break,
+                        Flow::Return(v) => return Ok(Flow::Return(v)),
+                        _ => {}
+                    }
+                }
+            }
+            StmtKind::Repeat { count, var, body } => {
+                let count = self.eval(count)?.as_number().max(0.0) as u64;
+                for i in 0..count {
+                    match self.counted(var, i as f64, body)? {
+                        Flow::Break => break,
+                        Flow::Return(v) => return Ok(Flow::Return(v)),
+                        _ => {}
+                    }
+                }
+            }
+            StmtKind::For { from, to, var, body } => {
+                let from = self.eval(from)?.as_number().round() as i64;
+                let to = self.eval(to)?.as_number().round() as i64;
+                let step = if to >= from { 1 } else { -1 };
+                let mut i = from;
+                loop {
+                    match self.counted(var, i as f64, body)? {
+                        Flow::Break => break,
+                        Flow::Return(v) => return Ok(Flow::Return(v)),
+                        _ => {}
+                    }
+                    if i == to {
+                        break;
+                    }
+                    i += step;
+                }
+            }
+            StmtKind::Break => return Ok(Flow::Break),
+            StmtKind::Continue => return Ok(Flow::Continue),
+            StmtKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.eval(value)?,
+                    None => Value::Number(0.0),
+                };
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn counted(&mut self, var: &Option<Ident>, i: f64, body: &Block) -> RunResult<Flow> {
+        let mut scope = HashMap::new();
+        if let Some(var) = var {
+            scope.insert(var.name.clone(), Slot::Scalar(Value::Number(i)));
+        }
+        self.loop_body(scope, body)
+    }
+
+    fn loop_body(&mut self, scope: HashMap<String, Slot>, body: &Block) -> RunResult<Flow> {
+        self.frames.last_mut().unwrap().push(scope);
+        let flow = self.block(body);
+        self.frames.last_mut().unwrap().pop();
+        flow
+    }
+
+    fn assign(&mut self, target: &Expr, value: Value) -> RunResult<()> {
+        match target {
+            Expr::Var(name) => match self.slot_mut(&name.name) {
+                Some(Slot::Scalar(slot)) => {
+                    *slot = value;
+                    Ok(())
+                }
+                Some(Slot::Array(_)) => self.error(format!("cannot assign a value to array ${}", name.name)),
+                None => self.error(format!("undefined variable ${}", name.name)),
+            },
+            Expr::Index { base, index } => {
+                let Expr::Var(name) = base.as_ref() else {
+                    return self.error("can only index arrays");
+                };
+                let index = self.eval(index)?.as_number();
+                let line = self.line;
+                match self.slot_mut(&name.name) {
+                    Some(Slot::Array(items)) => {
+                        let Some(item) = items.get_mut(index as usize).filter(|_| index >= 0.0) else {
+                            let size = items.len();
+                            return Err(RuntimeError { line, message: format!("index {} out of bounds for ${} (size {})", index, name.name, size) });
+                        };
+                        *item = value;
+                        Ok(())
+                    }
+                    _ => self.error(format!("${} is not an array", name.name)),
+                }
+            }
+            _ => self.error("can only assign to a variable"),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr) -> RunResult<Value> {
+        match expr {
+            Expr::Number(n) => Ok(Value::Number(*n)),
+            Expr::Text(t) => Ok(Value::Text(t.clone())),
+            Expr::Var(name) => match self.slot(&name.name) {
+                Some(Slot::Scalar(value)) => Ok(value.clone()),
+                Some(Slot::Array(_)) => self.error(format!("${} is an array", name.name)),
+                None => self.error(format!("undefined variable ${}", name.name)),
+            },
+            Expr::Call { name, args } => {
+                let args = args.iter().map(|a| self.eval(a)).collect::<RunResult<Vec<Value>>>()?;
+                self.call(&name.name, args)
+            }
+            Expr::Builtin { name, args } => {
+                let args = args.iter().map(|a| self.eval(a)).collect::<RunResult<Vec<Value>>>()?;
+                self.builtin(&name.name, args)
+            }
+            Expr::Index { base, index } => {
+                let Expr::Var(name) = base.as_ref() else {
+                    return self.error("can only index arrays");
+                };
+                let index = self.eval(index)?.as_number();
+                match self.slot(&name.name) {
+                    Some(Slot::Array(items)) => match items.get(index as usize).filter(|_| index >= 0.0) {
+                        Some(value) => Ok(value.clone()),
+                        None => self.error(format!("index {} out of bounds for ${} (size {})", index, name.name, items.len())),
+                    },
+                    _ => self.error(format!("${} is not an array", name.name)),
+                }
+            }
+            Expr::Member { base, name, args } => self.member(base, &name.name, args.as_deref()),
+            Expr::Unary { op, expr } => {
+                let value = self.eval(expr)?;
+                Ok(match op {
+                    UnaryOp::Neg => Value::Number(-value.as_number()),
+                    UnaryOp::Not => bool_value(!value.is_truthy()),
+                })
+            }
+            Expr::Binary { op: BinaryOp::And, lhs, rhs } => {
+                Ok(bool_value(self.eval(lhs)?.is_truthy() && self.eval(rhs)?.is_truthy()))
+            }
+            Expr::Binary { op: BinaryOp::Or, lhs, rhs } => {
+                Ok(bool_value(self.eval(lhs)?.is_truthy() || self.eval(rhs)?.is_truthy()))
+            }
+            Expr::Binary { op, lhs, rhs } => {
+                let lhs = self.eval(lhs)?;
+                let rhs = self.eval(rhs)?;
+                Ok(binary(*op, lhs, rhs))
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Value>) -> RunResult<Value> {
+        let Some((p, i)) = self.functions.get(name).copied() else {
+            return self.error(format!("unknown function @{}", name));
+        };
+        if self.frames.len() > MAX_CALL_DEPTH {
+            return self.error("call stack overflow");
+        }
+        let programs = self.programs.clone();
+        let ItemKind::Function { params, body, .. } = &programs[p].items[i].kind else {
+            unreachable!();
+        };
+        let line = self.line;
+        let steps = self.steps;
+        let result = self.run_body(body, args, params);
+        self.steps += steps;
+        self.line = line;
+        result
+    }
+
+    fn member(&mut self, base: &Expr, name: &str, args: Option<&[Expr]>) -> RunResult<Value> {
+        let Expr::Var(var) = base else {
+            let value = self.eval(base)?;
+            return match name {
+                "size" => Ok(Value::Number(value.as_text().chars().count() as f64)),
+                _ => self.error(format!("unknown member .{}", name)),
+            };
+        };
+        let args = match args {
+            Some(args) => args.iter().map(|a| self.eval(a)).collect::<RunResult<Vec<Value>>>()?,
+            None => vec![],
+        };
+        let line = self.line;
+        let fail = |message: String| Err(RuntimeError { line, message });
+
+        let items = match self.slot_mut(&var.name) {
+            Some(Slot::Array(items)) => items,
+            Some(Slot::Scalar(value)) => {
+                return match name {
+                    "size" => Ok(Value::Number(value.as_text().chars().count() as f64)),
+                    _ => fail(format!("${} is not an array", var.name)),
+                };
+            }
+            None => return fail(format!("undefined variable ${}", var.name)),
+        };
+        let numbers = || items.iter().map(Value::as_number);
+        let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Number(0.0));
+
+        let result = match name {
+            "size" => Value::Number(items.len() as f64),
+            "min" => Value::Number(numbers().fold(f64::INFINITY, f64::min)),
+            "max" => Value::Number(numbers().fold(f64::NEG_INFINITY, f64::max)),
+            "sum" => Value::Number(numbers().sum()),
+            "avg" => Value::Number(if items.is_empty() { 0.0 } else { numbers().sum::<f64>() / items.len() as f64 }),
+            "last" => items.last().cloned().unwrap_or(Value::Number(0.0)),
+            "append" => {
+                items.push(arg(0));
+                Value::Number(0.0)
+            }
+            "pop" => items.pop().unwrap_or(Value::Number(0.0)),
+            "insert" => {
+                let index = (arg(0).as_number().max(0.0) as usize).min(items.len());
+                items.insert(index, arg(1));
+                Value::Number(0.0)
+            }
+            "erase" => {
+                let index = arg(0).as_number();
+                if index < 0.0 || index as usize >= items.len() {
+                    let size = items.len();
+                    return fail(format!("index {} out of bounds for ${} (size {})", index, var.name, size));
+                }
+                items.remove(index as usize);
+                Value::Number(0.0)
+            }
+            "clear" => {
+                items.clear();
+                Value::Number(0.0)
+            }
+            "fill" => {
+                *items = vec![arg(1); arg(0).as_number().max(0.0) as usize];
+                Value::Number(0.0)
+            }
+            "sort" | "sortd" => {
+                items.sort_by(|a, b| a.as_number().total_cmp(&b.as_number()));
+                if name == "sortd" {
+                    items.reverse();
+                }
+                Value::Number(0.0)
+            }
+            _ => return fail(format!("unknown member .{}", name)),
+        };
+        Ok(result)
+    }
+
+    fn builtin(&mut self, name: &str, args: Vec<Value>) -> RunResult<Value> {
+        let n = |i: usize| args.get(i).map(Value::as_number).unwrap_or(0.0);
+        let t = |i: usize| args.get(i).map(Value::as_text).unwrap_or_default();
+        let port = |i: usize| args.get(i).map(Value::as_text).unwrap_or_default();
+        let number = |v: f64| Ok(Value::Number(v));
+
+        match name {
+            "abs" => number(n(0).abs()),
+            "fract" => number(n(0).fract()),
+            "floor" => number(n(0).floor()),
+            "ceil" => number(n(0).ceil()),
+            "round" => number(n(0).round()),
+            "sign" => number(if n(0) > 0.0 { 1.0 } else if n(0) < 0.0 { -1.0 } else { 0.0 }),
+            "sqrt" => number(n(0).sqrt()),
+            "pow" => number(n(0).powf(n(1))),
+            "log" => number(if args.len() > 1 { n(0).log(n(1)) } else { n(0).ln() }),
+            "sin" => number(n(0).sin()),
+            "cos" => number(n(0).cos()),
+            "tan" => number(n(0).tan()),
+            "asin" => number(n(0).asin()),
+            "acos" => number(n(0).acos()),
+            "atan" => number(if args.len() > 1 { n(0).atan2(n(1)) } else { n(0).atan() }),
+            "min" => number(n(0).min(n(1))),
+            "max" => number(n(0).max(n(1))),
+            "avg" => number((n(0) + n(1)) / 2.0),
+            "clamp" => number(n(0).max(n(1)).min(n(2))),
+            "lerp" => number(n(0) + (n(1) - n(0)) * n(2)),
+            "step" => number(if n(1) < n(0) { 0.0 } else { 1.0 }),
+            "smoothstep" => {
+                let x = ((n(2) - n(0)) / (n(1) - n(0))).clamp(0.0, 1.0);
+                number(x * x * (3.0 - 2.0 * x))
+            }
+            "mod" => number(n(0) % n(1)),
+            "pi" => number(std::f64::consts::PI),
+            "text" => Ok(Value::Text(format_text(&t(0), &args[1.min(args.len())..]))),
+            "size" => number(t(0).chars().count() as f64),
+            "upper" => Ok(Value::Text(t(0).to_uppercase())),
+            "lower" => Ok(Value::Text(t(0).to_lowercase())),
+            "substring" => {
+                let start = n(1).max(0.0) as usize;
+                let length = if args.len() > 2 { n(2).max(0.0) as usize } else { usize::MAX };
+                Ok(Value::Text(t(0).chars().skip(start).take(length).collect()))
+            }
+            "find" => {
+                let haystack = t(0);
+                number(match haystack.find(&t(1)) {
+                    Some(i) => haystack[..i].chars().count() as f64,
+                    None => -1.0,
+                })
+            }
+            "isnumeric" => Ok(bool_value(t(0).trim().parse::<f64>().is_ok())),
+            "print" => {
+                let line: Vec<String> = args.iter().map(Value::as_text).collect();
+                self.io.prints.push(line.join(""));
+                number(0.0)
+            }
+            "input_number" => number(self.io.inputs.get(&(port(0), n(1) as u32)).map(Value::as_number).unwrap_or(0.0)),
+            "input_text" => Ok(Value::Text(self.io.inputs.get(&(port(0), n(1) as u32)).map(Value::as_text).unwrap_or_default())),
+            "output_number" => {
+                self.io.outputs.insert((port(0), n(1) as u32), Value::Number(n(2)));
+                number(0.0)
+            }
+            "output_text" => {
+                self.io.outputs.insert((port(0), n(1) as u32), Value::Text(t(2)));
+                number(0.0)
+            }
+            "time" => number(self.time),
+            "delta_time" => number(1.0 / self.tick_rate),
+            _ => self.error(format!("unknown built-in '{}'", name)),
+        }
+    }
+}
+
+fn binary(op: BinaryOp, lhs: Value, rhs: Value) -> Value {
+    let both_text = matches!((&lhs, &rhs), (Value::Text(_), Value::Text(_)));
+    match op {
+        BinaryOp::Eq if both_text => bool_value(lhs.as_text() == rhs.as_text()),
+        BinaryOp::Ne if both_text => bool_value(lhs.as_text() != rhs.as_text()),
+        BinaryOp::Eq => bool_value(lhs.as_number() == rhs.as_number()),
+        BinaryOp::Ne => bool_value(lhs.as_number() != rhs.as_number()),
+        BinaryOp::Lt => bool_value(lhs.as_number() < rhs.as_number()),
+        BinaryOp::Le => bool_value(lhs.as_number() <= rhs.as_number()),
+        BinaryOp::Gt => bool_value(lhs.as_number() > rhs.as_number()),
+        BinaryOp::Ge => bool_value(lhs.as_number() >= rhs.as_number()),
+        BinaryOp::And => bool_value(lhs.is_truthy() && rhs.is_truthy()),
+        BinaryOp::Or => bool_value(lhs.is_truthy() || rhs.is_truthy()),
+        BinaryOp::Xor => bool_value(lhs.is_truthy() != rhs.is_truthy()),
+        BinaryOp::Concat => Value::Text(lhs.as_text() + &rhs.as_text()),
+        BinaryOp::Add => Value::Number(lhs.as_number() + rhs.as_number()),
+        BinaryOp::Sub => Value::Number(lhs.as_number() - rhs.as_number()),
+        BinaryOp::Mul => Value::Number(lhs.as_number() * rhs.as_number()),
+        BinaryOp::Div => Value::Number(lhs.as_number() / rhs.as_number()),
+        BinaryOp::Mod => Value::Number(lhs.as_number() % rhs.as_number()),
+        BinaryOp::Pow => Value::Number(lhs.as_number().powf(rhs.as_number())),
+    }
+}
+
+/// Replace each `{}` in `format` with the next argument; `{0.00}` rounds to that many decimals.
+fn format_text(format: &str, args: &[Value]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut rest = format;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..open]);
+        let spec = &rest[open + 1..open + close];
+        let value = args.next().cloned().unwrap_or(Value::Text(String::new()));
+        match spec.split_once('.') {
+            Some((_, decimals)) if matches!(value, Value::Number(_)) => {
+                out.push_str(&format!("{:.*}", decimals.len(), value.as_number()));
+            }
+            _ => out.push_str(&value.as_text()),
+        }
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
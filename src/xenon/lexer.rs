@@ -0,0 +1,156 @@
+use super::ast::{Diagnostic, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// `$name`, stored without the sigil
+    Variable(String),
+    /// `@name`, stored without the sigil
+    Function(String),
+    /// keywords, built-ins, types and member names
+    Word(String),
+    Number(f64),
+    Text(String),
+    Symbol(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+impl Token {
+    pub fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(&self.kind, TokenKind::Symbol(s) if *s == symbol)
+    }
+
+    pub fn is_word(&self, word: &str) -> bool {
+        matches!(&self.kind, TokenKind::Word(w) if w == word)
+    }
+}
+
+/// A source line with its indentation; blank and comment-only lines have no tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub number: usize,
+    /// indentation width; a tab counts as four columns
+    pub indent: usize,
+    pub tokens: Vec<Token>,
+    pub comment: Option<String>,
+}
+
+// longest first so `+=` wins over `+`
+const SYMBOLS: &[&str] = &[
+    "==", "!=", "<>", "<=", ">=", "&&", "||", "++", "--",
+    "+=", "-=", "*=", "/=", "%=", "^=", "&=",
+    "+", "-", "*", "/", "%", "^", "&", "|", "!", "<", ">", "=", "(", ")", ",", ".", ":",
+];
+
+pub fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+pub fn tokenize(source: &str) -> (Vec<Line>, Vec<Diagnostic>) {
+    let mut diagnostics = vec![];
+    let lines = source.lines().enumerate().map(|(number, text)| {
+        let chars: Vec<char> = text.chars().collect();
+        let mut indent = 0;
+        let mut i = 0;
+        while i < chars.len() && (chars[i] == ' ' || chars[i] == '\t') {
+            indent += if chars[i] == '\t' { 4 } else { 1 };
+            i += 1;
+        }
+
+        let mut tokens: Vec<Token> = vec![];
+        let mut comment = None;
+        while i < chars.len() {
+            let c = chars[i];
+            let start = i;
+            if c == ' ' || c == '\t' {
+                i += 1;
+                continue;
+            }
+            if c == ';' {
+                comment = Some(chars[i + 1..].iter().collect::<String>().trim().to_string());
+                break;
+            }
+            let kind = if c == '$' || c == '@' {
+                i += 1;
+                while i < chars.len() && is_name_char(chars[i]) {
+                    i += 1;
+                }
+                let name: String = chars[start + 1..i].iter().collect();
+                if name.is_empty() {
+                    diagnostics.push(Diagnostic::new(Span::new(number, start, i), format!("expected a name after '{}'", c)));
+                }
+                if c == '$' { TokenKind::Variable(name) } else { TokenKind::Function(name) }
+            } else if c.is_ascii_digit() {
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                // `$array.1` is an index, not the start of a decimal
+                let after_dot = tokens.last().is_some_and(|t| t.is_symbol("."));
+                if !after_dot && i + 1 < chars.len() && chars[i] == '.' && chars[i + 1].is_ascii_digit() {
+                    i += 1;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+                let text: String = chars[start..i].iter().collect();
+                TokenKind::Number(text.parse().unwrap_or_default())
+            } else if c.is_alphabetic() || c == '_' {
+                while i < chars.len() && is_name_char(chars[i]) {
+                    i += 1;
+                }
+                TokenKind::Word(chars[start..i].iter().collect())
+            } else if c == '"' {
+                i += 1;
+                let mut text = String::new();
+                let mut closed = false;
+                while i < chars.len() {
+                    match chars[i] {
+                        '"' => {
+                            closed = true;
+                            i += 1;
+                            break;
+                        }
+                        '\\' if i + 1 < chars.len() => {
+                            text.push(match chars[i + 1] {
+                                'n' => '\n',
+                                't' => '\t',
+                                other => other,
+                            });
+                            i += 2;
+                        }
+                        other => {
+                            text.push(other);
+                            i += 1;
+                        }
+                    }
+                }
+                if !closed {
+                    diagnostics.push(Diagnostic::new(Span::new(number, start, i), "unterminated text"));
+                }
+                TokenKind::Text(text)
+            } else {
+                let rest: String = chars[i..].iter().take(2).collect();
+                match SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+                    Some(symbol) => {
+                        i += symbol.len();
+                        TokenKind::Symbol(symbol)
+                    }
+                    None => {
+                        diagnostics.push(Diagnostic::new(Span::new(number, start, start + 1), format!("unexpected character '{}'", c)));
+                        i += 1;
+                        continue;
+                    }
+                }
+            };
+            tokens.push(Token { kind, span: Span::new(number, start, i) });
+        }
+
+        Line { number, indent, tokens, comment }
+    }).collect();
+
+    (lines, diagnostics)
+}
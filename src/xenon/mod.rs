@@ -0,0 +1,10 @@
+pub(crate) mod ast;
+pub(crate) mod builtins;
+pub(crate) mod bundle;
+pub(crate) mod doc;
+pub(crate) mod include;
+pub(crate) mod interpreter;
+pub(crate) mod lexer;
+pub(crate) mod parser;
+pub(crate) mod rename;
+pub(crate) mod symbols;
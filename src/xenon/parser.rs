@@ -0,0 +1,628 @@
+use super::ast::*;
+use super::lexer::{tokenize, Line, Token, TokenKind};
+
+/// Result of parsing a XenonCode source; parsing always produces a program, with whatever could
+/// not be understood reported as diagnostics.
+#[derive(Debug, Clone, Default)]
+pub struct Parsed {
+    pub program: Program,
+    pub diagnostics: Vec<Diagnostic>,
+    pub lines: Vec<Line>,
+}
+
+pub fn parse(source: &str) -> Parsed {
+    let (lines, diagnostics) = tokenize(source);
+    let mut parser = Parser {
+        lines: lines.iter().filter(|l| !l.tokens.is_empty()).cloned().collect(),
+        pos: 0,
+        diagnostics,
+    };
+    let program = parser.program();
+    Parsed {
+        program,
+        diagnostics: parser.diagnostics,
+        lines,
+    }
+}
+
+type ParseResult<T> = Result<T, Diagnostic>;
+
+struct Parser {
+    lines: Vec<Line>,
+    pos: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Parser {
+    fn program(&mut self) -> Program {
+        let mut program = Program::default();
+        while self.pos < self.lines.len() {
+            let line = self.lines[self.pos].clone();
+            self.pos += 1;
+            if line.indent != 0 {
+                self.diagnostics.push(Diagnostic::new(line.tokens[0].span, "unexpected indentation"));
+                continue;
+            }
+            let mut cursor = Cursor::new(&line);
+            match self.item(&mut cursor, &line) {
+                Ok(Some(kind)) => program.items.push(Item { kind, line: line.number }),
+                Ok(None) => {}
+                Err(e) => self.diagnostics.push(e),
+            }
+        }
+        program
+    }
+
+    fn item(&mut self, cursor: &mut Cursor, line: &Line) -> ParseResult<Option<ItemKind>> {
+        let first = cursor.next().unwrap();
+        let item = match &first.kind {
+            TokenKind::Word(w) if w == "include" => {
+                let token = cursor.next().ok_or_else(|| cursor.error("expected a file name"))?;
+                let TokenKind::Text(path) = &token.kind else {
+                    return Err(Diagnostic::new(token.span, "expected a quoted file name"));
+                };
+                ItemKind::Include { path: path.clone(), span: token.span }
+            }
+            TokenKind::Word(w) if w == "const" => {
+                let name = cursor.variable()?;
+                cursor.expect_symbol("=")?;
+                let value = cursor.expr()?;
+                ItemKind::Const { name, value }
+            }
+            TokenKind::Word(w) if w == "var" || w == "array" || w == "storage" => {
+                ItemKind::Var(cursor.var_decl(w)?)
+            }
+            TokenKind::Word(w) if w == "function" => {
+                let header = cursor.function_header();
+                // keep the body out of the top level even when the header is broken
+                let body = self.block(line.indent);
+                let (name, params, ret) = header?;
+                return Ok(Some(ItemKind::Function { name, params, ret, body }));
+            }
+            TokenKind::Word(w) if matches!(w.as_str(), "init" | "tick" | "shutdown" | "timer" | "input") => {
+                let entry = cursor.entry(w);
+                let body = self.block(line.indent);
+                return Ok(Some(ItemKind::Entry { entry: entry?, body }));
+            }
+            _ => {
+                self.block(line.indent);
+                return Err(Diagnostic::new(first.span, "expected a declaration, function or entry point"));
+            }
+        };
+        cursor.expect_end()?;
+        self.no_block(line.indent);
+        Ok(Some(item))
+    }
+
+    /// Report and skip lines indented under a line that doesn't take a body.
+    fn no_block(&mut self, indent: usize) {
+        while self.pos < self.lines.len() && self.lines[self.pos].indent > indent {
+            self.diagnostics.push(Diagnostic::new(self.lines[self.pos].tokens[0].span, "unexpected indentation"));
+            self.pos += 1;
+        }
+    }
+
+    fn block(&mut self, parent_indent: usize) -> Block {
+        let mut block = vec![];
+        let mut block_indent = None;
+        while self.pos < self.lines.len() {
+            let line = self.lines[self.pos].clone();
+            if line.indent <= parent_indent {
+                break;
+            }
+            let indent = *block_indent.get_or_insert(line.indent);
+            if line.indent != indent {
+                self.diagnostics.push(Diagnostic::new(line.tokens[0].span, "inconsistent indentation"));
+            }
+            self.pos += 1;
+            match self.stmt(&line) {
+                Ok(Some(stmt)) => block.push(stmt),
+                Ok(None) => {}
+                Err(e) => self.diagnostics.push(e),
+            }
+        }
+        if block_indent.is_none() {
+            // an empty body is legal, but usually a mistake in indentation
+            let line = &self.lines[self.pos.saturating_sub(1)];
+            if let Some(last) = line.tokens.last() {
+                self.diagnostics.push(Diagnostic::new(last.span, "expected an indented block"));
+            }
+        }
+        block
+    }
+
+    fn stmt(&mut self, line: &Line) -> ParseResult<Option<Stmt>> {
+        let mut cursor = Cursor::new(line);
+        let first = cursor.peek().unwrap().clone();
+        let kind = match &first.kind {
+            TokenKind::Word(w) if w == "var" || w == "array" || w == "storage" => {
+                cursor.next();
+                StmtKind::Var(cursor.var_decl(w)?)
+            }
+            TokenKind::Word(w) if w == "if" => {
+                cursor.next();
+                let cond = cursor.expr();
+                let end = cursor.expect_end();
+                let body = self.block(line.indent);
+                let mut branches = vec![(cond?, body)];
+                end?;
+                let mut otherwise = None;
+                while self.pos < self.lines.len() && self.lines[self.pos].indent == line.indent {
+                    let next = self.lines[self.pos].clone();
+                    let mut branch = Cursor::new(&next);
+                    if branch.eat_word("elseif") {
+                        self.pos += 1;
+                        let cond = branch.expr();
+                        let end = branch.expect_end();
+                        let body = self.block(next.indent);
+                        branches.push((cond?, body));
+                        end?;
+                    } else if branch.eat_word("else") {
+                        self.pos += 1;
+                        let end = branch.expect_end();
+                        otherwise = Some(self.block(next.indent));
+                        end?;
+                        break;
+                    } else {
+                        break;
+                    }
+                }
+                return Ok(Some(Stmt { kind: StmtKind::If { branches, otherwise }, line: line.number }));
+            }
+            TokenKind::Word(w) if w == "elseif" || w == "else" => {
+                self.block(line.indent);
+                return Err(Diagnostic::new(first.span, format!("'{}' without a matching 'if'", w)));
+            }
+            TokenKind::Word(w) if w == "while" => {
+                cursor.next();
+                let cond = cursor.expr().and_then(|c| cursor.expect_end().map(|_| c));
+                let body = self.block(line.indent);
+                StmtKind::While { cond: cond?, body }
+            }
+            TokenKind::Word(w) if w == "foreach" => {
+                cursor.next();
+                let header = cursor.foreach_header();
+                let body = self.block(line.indent);
+                let (array, index, item) = header?;
+                StmtKind::Foreach { array, index, item, body }
+            }
+            TokenKind::Word(w) if w == "repeat" => {
+                cursor.next();
+                let header = cursor.expr().and_then(|count| Ok((count, cursor.loop_var()?)));
+                let body = self.block(line.indent);
+                let (count, var) = header?;
+                StmtKind::Repeat { count, var, body }
+            }
+            TokenKind::Word(w) if w == "for" => {
+                cursor.next();
+                let header = cursor.expr().and_then(|from| {
+                    cursor.expect_symbol(",")?;
+                    let to = cursor.expr()?;
+                    Ok((from, to, cursor.loop_var()?))
+                });
+                let body = self.block(line.indent);
+                let (from, to, var) = header?;
+                StmtKind::For { from, to, var, body }
+            }
+            TokenKind::Word(w) if w == "break" || w == "continue" => {
+                cursor.next();
+                if w == "break" { StmtKind::Break } else { StmtKind::Continue }
+            }
+            TokenKind::Word(w) if w == "return" => {
+                cursor.next();
+                let value = if cursor.peek().is_some() { Some(cursor.expr()?) } else { None };
+                StmtKind::Return(value)
+            }
+            TokenKind::Word(w) if w == "output" && cursor.peek_at(1).is_some_and(|t| t.is_symbol(".")) => {
+                cursor.next();
+                let port = cursor.port()?;
+                let args = cursor.args()?;
+                StmtKind::Output { port, args }
+            }
+            TokenKind::Word(w) if w == "const" || w == "function" || w == "include" => {
+                self.no_block(line.indent);
+                return Err(Diagnostic::new(first.span, format!("'{}' is only allowed at the top level", w)));
+            }
+            _ => {
+                let target = cursor.expr()?;
+                if let Some(op) = cursor.assign_op() {
+                    if !target.is_place() {
+                        return Err(Diagnostic::new(first.span, "can only assign to a variable"));
+                    }
+                    let value = cursor.expr()?;
+                    StmtKind::Assign { target, op, value }
+                } else if cursor.eat_symbol("++") {
+                    StmtKind::Increment { target, delta: 1.0 }
+                } else if cursor.eat_symbol("--") {
+                    StmtKind::Increment { target, delta: -1.0 }
+                } else if target.is_call() {
+                    StmtKind::Expr(target)
+                } else {
+                    return Err(Diagnostic::new(first.span, "expected a statement"));
+                }
+            }
+        };
+        let end = cursor.expect_end();
+        if !kind.has_body() {
+            self.no_block(line.indent);
+        }
+        end?;
+        Ok(Some(Stmt { kind, line: line.number }))
+    }
+}
+
+impl StmtKind {
+    fn has_body(&self) -> bool {
+        matches!(self, StmtKind::If { .. } | StmtKind::While { .. } | StmtKind::Foreach { .. } | StmtKind::Repeat { .. } | StmtKind::For { .. })
+    }
+}
+
+impl Expr {
+    fn is_place(&self) -> bool {
+        match self {
+            Expr::Var(_) => true,
+            Expr::Index { base, .. } => base.is_place(),
+            _ => false,
+        }
+    }
+
+    fn is_call(&self) -> bool {
+        matches!(self, Expr::Call { .. } | Expr::Builtin { .. } | Expr::Member { args: Some(_), .. })
+    }
+}
+
+struct Cursor<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(line: &'a Line) -> Self {
+        Cursor { tokens: &line.tokens, pos: 0, line: line.number }
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&'a Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn error(&self, message: &str) -> Diagnostic {
+        let span = match self.peek() {
+            Some(token) => token.span,
+            None => self.tokens.last().map(|t| Span::new(self.line, t.span.end, t.span.end + 1)).unwrap_or_default(),
+        };
+        Diagnostic::new(span, message)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        if self.peek().is_some_and(|t| t.is_symbol(symbol)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        if self.peek().is_some_and(|t| t.is_word(word)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> ParseResult<()> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", symbol)))
+        }
+    }
+
+    fn expect_end(&self) -> ParseResult<()> {
+        match self.peek() {
+            None => Ok(()),
+            Some(token) => Err(Diagnostic::new(token.span, "unexpected token")),
+        }
+    }
+
+    fn variable(&mut self) -> ParseResult<Ident> {
+        match self.peek() {
+            Some(Token { kind: TokenKind::Variable(name), span }) => {
+                self.pos += 1;
+                Ok(Ident { name: name.clone(), span: *span })
+            }
+            _ => Err(self.error("expected a variable name starting with '$'")),
+        }
+    }
+
+    fn number(&mut self) -> ParseResult<f64> {
+        match self.peek() {
+            Some(Token { kind: TokenKind::Number(n), .. }) => {
+                self.pos += 1;
+                Ok(*n)
+            }
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    fn function_header(&mut self) -> ParseResult<(Ident, Vec<Param>, Option<Type>)> {
+        let token = self.next().ok_or_else(|| self.error("expected a function name"))?;
+        let TokenKind::Function(name) = &token.kind else {
+            return Err(Diagnostic::new(token.span, "function names start with '@'"));
+        };
+        let name = Ident { name: name.clone(), span: token.span };
+        let params = self.params()?;
+        let ret = if self.eat_symbol(":") { Some(self.ty()?) } else { None };
+        self.expect_end()?;
+        Ok((name, params, ret))
+    }
+
+    /// Everything after the first word of an entry point.
+    fn entry(&mut self, keyword: &str) -> ParseResult<Entry> {
+        let entry = match keyword {
+            "init" => Entry::Init,
+            "tick" => Entry::Tick,
+            "shutdown" => Entry::Shutdown,
+            "timer" => {
+                let kind = self.next().ok_or_else(|| self.error("expected 'frequency' or 'interval'"))?;
+                let value = self.number()?;
+                if kind.is_word("frequency") {
+                    Entry::TimerFrequency(value)
+                } else if kind.is_word("interval") {
+                    Entry::TimerInterval(value)
+                } else {
+                    return Err(Diagnostic::new(kind.span, "expected 'frequency' or 'interval'"));
+                }
+            }
+            _ => {
+                let port = self.port()?;
+                let params = if self.peek().is_some() { self.params()? } else { vec![] };
+                Entry::Input { port, params }
+            }
+        };
+        self.expect_end()?;
+        Ok(entry)
+    }
+
+    /// `.N` after `input` or `output`
+    fn port(&mut self) -> ParseResult<u32> {
+        self.expect_symbol(".")?;
+        let port = self.number()?;
+        Ok(port as u32)
+    }
+
+    fn ty(&mut self) -> ParseResult<Type> {
+        if self.eat_word("number") {
+            Ok(Type::Number)
+        } else if self.eat_word("text") {
+            Ok(Type::Text)
+        } else {
+            Err(self.error("expected 'number' or 'text'"))
+        }
+    }
+
+    /// Everything after `var`, `array` or `storage`.
+    fn var_decl(&mut self, keyword: &str) -> ParseResult<VarDecl> {
+        let storage = keyword == "storage";
+        let array = if storage {
+            if self.eat_word("array") {
+                true
+            } else if self.eat_word("var") {
+                false
+            } else {
+                return Err(self.error("expected 'var' or 'array'"));
+            }
+        } else {
+            keyword == "array"
+        };
+        let name = self.variable()?;
+        let ty = if self.eat_symbol(":") { Some(self.ty()?) } else { None };
+        let value = if !array && self.eat_symbol("=") { Some(self.expr()?) } else { None };
+        Ok(VarDecl { name, storage, array, ty, value })
+    }
+
+    fn params(&mut self) -> ParseResult<Vec<Param>> {
+        self.expect_symbol("(")?;
+        let mut params = vec![];
+        if self.eat_symbol(")") {
+            return Ok(params);
+        }
+        loop {
+            let name = self.variable()?;
+            let ty = if self.eat_symbol(":") { Some(self.ty()?) } else { None };
+            params.push(Param { name, ty });
+            if self.eat_symbol(")") {
+                return Ok(params);
+            }
+            self.expect_symbol(",")?;
+        }
+    }
+
+    fn args(&mut self) -> ParseResult<Vec<Expr>> {
+        self.expect_symbol("(")?;
+        let mut args = vec![];
+        if self.eat_symbol(")") {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expr()?);
+            if self.eat_symbol(")") {
+                return Ok(args);
+            }
+            self.expect_symbol(",")?;
+        }
+    }
+
+    /// optional `($var)` after `repeat` and `for`
+    fn loop_var(&mut self) -> ParseResult<Option<Ident>> {
+        if !self.eat_symbol("(") {
+            return Ok(None);
+        }
+        let var = self.variable()?;
+        self.expect_symbol(")")?;
+        Ok(Some(var))
+    }
+
+    fn foreach_header(&mut self) -> ParseResult<(Expr, Option<Ident>, Ident)> {
+        let array = self.expr()?;
+        self.expect_symbol("(")?;
+        let first = self.variable()?;
+        let (index, item) = if self.eat_symbol(",") {
+            (Some(first), self.variable()?)
+        } else {
+            (None, first)
+        };
+        self.expect_symbol(")")?;
+        Ok((array, index, item))
+    }
+
+    fn assign_op(&mut self) -> Option<AssignOp> {
+        let op = match self.peek()?.kind {
+            TokenKind::Symbol("=") => AssignOp::Set,
+            TokenKind::Symbol("+=") => AssignOp::Add,
+            TokenKind::Symbol("-=") => AssignOp::Sub,
+            TokenKind::Symbol("*=") => AssignOp::Mul,
+            TokenKind::Symbol("/=") => AssignOp::Div,
+            TokenKind::Symbol("%=") => AssignOp::Mod,
+            TokenKind::Symbol("^=") => AssignOp::Pow,
+            TokenKind::Symbol("&=") => AssignOp::Concat,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(op)
+    }
+
+    fn expr(&mut self) -> ParseResult<Expr> {
+        self.binary(0)
+    }
+
+    fn binary_op(&self, level: usize) -> Option<BinaryOp> {
+        let token = self.peek()?;
+        let op = match (&token.kind, level) {
+            (TokenKind::Word(w), 0) if w == "or" => BinaryOp::Or,
+            (TokenKind::Word(w), 0) if w == "xor" => BinaryOp::Xor,
+            (TokenKind::Symbol("||"), 0) => BinaryOp::Or,
+            (TokenKind::Word(w), 1) if w == "and" => BinaryOp::And,
+            (TokenKind::Symbol("&&"), 1) => BinaryOp::And,
+            (TokenKind::Symbol("=="), 2) => BinaryOp::Eq,
+            (TokenKind::Symbol("!=") | TokenKind::Symbol("<>"), 2) => BinaryOp::Ne,
+            (TokenKind::Symbol("<"), 2) => BinaryOp::Lt,
+            (TokenKind::Symbol("<="), 2) => BinaryOp::Le,
+            (TokenKind::Symbol(">"), 2) => BinaryOp::Gt,
+            (TokenKind::Symbol(">="), 2) => BinaryOp::Ge,
+            (TokenKind::Symbol("&"), 3) => BinaryOp::Concat,
+            (TokenKind::Symbol("+"), 4) => BinaryOp::Add,
+            (TokenKind::Symbol("-"), 4) => BinaryOp::Sub,
+            (TokenKind::Symbol("*"), 5) => BinaryOp::Mul,
+            (TokenKind::Symbol("/"), 5) => BinaryOp::Div,
+            (TokenKind::Symbol("%"), 5) => BinaryOp::Mod,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    fn binary(&mut self, level: usize) -> ParseResult<Expr> {
+        if level > 5 {
+            return self.unary();
+        }
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(op) = self.binary_op(level) {
+            self.pos += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary { op, lhs: Box::new(lhs), rhs: Box::new(rhs) };
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> ParseResult<Expr> {
+        if self.eat_symbol("-") {
+            return Ok(Expr::Unary { op: UnaryOp::Neg, expr: Box::new(self.unary()?) });
+        }
+        if self.eat_symbol("!") || self.eat_word("not") {
+            return Ok(Expr::Unary { op: UnaryOp::Not, expr: Box::new(self.unary()?) });
+        }
+        let base = self.postfix()?;
+        if self.eat_symbol("^") {
+            let exponent = self.unary()?;
+            return Ok(Expr::Binary { op: BinaryOp::Pow, lhs: Box::new(base), rhs: Box::new(exponent) });
+        }
+        Ok(base)
+    }
+
+    fn postfix(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.primary()?;
+        while self.eat_symbol(".") {
+            let token = self.next().ok_or_else(|| self.error("expected an index or member name"))?;
+            expr = match &token.kind {
+                TokenKind::Number(n) => Expr::Index { base: Box::new(expr), index: Box::new(Expr::Number(*n)) },
+                TokenKind::Variable(name) => Expr::Index {
+                    base: Box::new(expr),
+                    index: Box::new(Expr::Var(Ident { name: name.clone(), span: token.span })),
+                },
+                TokenKind::Word(name) => {
+                    let name = Ident { name: name.clone(), span: token.span };
+                    let args = if self.peek().is_some_and(|t| t.is_symbol("(")) { Some(self.args()?) } else { None };
+                    Expr::Member { base: Box::new(expr), name, args }
+                }
+                _ => return Err(Diagnostic::new(token.span, "expected an index or member name")),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> ParseResult<Expr> {
+        let token = self.next().ok_or_else(|| self.error("expected an expression"))?;
+        match &token.kind {
+            TokenKind::Number(n) => Ok(Expr::Number(*n)),
+            TokenKind::Text(t) => Ok(Expr::Text(t.clone())),
+            TokenKind::Variable(name) => Ok(Expr::Var(Ident { name: name.clone(), span: token.span })),
+            TokenKind::Function(name) => {
+                let name = Ident { name: name.clone(), span: token.span };
+                Ok(Expr::Call { name, args: self.args()? })
+            }
+            TokenKind::Word(name) => {
+                let name = Ident { name: name.clone(), span: token.span };
+                let args = if self.peek().is_some_and(|t| t.is_symbol("(")) { self.args()? } else { vec![] };
+                Ok(Expr::Builtin { name, args })
+            }
+            TokenKind::Symbol("(") => {
+                let expr = self.expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            _ => Err(Diagnostic::new(token.span, "expected an expression")),
+        }
+    }
+}
+
+impl Parsed {
+    /// The block of comment-only lines directly above `line`, joined with newlines.
+    pub fn doc_comment(&self, line: usize) -> Option<String> {
+        let mut comments = vec![];
+        let mut current = line;
+        while current > 0 {
+            current -= 1;
+            match self.lines.get(current) {
+                Some(Line { tokens, comment: Some(comment), .. }) if tokens.is_empty() => comments.push(comment.clone()),
+                _ => break,
+            }
+        }
+        if comments.is_empty() {
+            return None;
+        }
+        comments.reverse();
+        Some(comments.join("\n"))
+    }
+}
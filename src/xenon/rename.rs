@@ -0,0 +1,47 @@
+use super::ast::Span;
+use super::lexer::is_name_char;
+use super::parser::parse;
+use super::symbols::analyze;
+
+/// Whether `name` can be written after `$` or `@`.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(is_name_char)
+}
+
+/// Spans of every declaration of and reference to `$name` (or `@name` for functions), in source
+/// order. Comments, text and built-ins of the same name are not included.
+pub fn occurrences(source: &str, name: &str, function: bool) -> Vec<Span> {
+    let symbols = analyze(&parse(source).program);
+    let mut spans: Vec<Span> = symbols.definitions.iter()
+        .filter(|s| s.name == name && s.is_function() == function)
+        .map(|s| s.span)
+        .chain(symbols.references.iter()
+            .filter(|r| r.name == name && r.function == function)
+            .map(|r| r.span))
+        .collect();
+    spans.sort();
+    spans.dedup();
+    spans
+}
+
+/// Rename `$from` (or `@from`) to `to` everywhere in `source`, leaving everything else untouched.
+///
+/// Returns the new source and the number of occurrences renamed.
+pub fn rename(source: &str, from: &str, to: &str, function: bool) -> (String, usize) {
+    let spans = occurrences(source, from, function);
+    if spans.is_empty() {
+        return (source.to_string(), 0);
+    }
+
+    // spans count characters of `str::lines`, so rebuild each line and keep its line ending
+    let mut renamed = String::with_capacity(source.len());
+    for (number, line) in source.split_inclusive('\n').enumerate() {
+        let mut chars: Vec<char> = line.chars().collect();
+        // right to left so earlier columns stay valid
+        for span in spans.iter().rev().filter(|s| s.line == number) {
+            chars.splice(span.start + 1..span.end, to.chars());
+        }
+        renamed.extend(chars);
+    }
+    (renamed, spans.len())
+}
@@ -0,0 +1,249 @@
+use super::ast::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Const,
+    Variable,
+    Array,
+    Parameter,
+    Local,
+}
+
+/// A name declared in a source file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub span: Span,
+    /// the declaration as it would be written, e.g. `function @pid($v : number) : number`
+    pub detail: String,
+    /// locals and parameters are only visible inside the item at this index
+    pub item: Option<usize>,
+}
+
+impl Symbol {
+    pub fn is_global(&self) -> bool {
+        self.item.is_none()
+    }
+
+    pub fn is_function(&self) -> bool {
+        self.kind == SymbolKind::Function
+    }
+}
+
+/// A use of a `$variable` or `@function`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub name: String,
+    pub function: bool,
+    pub span: Span,
+    /// index of the local symbol this resolves to; `None` means it refers to a global, which may
+    /// live in another file of the same HDD
+    pub local: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Symbols {
+    pub definitions: Vec<Symbol>,
+    pub references: Vec<Reference>,
+    /// built-in function calls, for hover
+    pub builtins: Vec<Ident>,
+}
+
+impl Symbols {
+    pub fn globals(&self) -> impl Iterator<Item = &Symbol> {
+        self.definitions.iter().filter(|s| s.is_global())
+    }
+
+    /// The definition or reference under the cursor.
+    pub fn at(&self, line: usize, column: usize) -> Option<Occurrence<'_>> {
+        if let Some(symbol) = self.definitions.iter().find(|s| s.span.contains(line, column)) {
+            return Some(Occurrence::Definition(symbol));
+        }
+        self.references.iter()
+            .find(|r| r.span.contains(line, column))
+            .map(Occurrence::Reference)
+    }
+}
+
+pub enum Occurrence<'a> {
+    Definition(&'a Symbol),
+    Reference(&'a Reference),
+}
+
+fn type_name(ty: &Option<Type>) -> &'static str {
+    match ty {
+        Some(Type::Text) => " : text",
+        Some(Type::Number) => " : number",
+        None => "",
+    }
+}
+
+fn var_detail(decl: &VarDecl) -> String {
+    format!("{}{} ${}{}",
+            if decl.storage { "storage " } else { "" },
+            if decl.array { "array" } else { "var" },
+            decl.name.name,
+            type_name(&decl.ty))
+}
+
+pub fn function_detail(name: &Ident, params: &[Param], ret: &Option<Type>) -> String {
+    let params: Vec<String> = params.iter().map(|p| format!("${}{}", p.name.name, type_name(&p.ty))).collect();
+    format!("function @{}({}){}", name.name, params.join(", "), type_name(ret))
+}
+
+struct Analyzer {
+    symbols: Symbols,
+    /// visible locals, innermost last; cleared for every item
+    scopes: Vec<Vec<usize>>,
+    item: usize,
+}
+
+impl Analyzer {
+    fn declare(&mut self, name: &Ident, kind: SymbolKind, detail: String) {
+        self.symbols.definitions.push(Symbol {
+            name: name.name.clone(),
+            kind,
+            span: name.span,
+            detail,
+            item: Some(self.item),
+        });
+        let index = self.symbols.definitions.len() - 1;
+        self.scopes.last_mut().unwrap().push(index);
+    }
+
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.scopes.iter().rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|i| self.symbols.definitions[**i].name == name)
+            .copied()
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        let mut found: Vec<(Ident, bool)> = vec![];
+        let mut builtins: Vec<Ident> = vec![];
+        expr.walk(&mut |e| match e {
+            Expr::Var(ident) => found.push((ident.clone(), false)),
+            Expr::Call { name, .. } => found.push((name.clone(), true)),
+            Expr::Builtin { name, .. } => builtins.push(name.clone()),
+            _ => {}
+        });
+        for (ident, function) in found {
+            let local = if function { None } else { self.lookup(&ident.name) };
+            self.symbols.references.push(Reference { name: ident.name, function, span: ident.span, local });
+        }
+        self.symbols.builtins.extend(builtins);
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.scopes.push(vec![]);
+        block.iter().for_each(|s| self.stmt(s));
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Var(decl) => {
+                decl.value.iter().for_each(|e| self.expr(e));
+                self.declare(&decl.name, SymbolKind::Local, var_detail(decl));
+            }
+            StmtKind::Assign { target, value, .. } => {
+                self.expr(target);
+                self.expr(value);
+            }
+            StmtKind::Increment { target, .. } => self.expr(target),
+            StmtKind::Expr(e) => self.expr(e),
+            StmtKind::Output { args, .. } => args.iter().for_each(|e| self.expr(e)),
+            StmtKind::If { branches, otherwise } => {
+                for (cond, body) in branches {
+                    self.expr(cond);
+                    self.block(body);
+                }
+                if let Some(body) = otherwise {
+                    self.block(body);
+                }
+            }
+            StmtKind::While { cond, body } => {
+                self.expr(cond);
+                self.block(body);
+            }
+            StmtKind::Foreach { array, index, item, body } => {
+                self.expr(array);
+                self.scopes.push(vec![]);
+                if let Some(index) = index {
+                    self.declare(index, SymbolKind::Local, format!("var ${} : number", index.name));
+                }
+                self.declare(item, SymbolKind::Local, format!("var ${}", item.name));
+                self.block(body);
+                self.scopes.pop();
+            }
+            StmtKind::Repeat { count, var, body } => {
+                self.expr(count);
+                self.loop_body(var, body);
+            }
+            StmtKind::For { from, to, var, body } => {
+                self.expr(from);
+                self.expr(to);
+                self.loop_body(var, body);
+            }
+            StmtKind::Return(value) => value.iter().for_each(|e| self.expr(e)),
+            StmtKind::Break | StmtKind::Continue => {}
+        }
+    }
+
+    fn loop_body(&mut self, var: &Option<Ident>, body: &Block) {
+        self.scopes.push(vec![]);
+        if let Some(var) = var {
+            self.declare(var, SymbolKind::Local, format!("var ${} : number", var.name));
+        }
+        self.block(body);
+        self.scopes.pop();
+    }
+
+    fn params(&mut self, params: &[Param]) {
+        for param in params {
+            self.declare(&param.name, SymbolKind::Parameter, format!("${}{}", param.name.name, type_name(&param.ty)));
+        }
+    }
+}
+
+pub fn analyze(program: &Program) -> Symbols {
+    let mut analyzer = Analyzer { symbols: Symbols::default(), scopes: vec![], item: 0 };
+
+    // globals first, so references can tell them apart from locals regardless of order
+    for item in &program.items {
+        let (name, kind, detail) = match &item.kind {
+            ItemKind::Const { name, .. } => (name, SymbolKind::Const, format!("const ${}", name.name)),
+            ItemKind::Var(decl) => {
+                let kind = if decl.array { SymbolKind::Array } else { SymbolKind::Variable };
+                (&decl.name, kind, var_detail(decl))
+            }
+            ItemKind::Function { name, params, ret, .. } => (name, SymbolKind::Function, function_detail(name, params, ret)),
+            _ => continue,
+        };
+        analyzer.symbols.definitions.push(Symbol { name: name.name.clone(), kind, span: name.span, detail, item: None });
+    }
+
+    for (index, item) in program.items.iter().enumerate() {
+        analyzer.item = index;
+        analyzer.scopes = vec![vec![]];
+        match &item.kind {
+            ItemKind::Const { value, .. } => analyzer.expr(value),
+            ItemKind::Var(decl) => decl.value.iter().for_each(|e| analyzer.expr(e)),
+            ItemKind::Function { params, body, .. } => {
+                analyzer.params(params);
+                analyzer.block(body);
+            }
+            ItemKind::Entry { entry, body } => {
+                if let Entry::Input { params, .. } = entry {
+                    analyzer.params(params);
+                }
+                analyzer.block(body);
+            }
+            ItemKind::Include { .. } => {}
+        }
+    }
+
+    analyzer.symbols
+}
@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use serde_json::{json, Value};
+
+fn archbelt(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_archbelt")).args(args).output().expect("could not run archbelt")
+}
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("archbelt-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn computer(alias: &str, x: i64, files: &[(&str, &str)]) -> Value {
+    json!({
+        "alias": alias,
+        "colors": [4286611584u32, 4294967295u32],
+        "data": {
+            "version": 1,
+            "hdd": {
+                "capacity": 65536,
+                "label": "",
+                "xc_files": files.iter().map(|(name, code)| json!({ "name": name, "plain_code": code })).collect::<Vec<_>>()
+            }
+        },
+        "module": "Computer",
+        "occupancies": [{ "frame_x": 0, "frame_y": 0, "frame_z": 0, "pos_x": x, "pos_y": 0, "pos_z": 0, "size_x": 2, "size_y": 1, "size_z": 2 }],
+        "orientation": { "w": 1.0, "x": 0.0, "y": 0.0, "z": 0.0 },
+        "position": { "x": x as f64 * 0.25, "y": 0.0, "z": 0.0 },
+        "type": "ARCHEAN_computer"
+    })
+}
+
+fn write_blueprint(dir: &Path, name: &str, components: Vec<Value>) {
+    let blueprint = json!({
+        "author": "Tester",
+        "box_max": { "x": 2.0, "y": 0.25, "z": 0.5 },
+        "box_min": { "x": 0.0, "y": 0.0, "z": 0.0 },
+        "box_size": { "x": 2.0, "y": 0.25, "z": 0.5 },
+        "data": {
+            "blocks": [],
+            "components": components,
+            "composite_builds": [],
+            "doors": [],
+            "frames": [],
+            "labels": [],
+            "pipes": [],
+            "symmetry_axis": 0,
+            "version": 3
+        },
+        "datetime": "2024-07-20 12:00:00",
+        "mass": 0.0,
+        "type": "blueprint",
+        "version": 3
+    });
+    std::fs::write(dir.join(format!("{}.json", name)), blueprint.to_string()).unwrap();
+}
+
+#[test]
+fn tests_a_yanked_computer_with_a_shared_include() {
+    let dir = scratch("shared-include");
+    let lib = "function @answer() : number\n\treturn 42\n";
+    write_blueprint(&dir, "Ship", vec![
+        computer("Alpha", 0, &[("main.xc", "include \"lib.xc\"\nvar $x = 0\ninit\n\t$x = @answer()\n"), ("lib.xc", lib)]),
+        computer("Beta", 4, &[("main.xc", "include \"lib.xc\"\nvar $y = 0\n"), ("lib.xc", lib)]),
+    ]);
+    let out = dir.join("out");
+    let yanked = archbelt(&["yank", "--out", out.to_str().unwrap(), "--blueprint-path", dir.to_str().unwrap(), "Ship"]);
+    assert!(yanked.status.success(), "{}", String::from_utf8_lossy(&yanked.stderr));
+    assert!(out.join("_shared").join("lib.xc").is_file());
+    assert!(!out.join("Alpha").join("lib.xc").exists());
+
+    std::fs::write(out.join("Alpha").join("main.test.json"),
+                   r#"{ "cases": [{ "name": "answer", "expect": [{ "vars": { "x": 42 } }] }] }"#).unwrap();
+    let tested = archbelt(&["test", out.join("Alpha").to_str().unwrap()]);
+    let stdout = String::from_utf8_lossy(&tested.stdout);
+    assert!(tested.status.success(), "{}", stdout);
+    assert!(stdout.contains("1 passed, 0 failed"), "{}", stdout);
+    std::fs::remove_dir_all(&dir).unwrap();
+}